@@ -0,0 +1,10 @@
+-- users
+
+ALTER TABLE users ADD COLUMN deletion_requested_at TIMESTAMP WITHOUT TIME ZONE;
+ALTER TABLE users ADD COLUMN deletion_scheduled_at TIMESTAMP WITHOUT TIME ZONE;
+CREATE INDEX users_deletion_scheduled_at_index ON users(deletion_scheduled_at);
+
+-- image_convert_jobs
+
+ALTER TABLE image_convert_jobs ADD COLUMN user_id VARCHAR(255);
+CREATE INDEX image_convert_jobs_user_id_index ON image_convert_jobs(user_id);
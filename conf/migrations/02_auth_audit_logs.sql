@@ -0,0 +1,22 @@
+-- auth_audit_logs
+
+CREATE TYPE auth_audit_event_type AS ENUM ('Register', 'Confirm', 'Login', 'Refresh', 'TokenDelete', 'Unregister', 'RoleChange', 'PasswordChange');
+CREATE TYPE auth_audit_auth_method AS ENUM ('Email', 'Google', 'RefreshToken', 'AccessToken');
+CREATE TYPE auth_audit_outcome AS ENUM ('Success', 'Failure');
+
+CREATE TABLE auth_audit_logs (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    user_id VARCHAR(255),
+    event_type auth_audit_event_type NOT NULL,
+    auth_method auth_audit_auth_method,
+    outcome auth_audit_outcome NOT NULL,
+    ip_address VARCHAR(255),
+    user_agent VARCHAR(1024),
+    detail TEXT,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX auth_audit_logs_user_id_created_at_index ON auth_audit_logs(user_id, created_at);
+CREATE INDEX auth_audit_logs_created_at_index ON auth_audit_logs(created_at);
+
+CREATE RULE auth_audit_logs_no_update AS ON UPDATE TO auth_audit_logs DO INSTEAD NOTHING;
+CREATE RULE auth_audit_logs_no_delete AS ON DELETE TO auth_audit_logs DO INSTEAD NOTHING;
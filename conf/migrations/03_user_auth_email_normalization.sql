@@ -0,0 +1,52 @@
+-- user_auth_emails
+
+-- Mirrors EmailPolicy::normalize; its rules are fixed rather than configured so stored values can't drift from it.
+ALTER TABLE user_auth_emails ADD COLUMN normalized_email VARCHAR(255);
+UPDATE user_auth_emails
+    SET normalized_email = COALESCE(
+        CASE
+            WHEN t.domain IN ('gmail.com', 'googlemail.com') THEN REPLACE(t.local_part, '.', '') || '@gmail.com'
+            ELSE t.local_part || '@' || t.domain
+        END,
+        LOWER(TRIM(t.email))
+    )
+    FROM (
+        SELECT email,
+            SPLIT_PART(LOWER(SUBSTRING(TRIM(email) FROM '^(.*)@[^@]*$')), '+', 1) AS local_part,
+            LOWER(SUBSTRING(TRIM(email) FROM '@([^@]*)$')) AS domain
+            FROM user_auth_emails
+    ) AS t
+    WHERE user_auth_emails.email = t.email;
+ALTER TABLE user_auth_emails ALTER COLUMN normalized_email SET NOT NULL;
+
+-- Verified addresses of different users can't be merged here, so the migration stops and lists them to be resolved by hand first.
+DO $$
+DECLARE
+    collisions TEXT;
+BEGIN
+    SELECT STRING_AGG(t.normalized_email || ' (' || t.emails || ')', ', ' ORDER BY t.normalized_email)
+        INTO collisions
+        FROM (
+            SELECT normalized_email, STRING_AGG(email, ', ' ORDER BY email) AS emails
+                FROM user_auth_emails
+                WHERE email_verified = true
+                GROUP BY normalized_email
+                HAVING COUNT(*) > 1
+        ) AS t;
+    IF collisions IS NOT NULL THEN
+        RAISE EXCEPTION 'verified emails collide after normalization: %', collisions;
+    END IF;
+END $$;
+
+-- Pending registrations colliding with another spelling of the same address are dropped, keeping a verified row or else the newest.
+-- The registrant's user is deleted along with it, and the row follows through the foreign key.
+DELETE FROM users
+    WHERE id IN (
+        SELECT t.user_id
+            FROM (
+                SELECT user_id, email_verified, ROW_NUMBER() OVER (PARTITION BY normalized_email ORDER BY email_verified DESC, updated_at DESC) AS n
+                    FROM user_auth_emails
+            ) AS t
+            WHERE t.n > 1 AND t.email_verified = false
+    );
+CREATE UNIQUE INDEX user_auth_emails_normalized_email_unique_index ON user_auth_emails(normalized_email);
@@ -0,0 +1,28 @@
+-- users
+
+ALTER TABLE users ADD COLUMN invitation_quota INTEGER NOT NULL DEFAULT 0;
+
+-- invitations
+
+CREATE TABLE invitations (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    code VARCHAR(255) NOT NULL UNIQUE,
+    inviter_user_id VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    max_uses INTEGER NOT NULL,
+    use_count INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX invitations_inviter_user_id_index ON invitations(inviter_user_id);
+
+-- invitation_redemptions
+
+CREATE TABLE invitation_redemptions (
+    invitation_id VARCHAR(255) NOT NULL,
+    inviter_user_id VARCHAR(255) NOT NULL,
+    invitee_user_id VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY (invitation_id, invitee_user_id)
+);
+CREATE INDEX invitation_redemptions_inviter_user_id_index ON invitation_redemptions(inviter_user_id);
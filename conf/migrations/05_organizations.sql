@@ -0,0 +1,43 @@
+-- organizations
+
+CREATE TYPE organization_role AS ENUM ('Owner', 'Admin', 'Member');
+
+CREATE TABLE organizations (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+
+-- organization_members
+
+CREATE TABLE organization_members (
+    organization_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255) NOT NULL,
+    role organization_role NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY (organization_id, user_id)
+);
+CREATE INDEX organization_members_user_id_index ON organization_members(user_id);
+
+-- organization_invitations
+
+CREATE TABLE organization_invitations (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    organization_id VARCHAR(255) NOT NULL,
+    code VARCHAR(255) NOT NULL UNIQUE,
+    email VARCHAR(255) NOT NULL,
+    role organization_role NOT NULL,
+    invited_by VARCHAR(255) NOT NULL,
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    accepted_at TIMESTAMP WITHOUT TIME ZONE,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX organization_invitations_organization_id_index ON organization_invitations(organization_id);
+
+-- image_convert_jobs
+
+ALTER TABLE image_convert_jobs ADD COLUMN organization_id VARCHAR(255);
+CREATE INDEX image_convert_jobs_organization_id_index ON image_convert_jobs(organization_id);
@@ -0,0 +1,8 @@
+-- auth_audit_logs
+
+ALTER TYPE auth_audit_event_type ADD VALUE 'ImpersonationStart';
+ALTER TYPE auth_audit_event_type ADD VALUE 'ImpersonationStop';
+ALTER TYPE auth_audit_event_type ADD VALUE 'ImpersonatedRequest';
+
+ALTER TABLE auth_audit_logs ADD COLUMN actor_user_id VARCHAR(255);
+CREATE INDEX auth_audit_logs_actor_user_id_created_at_index ON auth_audit_logs(actor_user_id, created_at);
@@ -0,0 +1,21 @@
+-- device_authorizations
+
+CREATE TYPE device_authorization_status AS ENUM ('Pending', 'Approved', 'Denied', 'Consumed');
+
+CREATE TABLE device_authorizations (
+    device_code VARCHAR(255) NOT NULL PRIMARY KEY,
+    user_code VARCHAR(255) NOT NULL UNIQUE,
+    client_id VARCHAR(255),
+    status device_authorization_status NOT NULL,
+    user_id VARCHAR(255),
+    interval_seconds INTEGER NOT NULL,
+    last_polled_at TIMESTAMP WITHOUT TIME ZONE,
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX device_authorizations_expires_at_index ON device_authorizations(expires_at);
+
+-- auth_audit_logs
+
+ALTER TYPE auth_audit_auth_method ADD VALUE 'DeviceCode';
@@ -0,0 +1,3 @@
+-- auth_audit_logs
+
+ALTER TYPE auth_audit_event_type ADD VALUE 'Reauthenticate';
@@ -0,0 +1,12 @@
+-- refresh_tokens
+
+ALTER TABLE refresh_tokens ADD COLUMN id VARCHAR(255);
+UPDATE refresh_tokens SET id = gen_random_uuid()::TEXT;
+ALTER TABLE refresh_tokens ALTER COLUMN id SET NOT NULL;
+
+ALTER TABLE refresh_tokens RENAME COLUMN refresh_token TO token_hash;
+UPDATE refresh_tokens SET token_hash = ENCODE(SHA256(CONVERT_TO(token_hash, 'UTF8')), 'hex');
+
+ALTER TABLE refresh_tokens DROP CONSTRAINT refresh_tokens_pkey;
+ALTER TABLE refresh_tokens ADD PRIMARY KEY (id);
+CREATE UNIQUE INDEX refresh_tokens_token_hash_unique_index ON refresh_tokens(token_hash);
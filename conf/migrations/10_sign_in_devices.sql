@@ -0,0 +1,24 @@
+-- users
+
+ALTER TABLE users ADD COLUMN sign_in_notification_enabled BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- user_sign_in_devices
+
+CREATE TABLE user_sign_in_devices (
+    user_id VARCHAR(255) NOT NULL,
+    fingerprint VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(255),
+    user_agent VARCHAR(1024),
+    session_id VARCHAR(255) NOT NULL,
+    revoke_code_hash VARCHAR(255),
+    revoke_expires_at TIMESTAMP WITHOUT TIME ZONE,
+    first_seen_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    last_seen_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY(user_id, fingerprint),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE UNIQUE INDEX user_sign_in_devices_revoke_code_hash_unique_index ON user_sign_in_devices(revoke_code_hash);
+
+-- auth_audit_logs
+
+ALTER TYPE auth_audit_event_type ADD VALUE 'SessionRevoke';
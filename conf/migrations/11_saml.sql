@@ -0,0 +1,29 @@
+-- organization_saml_providers
+
+CREATE TABLE organization_saml_providers (
+    organization_id VARCHAR(255) NOT NULL PRIMARY KEY,
+    idp_entity_id VARCHAR(1024) NOT NULL,
+    idp_sso_url VARCHAR(2048) NOT NULL,
+    idp_certificate TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE
+);
+
+-- saml_authn_requests
+
+CREATE TABLE saml_authn_requests (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    organization_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255),
+    login_code_hash VARCHAR(255),
+    expires_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE UNIQUE INDEX saml_authn_requests_login_code_hash_unique_index ON saml_authn_requests(login_code_hash);
+CREATE INDEX saml_authn_requests_expires_at_index ON saml_authn_requests(expires_at);
+
+-- auth_audit_logs
+
+ALTER TYPE auth_audit_auth_method ADD VALUE 'Saml';
@@ -0,0 +1,45 @@
+-- users
+
+ALTER TABLE users ADD COLUMN suspended_at TIMESTAMP WITHOUT TIME ZONE;
+
+-- user_auth_providers
+
+ALTER TABLE user_auth_providers ADD COLUMN external_id VARCHAR(255);
+
+-- organization_scim_tokens
+
+CREATE TABLE organization_scim_tokens (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    organization_id VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(255) NOT NULL UNIQUE,
+    created_by VARCHAR(255) NOT NULL,
+    last_used_at TIMESTAMP WITHOUT TIME ZONE,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE
+);
+CREATE INDEX organization_scim_tokens_organization_id_index ON organization_scim_tokens(organization_id);
+
+-- scim_groups
+
+CREATE TABLE scim_groups (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    organization_id VARCHAR(255) NOT NULL,
+    display_name VARCHAR(255) NOT NULL,
+    external_id VARCHAR(255),
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE
+);
+CREATE UNIQUE INDEX scim_groups_organization_id_display_name_unique_index ON scim_groups(organization_id, display_name);
+
+-- scim_group_members
+
+CREATE TABLE scim_group_members (
+    group_id VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY (group_id, user_id),
+    FOREIGN KEY (group_id) REFERENCES scim_groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX scim_group_members_user_id_index ON scim_group_members(user_id);
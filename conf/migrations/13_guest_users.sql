@@ -0,0 +1,20 @@
+-- users
+
+ALTER TYPE user_authentication_type ADD VALUE 'Guest';
+ALTER TYPE user_role ADD VALUE 'Guest';
+
+-- user_guests
+
+CREATE TABLE user_guests (
+    user_id VARCHAR(255) NOT NULL PRIMARY KEY,
+    device_id_hash VARCHAR(255) NOT NULL UNIQUE,
+    convert_quota INTEGER NOT NULL,
+    last_seen_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX user_guests_last_seen_at_index ON user_guests(last_seen_at);
+
+-- auth_audit_logs
+
+ALTER TYPE auth_audit_auth_method ADD VALUE 'Guest';
@@ -0,0 +1,27 @@
+-- legal_documents
+
+CREATE TYPE legal_document_type AS ENUM ('TermsOfService', 'PrivacyPolicy');
+
+CREATE TABLE legal_documents (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    document_type legal_document_type NOT NULL,
+    version VARCHAR(255) NOT NULL,
+    url VARCHAR(2048) NOT NULL,
+    published_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    UNIQUE (document_type, version)
+);
+CREATE INDEX legal_documents_published_at_index ON legal_documents(document_type, published_at);
+
+-- legal_acceptances
+
+CREATE TABLE legal_acceptances (
+    user_id VARCHAR(255) NOT NULL,
+    document_id VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(255),
+    user_agent TEXT,
+    accepted_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY (user_id, document_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (document_id) REFERENCES legal_documents(id)
+);
@@ -0,0 +1,5 @@
+-- email_send_logs
+
+-- A notification can name several recipients, and SNS may deliver it more than once.
+ALTER TABLE email_send_logs DROP CONSTRAINT email_send_logs_pkey;
+ALTER TABLE email_send_logs ADD PRIMARY KEY (message_id, email_address, event_type, created_at);
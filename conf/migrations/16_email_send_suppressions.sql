@@ -0,0 +1,4 @@
+-- email_send_blocked_addresses
+
+-- Hard bounces and complaints never expire; soft entries stop suppressing once expires_at passes.
+ALTER TABLE email_send_blocked_addresses ADD COLUMN expires_at TIMESTAMP WITHOUT TIME ZONE;
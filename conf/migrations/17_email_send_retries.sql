@@ -0,0 +1,12 @@
+-- email_send_job_batch_detail_attempts
+
+-- One row per failed send; retry_count matches the detail's value at the time of the attempt.
+CREATE TABLE email_send_job_batch_detail_attempts (
+    job_id VARCHAR(255) NOT NULL,
+    email_address VARCHAR(255) NOT NULL,
+    retry_count INTEGER NOT NULL,
+    transient BOOLEAN NOT NULL,
+    failed_reason TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    PRIMARY KEY(job_id, email_address, retry_count)
+);
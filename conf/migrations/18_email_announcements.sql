@@ -0,0 +1,4 @@
+-- users
+
+-- Announcements are opt-in, unlike the sign-in notification.
+ALTER TABLE users ADD COLUMN announcement_email_enabled BOOLEAN NOT NULL DEFAULT FALSE;
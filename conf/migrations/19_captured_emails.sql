@@ -0,0 +1,14 @@
+-- captured_emails
+
+-- Written by the local email transport in place of delivering the message.
+CREATE TABLE captured_emails (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    to_address VARCHAR(255) NOT NULL,
+    from_address VARCHAR(255) NOT NULL,
+    subject TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    html_body TEXT NOT NULL,
+    raw TEXT NOT NULL,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX captured_emails_created_at_index ON captured_emails(created_at);
@@ -0,0 +1,14 @@
+-- outbox_messages
+
+-- Queue messages written in the same transaction as the rows they refer to and delivered afterwards by the relay.
+CREATE TABLE outbox_messages (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    queue_url VARCHAR(255) NOT NULL,
+    message_body TEXT NOT NULL,
+    retry_count INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP WITHOUT TIME ZONE NOT NULL,
+    sent_at TIMESTAMP WITHOUT TIME ZONE,
+    created_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX outbox_messages_pending_index ON outbox_messages(next_attempt_at) WHERE sent_at IS NULL;
@@ -0,0 +1,8 @@
+-- auth_rate_limits
+
+CREATE TABLE auth_rate_limits (
+    key VARCHAR(255) NOT NULL PRIMARY KEY,
+    count INTEGER NOT NULL,
+    window_started_at TIMESTAMP WITHOUT TIME ZONE NOT NULL
+);
+CREATE INDEX auth_rate_limits_window_started_at_index ON auth_rate_limits(window_started_at);
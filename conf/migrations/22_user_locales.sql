@@ -0,0 +1,4 @@
+-- users
+
+-- Empty until the user confirms a sign-up or picks a language, and emails then fall back to the default locale.
+ALTER TABLE users ADD COLUMN locale VARCHAR(35) NOT NULL DEFAULT '';
@@ -11,8 +11,10 @@ case $1 in
     ;;
 "email-send-feedback")
     ;;
+"user-purge")
+    ;;
 *)
-    echo "Usage: $0 <image-convert|email-send|email-send-feedback>"
+    echo "Usage: $0 <image-convert|email-send|email-send-feedback|user-purge>"
     exit 1
     ;;
 esac
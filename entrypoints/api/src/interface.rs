@@ -1,3 +1,4 @@
+mod cookie;
 mod extractors;
 mod routes;
 mod server;
@@ -0,0 +1,77 @@
+use axum::http::HeaderMap;
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+
+use opxs_auth::shared::model::AuthToken;
+use opxs_base::AppError;
+
+use crate::shared::state::AppState;
+
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const CSRF_TOKEN_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_TOKEN_HEADER_NAME: &str = "x-csrf-token";
+pub const SESSION_MODE_HEADER_NAME: &str = "x-session-mode";
+
+const REFRESH_TOKEN_COOKIE_PATH: &str = "/api/v1/auth/token";
+const CSRF_TOKEN_COOKIE_PATH: &str = "/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionMode {
+    Body,
+    Cookie,
+}
+
+// In cookie mode the refresh token never reaches JS: it is moved into an HttpOnly cookie and a readable CSRF token is issued next to it.
+pub fn issue_session(state: &AppState, jar: CookieJar, mode: SessionMode, mut auth_token: AuthToken) -> (CookieJar, AuthToken) {
+    if mode == SessionMode::Body {
+        return (jar, auth_token);
+    }
+
+    let csrf_token = hex::encode(state.service.random_bytes_provider.get_bytes(32));
+
+    let max_age = time::Duration::seconds(auth_token.expires_in as i64);
+    let refresh_token = std::mem::take(&mut auth_token.refresh_token);
+
+    let jar = jar
+        .add(
+            Cookie::build(REFRESH_TOKEN_COOKIE_NAME, refresh_token)
+                .path(REFRESH_TOKEN_COOKIE_PATH)
+                .http_only(true)
+                .secure(true)
+                .same_site(SameSite::Strict)
+                .max_age(max_age)
+                .finish(),
+        )
+        .add(
+            Cookie::build(CSRF_TOKEN_COOKIE_NAME, csrf_token)
+                .path(CSRF_TOKEN_COOKIE_PATH)
+                .secure(true)
+                .same_site(SameSite::Strict)
+                .max_age(max_age)
+                .finish(),
+        );
+
+    (jar, auth_token)
+}
+
+pub fn remove_session(jar: CookieJar) -> CookieJar {
+    jar.remove(Cookie::build(REFRESH_TOKEN_COOKIE_NAME, "").path(REFRESH_TOKEN_COOKIE_PATH).finish())
+        .remove(Cookie::build(CSRF_TOKEN_COOKIE_NAME, "").path(CSRF_TOKEN_COOKIE_PATH).finish())
+}
+
+pub fn get_refresh_token(jar: &CookieJar) -> Option<String> {
+    jar.get(REFRESH_TOKEN_COOKIE_NAME).map(|cookie| cookie.value().to_owned())
+}
+
+pub fn verify_csrf_token(jar: &CookieJar, headers: &HeaderMap) -> Result<(), AppError> {
+    let cookie_token = jar.get(CSRF_TOKEN_COOKIE_NAME).map(|cookie| cookie.value().to_owned());
+    let header_token = headers.get(CSRF_TOKEN_HEADER_NAME).and_then(|v| v.to_str().ok());
+
+    match (cookie_token, header_token) {
+        (Some(cookie_token), Some(header_token))
+            if !cookie_token.is_empty() && ring::constant_time::verify_slices_are_equal(cookie_token.as_bytes(), header_token.as_bytes()).is_ok() =>
+        {
+            Ok(())
+        }
+        _ => Err(AppError::Forbidden),
+    }
+}
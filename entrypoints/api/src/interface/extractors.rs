@@ -1,39 +1,296 @@
+use std::net::SocketAddr;
+
 use axum::{
     async_trait,
-    extract::{rejection::JsonRejection, FromRequest, TypedHeader},
-    http::Request,
+    extract::{rejection::JsonRejection, ConnectInfo, FromRequest, FromRequestParts, OriginalUri, TypedHeader},
+    http::{
+        header::{ACCEPT_LANGUAGE, AUTHORIZATION, USER_AGENT},
+        request::Parts,
+        Request,
+    },
     Json,
 };
 use headers::{authorization::Bearer, Authorization};
 use serde::de::DeserializeOwned;
 use validator::Validate;
 
-use opxs_auth::shared::{jwt, model::User};
+use opxs_auth::shared::{
+    jwt::{self, Claims},
+    model::{ClientInfo, User, UserRole},
+};
 use opxs_base::AppError;
 
-use crate::shared::state::AppState;
+use crate::{
+    interface::cookie::{self, SessionMode},
+    shared::state::AppState,
+};
 
 #[async_trait]
-impl<B> FromRequest<AppState, B> for User
-where
-    B: Send + 'static,
-{
+impl FromRequestParts<AppState> for Claims {
     type Rejection = AppError;
 
-    async fn from_request(req: Request<B>, state: &AppState) -> Result<Self, Self::Rejection> {
-        let TypedHeader(Authorization(bearer)) = TypedHeader::<Authorization<Bearer>>::from_request(req, state).await?;
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await?;
 
         let access_token = bearer.token();
         let now = state.service.system_clock.now();
         let claims = jwt::verify(&state.conf.auth.jwt.secret.current, access_token, now)?;
 
-        let user_id = claims.sub;
-        let user = state.service.user.get_user(&user_id).await?;
+        Ok(claims)
+    }
+}
+
+// Requests under an impersonation token are audited with both identities before the handler runs.
+// Users who haven't accepted the current legal documents are rejected until they do.
+#[async_trait]
+impl FromRequestParts<AppState> for User {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+
+        if let Some(actor) = claims.act.as_ref() {
+            let client = ClientInfo::from_request_parts(parts, state).await?;
+            let path = match parts.extensions.get::<OriginalUri>() {
+                Some(OriginalUri(uri)) => uri.path().to_string(),
+                None => parts.uri.path().to_string(),
+            };
+            state
+                .service
+                .impersonation
+                .log_request(&claims.sub, &actor.sub, parts.method.as_str(), &path, &client)
+                .await?;
+        }
+
+        let user = state.service.user.get_user(&claims.sub).await?;
+
+        // Only the user can accept, so an impersonating administrator isn't held back by pending documents.
+        if claims.act.is_none() {
+            state.service.legal.ensure_accepted(&user.id).await?;
+        }
 
         Ok(user)
     }
 }
 
+// None only when no Authorization header is sent; a token that is present must resolve to a valid User,
+// so bad, expired or suspended sessions are rejected instead of being treated as anonymous.
+pub struct OptionalUser(pub Option<User>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for OptionalUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if !parts.headers.contains_key(AUTHORIZATION) {
+            return Ok(OptionalUser(None));
+        }
+
+        let user = User::from_request_parts(parts, state).await?;
+
+        Ok(OptionalUser(Some(user)))
+    }
+}
+
+// Rejects impersonation tokens, for operations that touch credentials or the account itself.
+// Not gated on legal acceptance, so a user can still accept, sign out or leave.
+pub struct NonImpersonatedUser(pub User);
+
+#[async_trait]
+impl FromRequestParts<AppState> for NonImpersonatedUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        if claims.act.is_some() {
+            return Err(AppError::Forbidden);
+        }
+
+        let user = state.service.user.get_user(&claims.sub).await?;
+
+        Ok(NonImpersonatedUser(user))
+    }
+}
+
+const MAX_AUTH_AGE_SECONDS: i64 = 5 * 60;
+
+// Requires an interactive login or step-up re-authentication within the last few minutes.
+pub struct RecentlyAuthenticatedUser(pub User);
+
+#[async_trait]
+impl FromRequestParts<AppState> for RecentlyAuthenticatedUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        if claims.act.is_some() {
+            return Err(AppError::Forbidden);
+        }
+
+        let now = state.service.system_clock.now().timestamp();
+        match claims.auth_time {
+            Some(auth_time) if now - auth_time <= MAX_AUTH_AGE_SECONDS => {}
+            _ => return Err(AppError::ReauthenticationRequired),
+        }
+
+        let user = state.service.user.get_user(&claims.sub).await?;
+
+        Ok(RecentlyAuthenticatedUser(user))
+    }
+}
+
+pub struct Impersonation {
+    pub user_id: String,
+    pub actor_user_id: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Impersonation {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+        let actor = claims.act.ok_or(AppError::Forbidden)?;
+
+        Ok(Impersonation {
+            user_id: claims.sub,
+            actor_user_id: actor.sub,
+        })
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let NonImpersonatedUser(user) = NonImpersonatedUser::from_request_parts(parts, state).await?;
+        if user.role != UserRole::Admin {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(AdminUser(user))
+    }
+}
+
+pub const ORGANIZATION_ID_HEADER_NAME: &str = "x-organization-id";
+
+pub struct OrganizationContext(pub Option<String>);
+
+// The header takes precedence over the `org` claim of an organization-scoped access token.
+// Resolves to None when neither is given, and rejects callers who aren't members.
+#[async_trait]
+impl FromRequestParts<AppState> for OrganizationContext {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get(ORGANIZATION_ID_HEADER_NAME)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let bearer = Option::<TypedHeader<Authorization<Bearer>>>::from_request_parts(parts, state)
+            .await
+            .unwrap_or(None);
+
+        let now = state.service.system_clock.now();
+        let claims = bearer.and_then(|TypedHeader(Authorization(bearer))| jwt::verify(&state.conf.auth.jwt.secret.current, bearer.token(), now).ok());
+
+        let Some(organization_id) = header.or_else(|| claims.as_ref().and_then(|c| c.org.clone())) else {
+            return Ok(OrganizationContext(None));
+        };
+        let claims = claims.ok_or(AppError::Forbidden)?;
+        // Access tokens outlive a suspension, so the user is checked the same way the User extractor does.
+        state.service.user.get_user(&claims.sub).await?;
+        state.service.organization.get_role(&organization_id, &claims.sub).await?;
+
+        Ok(OrganizationContext(Some(organization_id)))
+    }
+}
+
+// SCIM clients authenticate with a per-organization bearer token rather than a user session.
+pub struct ScimOrganization(pub String);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ScimOrganization {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state).await?;
+        let organization_id = state.service.scim.authenticate(bearer.token()).await?;
+
+        Ok(ScimOrganization(organization_id))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &AppState) -> Result<Self, Self::Rejection> {
+        // Clients can put anything in front of X-Forwarded-For; only the last entry, appended by our load balancer, is trusted.
+        let forwarded_for = parts
+            .headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let ip_address = forwarded_for.or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        });
+        let user_agent = parts.headers.get(USER_AGENT).and_then(|v| v.to_str().ok()).map(|v| v.to_string());
+
+        Ok(ClientInfo { ip_address, user_agent })
+    }
+}
+
+// The preferred language of the caller, taken from the first Accept-Language entry; empty when absent.
+pub struct Locale(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Locale
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let locale = parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim())
+            .filter(|v| *v != "*")
+            .unwrap_or_default();
+
+        Ok(Locale(locale.to_string()))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SessionMode
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let mode = parts.headers.get(cookie::SESSION_MODE_HEADER_NAME).and_then(|v| v.to_str().ok());
+
+        match mode {
+            None | Some("body") => Ok(SessionMode::Body),
+            Some("cookie") => Ok(SessionMode::Cookie),
+            Some(v) => Err(AppError::InvalidRequest(anyhow::anyhow!("Unknown session mode: {}", v))),
+        }
+    }
+}
+
 // https://github.com/tokio-rs/axum/blob/main/examples/validator/src/main.rs
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ValidatedJson<T>(pub T);
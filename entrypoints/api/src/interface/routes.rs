@@ -1,2 +1,6 @@
+pub mod admin;
 pub mod auth;
 pub mod image;
+pub mod local;
+pub mod organization;
+pub mod scim;
@@ -0,0 +1,20 @@
+pub mod audit;
+pub mod email;
+pub mod impersonation;
+pub mod invitation;
+pub mod legal;
+
+use axum::Router;
+
+use crate::shared::state::AppState;
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .nest_service("/audit", audit::gen_service(state.clone()))
+        .nest_service("/email", email::gen_service(state.clone()))
+        .nest_service("/impersonation", impersonation::gen_service(state.clone()))
+        .nest_service("/invitation", invitation::gen_service(state.clone()))
+        .nest_service("/legal", legal::gen_service(state.clone()))
+        .with_state(state)
+}
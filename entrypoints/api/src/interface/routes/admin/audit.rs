@@ -0,0 +1,54 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use opxs_auth::shared::model::{AuthAuditEventType, AuthAuditLog};
+use opxs_base::AppError;
+
+use crate::{interface::extractors::AdminUser, shared::state::AppState};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/logs", get(logs)).with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit/logs",
+    params(LogsInput),
+    responses(
+        (status = 200, body = [AuthAuditLog])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn logs(State(state): State<AppState>, _admin: AdminUser, input: Query<LogsInput>) -> Result<Json<Vec<AuthAuditLog>>, AppError> {
+    let logs = state
+        .service
+        .audit
+        .get_logs(
+            input.user_id.as_deref(),
+            input.actor_user_id.as_deref(),
+            input.event_type.as_ref(),
+            input.before.as_ref(),
+            input.limit.unwrap_or(50),
+        )
+        .await?;
+
+    Ok(Json(logs))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct LogsInput {
+    pub user_id: Option<String>,
+    pub actor_user_id: Option<String>,
+    pub event_type: Option<AuthAuditEventType>,
+    pub before: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+}
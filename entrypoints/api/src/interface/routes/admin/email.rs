@@ -0,0 +1,297 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use hyper::StatusCode;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use opxs_base::AppError;
+use opxs_email_send::{AnnouncementRequestParam, EmailSendBlockedAddress, EmailSendJobBatchDetailAttempt, EmailSendJobProgress};
+
+use crate::{
+    interface::extractors::{AdminUser, ValidatedJson},
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/suppressions", get(suppressions).post(suppress))
+        .route("/suppressions/:email_address", delete(unsuppress))
+        .route("/announcements", post(announce))
+        .route("/jobs/:job_id", get(job))
+        .route("/jobs/:job_id/attempts", get(attempts))
+        .route("/jobs/:job_id/pause", post(pause))
+        .route("/jobs/:job_id/resume", post(resume))
+        .route("/jobs/:job_id/cancel", post(cancel))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/email/suppressions",
+    params(SuppressionsInput),
+    responses(
+        (status = 200, body = [EmailSendBlockedAddress])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn suppressions(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    input: Query<SuppressionsInput>,
+) -> Result<Json<Vec<EmailSendBlockedAddress>>, AppError> {
+    let entries = state
+        .service
+        .email_suppression
+        .get_entries(input.email_address.as_deref(), input.before.as_ref(), input.limit.unwrap_or(50))
+        .await?;
+
+    Ok(Json(entries))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct SuppressionsInput {
+    pub email_address: Option<String>,
+    pub before: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email/suppressions",
+    request_body = SuppressInput,
+    responses(
+        (status = 200, body = EmailSendBlockedAddress)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn suppress(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    ValidatedJson(input): ValidatedJson<SuppressInput>,
+) -> Result<Json<EmailSendBlockedAddress>, AppError> {
+    let expires_at = input.expires_at.map(|t| t.and_utc());
+    if expires_at.is_some_and(|t| t <= state.service.system_clock.now()) {
+        return Err(AppError::InvalidRequest(anyhow::anyhow!("expires_at is in the past")));
+    }
+
+    let entry = state
+        .service
+        .email_suppression
+        .add(&input.email_address, &input.reason, expires_at.as_ref())
+        .await?;
+
+    Ok(Json(entry))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct SuppressInput {
+    #[validate(email)]
+    pub email_address: String,
+    #[validate(length(min = 1))]
+    pub reason: String,
+    // UTC; omit for a permanent entry.
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/email/suppressions/{email_address}",
+    params(
+        ("email_address" = String, Path,)
+    ),
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn unsuppress(State(state): State<AppState>, _admin: AdminUser, Path(email_address): Path<String>) -> Result<StatusCode, AppError> {
+    if !state.service.email_suppression.remove(&email_address).await? {
+        return Err(AppError::NotFound);
+    }
+    Ok(StatusCode::OK)
+}
+
+// Sends to every user who opted in to announcements at the time of the request.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email/announcements",
+    request_body = AnnounceInput,
+    responses(
+        (status = 200, body = EmailSendJobProgress)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn announce(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    ValidatedJson(input): ValidatedJson<AnnounceInput>,
+) -> Result<Json<EmailSendJobProgress>, AppError> {
+    let to_email_addresses = state.service.user.get_announcement_email_addresses().await?;
+    if to_email_addresses.is_empty() {
+        return Err(AppError::InvalidRequest(anyhow::anyhow!("no users opted in to announcements")));
+    }
+
+    let param = AnnouncementRequestParam {
+        from_email_address: state.conf.email.from_email_address.clone(),
+        subject: input.subject,
+        body: input.body,
+        settings_url: format!("{}settings", state.conf.web.origin.as_str()),
+        locale: input.locale.unwrap_or_default(),
+    };
+
+    let job_id = state.service.tsid_provider.gen().to_string();
+    state
+        .service
+        .email_send_job_creator
+        .create_announcement_job(&job_id, &param, &to_email_addresses, state.conf.email.bulk.batch_size)
+        .await?;
+
+    get_progress(&state, &job_id).await.map(Json)
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AnnounceInput {
+    #[validate(length(min = 1))]
+    pub subject: String,
+    #[validate(length(min = 1))]
+    pub body: String,
+    pub locale: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/email/jobs/{job_id}",
+    params(
+        ("job_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = EmailSendJobProgress)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn job(State(state): State<AppState>, _admin: AdminUser, Path(job_id): Path<String>) -> Result<Json<EmailSendJobProgress>, AppError> {
+    get_progress(&state, &job_id).await.map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/email/jobs/{job_id}/attempts",
+    params(
+        ("job_id" = String, Path,),
+        AttemptsInput
+    ),
+    responses(
+        (status = 200, body = [EmailSendJobBatchDetailAttempt])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn attempts(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(job_id): Path<String>,
+    input: Query<AttemptsInput>,
+) -> Result<Json<Vec<EmailSendJobBatchDetailAttempt>>, AppError> {
+    get_progress(&state, &job_id).await?;
+    let attempts = state
+        .service
+        .email_send_job_controller
+        .get_attempts(&job_id, &input.email_address)
+        .await?;
+
+    Ok(Json(attempts))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct AttemptsInput {
+    pub email_address: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email/jobs/{job_id}/pause",
+    params(
+        ("job_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = EmailSendJobProgress)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn pause(State(state): State<AppState>, _admin: AdminUser, Path(job_id): Path<String>) -> Result<Json<EmailSendJobProgress>, AppError> {
+    get_progress(&state, &job_id).await?;
+    if !state.service.email_send_job_controller.pause(&job_id).await? {
+        return Err(AppError::Conflict(anyhow::anyhow!("job has no batch to pause")));
+    }
+    get_progress(&state, &job_id).await.map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email/jobs/{job_id}/resume",
+    params(
+        ("job_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = EmailSendJobProgress)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn resume(State(state): State<AppState>, _admin: AdminUser, Path(job_id): Path<String>) -> Result<Json<EmailSendJobProgress>, AppError> {
+    get_progress(&state, &job_id).await?;
+    if !state.service.email_send_job_controller.resume(&job_id).await? {
+        return Err(AppError::Conflict(anyhow::anyhow!("job has no paused batch")));
+    }
+    get_progress(&state, &job_id).await.map(Json)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/email/jobs/{job_id}/cancel",
+    params(
+        ("job_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = EmailSendJobProgress)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn cancel(State(state): State<AppState>, _admin: AdminUser, Path(job_id): Path<String>) -> Result<Json<EmailSendJobProgress>, AppError> {
+    get_progress(&state, &job_id).await?;
+    if !state.service.email_send_job_controller.cancel(&job_id).await? {
+        return Err(AppError::Conflict(anyhow::anyhow!("job has no unfinished batch")));
+    }
+    get_progress(&state, &job_id).await.map(Json)
+}
+
+async fn get_progress(state: &AppState, job_id: &str) -> Result<EmailSendJobProgress, AppError> {
+    state
+        .service
+        .email_send_job_controller
+        .get_progress(job_id)
+        .await?
+        .ok_or(AppError::NotFound)
+}
@@ -0,0 +1,81 @@
+use axum::{extract::State, routing::post, Json, Router};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{AuthToken, ClientInfo};
+use opxs_base::AppError;
+
+use crate::{
+    interface::{
+        cookie::{self, SessionMode},
+        extractors::{AdminUser, Impersonation, ValidatedJson},
+    },
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/start", post(start)).route("/stop", post(stop)).with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/impersonation/start",
+    request_body = StartInput,
+    responses(
+        (status = 200, body = AuthToken)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn start(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    client: ClientInfo,
+    ValidatedJson(input): ValidatedJson<StartInput>,
+) -> Result<Json<AuthToken>, AppError> {
+    let auth_token = state
+        .service
+        .impersonation
+        .start(&admin, &input.user_id, input.reason.as_deref(), &client)
+        .await?;
+    Ok(Json(auth_token))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct StartInput {
+    pub user_id: String,
+    #[validate(length(max = 1024))]
+    pub reason: Option<String>,
+}
+
+// Called with the impersonation token; hands the admin a fresh session of their own.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/impersonation/stop",
+    responses(
+        (status = 200, body = AuthToken)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn stop(
+    State(state): State<AppState>,
+    impersonation: Impersonation,
+    client: ClientInfo,
+    session_mode: SessionMode,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let auth_token = state
+        .service
+        .impersonation
+        .stop(&impersonation.user_id, &impersonation.actor_user_id, &client)
+        .await?;
+    let (jar, auth_token) = cookie::issue_session(&state, jar, session_mode, auth_token);
+
+    Ok((jar, Json(auth_token)))
+}
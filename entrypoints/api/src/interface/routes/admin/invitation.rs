@@ -0,0 +1,44 @@
+use axum::{extract::State, routing::put, Router};
+use hyper::StatusCode;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_base::AppError;
+
+use crate::{
+    interface::extractors::{AdminUser, ValidatedJson},
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/quota", put(quota)).with_state(state)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/invitation/quota",
+    request_body = QuotaInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn quota(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    ValidatedJson(input): ValidatedJson<QuotaInput>,
+) -> Result<StatusCode, AppError> {
+    state.service.invitation.update_quota(&input.user_id, input.quota).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct QuotaInput {
+    pub user_id: String,
+    #[validate(range(min = 0))]
+    pub quota: i32,
+}
@@ -0,0 +1,59 @@
+use axum::{extract::State, routing::post, Json, Router};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{LegalDocument, LegalDocumentType};
+use opxs_base::AppError;
+
+use crate::{
+    interface::extractors::{AdminUser, ValidatedJson},
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/documents", post(publish)).with_state(state)
+}
+
+// Publishing a new version makes every user re-accept it before further API use.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/legal/documents",
+    request_body = PublishInput,
+    responses(
+        (status = 200, body = LegalDocument)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn publish(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    ValidatedJson(input): ValidatedJson<PublishInput>,
+) -> Result<Json<LegalDocument>, AppError> {
+    let document = state
+        .service
+        .legal
+        .publish(
+            &input.document_type,
+            &input.version,
+            &input.url,
+            input.published_at.map(|t| t.and_utc()).as_ref(),
+        )
+        .await?;
+    Ok(Json(document))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct PublishInput {
+    pub document_type: LegalDocumentType,
+    #[validate(length(min = 1, max = 255))]
+    pub version: String,
+    #[validate(url)]
+    pub url: String,
+    // UTC; defaults to now, and a future time schedules the version.
+    pub published_at: Option<NaiveDateTime>,
+}
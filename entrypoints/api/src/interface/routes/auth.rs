@@ -1,5 +1,14 @@
+pub mod announcement;
+pub mod audit;
+pub mod device;
 pub mod email;
 pub mod google;
+pub mod guest;
+pub mod invitation;
+pub mod legal;
+pub mod locale;
+pub mod saml;
+pub mod sign_in;
 pub mod token;
 
 use axum::{routing::get, Json, Router};
@@ -13,8 +22,17 @@ use crate::shared::state::AppState;
 pub fn gen_service(state: AppState) -> Router {
     Router::new()
         .route("/me", get(me))
+        .nest_service("/announcement", announcement::gen_service(state.clone()))
+        .nest_service("/audit", audit::gen_service(state.clone()))
+        .nest_service("/device", device::gen_service(state.clone()))
         .nest_service("/email", email::gen_service(state.clone()))
         .nest_service("/google", google::gen_service(state.clone()))
+        .nest_service("/guest", guest::gen_service(state.clone()))
+        .nest_service("/invitation", invitation::gen_service(state.clone()))
+        .nest_service("/legal", legal::gen_service(state.clone()))
+        .nest_service("/locale", locale::gen_service(state.clone()))
+        .nest_service("/saml", saml::gen_service(state.clone()))
+        .nest_service("/sign-in", sign_in::gen_service(state.clone()))
         .nest_service("/token", token::gen_service(state.clone()))
         .with_state(state)
 }
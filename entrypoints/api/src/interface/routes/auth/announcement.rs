@@ -0,0 +1,55 @@
+use axum::{extract::State, routing::get, Json, Router};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::User;
+use opxs_base::AppError;
+
+use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/", get(get_setting).put(update_setting)).with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/announcement",
+    responses(
+        (status = 200, body = AnnouncementSetting)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn get_setting(State(state): State<AppState>, user: User) -> Result<Json<AnnouncementSetting>, AppError> {
+    let enabled = state.service.user.get_announcement_enabled(&user.id).await?;
+    Ok(Json(AnnouncementSetting { enabled }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/auth/announcement",
+    request_body = AnnouncementSetting,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn update_setting(
+    State(state): State<AppState>,
+    user: User,
+    ValidatedJson(input): ValidatedJson<AnnouncementSetting>,
+) -> Result<StatusCode, AppError> {
+    state.service.user.update_announcement_enabled(&user.id, input.enabled).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct AnnouncementSetting {
+    pub enabled: bool,
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use opxs_auth::shared::model::{AuthAuditLog, User};
+use opxs_base::AppError;
+
+use crate::shared::state::AppState;
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/logs", get(logs)).with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/audit/logs",
+    params(LogsInput),
+    responses(
+        (status = 200, body = [AuthAuditLog])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn logs(State(state): State<AppState>, user: User, input: Query<LogsInput>) -> Result<Json<Vec<AuthAuditLog>>, AppError> {
+    let logs = state
+        .service
+        .audit
+        .get_user_logs(&user.id, input.before.as_ref(), input.limit.unwrap_or(50))
+        .await?;
+
+    Ok(Json(logs))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct LogsInput {
+    pub before: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+}
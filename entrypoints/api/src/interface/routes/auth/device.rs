@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use opxs_auth::shared::model::{AuthToken, ClientInfo, DeviceAuthorization, User};
+use opxs_base::AppError;
+
+use crate::{
+    interface::extractors::{NonImpersonatedUser, ValidatedJson},
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/authorize", post(authorize))
+        .route("/token", post(token))
+        .route("/verify", get(verify))
+        .route("/approve", post(approve))
+        .route("/deny", post(deny))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/device/authorize",
+    request_body = AuthorizeInput,
+    responses(
+        (status = 200, body = AuthorizeOutput)
+    )
+)]
+pub async fn authorize(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(input): ValidatedJson<AuthorizeInput>,
+) -> Result<Json<AuthorizeOutput>, AppError> {
+    let authorization = state.service.device_auth.authorize(input.client_id.as_deref(), &client).await?;
+
+    let verification_uri = format!("{}auth/device", state.conf.web.origin.as_str());
+    let verification_uri_complete = Url::parse_with_params(&verification_uri, &[("user_code", authorization.user_code.as_str())])
+        .unwrap()
+        .to_string();
+    let expires_in = (authorization.expires_at - state.service.system_clock.now().naive_utc()).num_seconds();
+
+    Ok(Json(AuthorizeOutput {
+        device_code: authorization.device_code,
+        user_code: authorization.user_code,
+        verification_uri,
+        verification_uri_complete,
+        expires_in,
+        interval: authorization.interval_seconds,
+    }))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AuthorizeInput {
+    #[validate(length(max = 255))]
+    pub client_id: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuthorizeOutput {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/device/token",
+    request_body = TokenInput,
+    responses(
+        (status = 200, body = AuthToken)
+    )
+)]
+pub async fn token(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(input): ValidatedJson<TokenInput>,
+) -> Result<Json<AuthToken>, AppError> {
+    let auth_token = state.service.device_auth.poll(&input.device_code, &client).await?;
+    Ok(Json(auth_token))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TokenInput {
+    pub device_code: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/device/verify",
+    params(VerifyInput),
+    responses(
+        (status = 200, body = DeviceAuthorization)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn verify(State(state): State<AppState>, _user: User, input: Query<VerifyInput>) -> Result<Json<DeviceAuthorization>, AppError> {
+    let authorization = state.service.device_auth.get_pending(&input.user_code).await?;
+    Ok(Json(authorization))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams, Validate)]
+pub struct VerifyInput {
+    pub user_code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/device/approve",
+    request_body = VerifyInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn approve(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    ValidatedJson(input): ValidatedJson<VerifyInput>,
+) -> Result<StatusCode, AppError> {
+    state.service.device_auth.approve(&input.user_code, &user.id).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/device/deny",
+    request_body = VerifyInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn deny(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    ValidatedJson(input): ValidatedJson<VerifyInput>,
+) -> Result<StatusCode, AppError> {
+    state.service.device_auth.deny(&input.user_code, &user.id).await?;
+    Ok(StatusCode::OK)
+}
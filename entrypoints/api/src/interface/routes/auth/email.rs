@@ -1,22 +1,35 @@
 use axum::{extract::State, routing::post, Json, Router};
+use axum_extra::extract::cookie::CookieJar;
 use hyper::StatusCode;
 use serde::Deserialize;
 use url::Url;
 use utoipa::ToSchema;
 use validator::Validate;
 
-use opxs_auth::shared::model::{AuthToken, User};
+use opxs_auth::{
+    email::EmailRegistration,
+    shared::model::{AuthToken, ClientInfo},
+};
 use opxs_base::AppError;
 
-use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+use crate::{
+    interface::{
+        cookie::{self, SessionMode},
+        extractors::{Locale, NonImpersonatedUser, RecentlyAuthenticatedUser, ValidatedJson},
+        routes::auth::sign_in,
+    },
+    shared::state::AppState,
+};
 
 #[allow(unused)]
 pub fn gen_service(state: AppState) -> Router {
     Router::new()
         .route("/register", post(register))
+        .route("/upgrade", post(upgrade))
         .route("/confirm", post(confirm))
         .route("/unregister", post(unregister))
         .route("/login", post(login))
+        .route("/reauthenticate", post(reauthenticate))
         .with_state(state)
 }
 
@@ -28,40 +41,119 @@ pub fn gen_service(state: AppState) -> Router {
         (status = 200)
     )
 )]
-pub async fn register(State(state): State<AppState>, ValidatedJson(input): ValidatedJson<RegisterInput>) -> Result<StatusCode, AppError> {
-    let token = state.service.email_auth.register(&input.name, &input.email, &input.password).await?;
+pub async fn register(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Locale(locale): Locale,
+    ValidatedJson(input): ValidatedJson<RegisterInput>,
+) -> Result<StatusCode, AppError> {
+    let registration = state
+        .service
+        .email_auth
+        .register(
+            &input.name,
+            &input.email,
+            &input.password,
+            input.invitation_code.as_deref(),
+            &input.legal_document_ids,
+            &client,
+        )
+        .await?;
+    send_registration_email(&state, &input, registration, &locale).await?;
 
-    let email_confirm_url = Url::parse_with_params(
-        format!("{}auth/register/email/confirm", state.conf.web.origin.as_str()).as_str(),
-        &[("token", token)],
-    )
-    .unwrap()
-    .to_string();
+    Ok(StatusCode::OK)
+}
 
-    let job_id = state.service.tsid_provider.gen().to_string();
-    state
+// Turns the signed-in guest into an email account once the address is confirmed, keeping its jobs and history.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/email/upgrade",
+    request_body = RegisterInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn upgrade(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    client: ClientInfo,
+    Locale(locale): Locale,
+    ValidatedJson(input): ValidatedJson<RegisterInput>,
+) -> Result<StatusCode, AppError> {
+    let registration = state
         .service
-        .email_send_job_creator
-        .create_email_confirm_job(
-            &job_id,
+        .email_auth
+        .upgrade_guest(
+            &user,
             &input.name,
             &input.email,
-            &state.conf.email.from_email_address,
-            &email_confirm_url,
+            &input.password,
+            input.invitation_code.as_deref(),
+            &input.legal_document_ids,
+            &client,
         )
         .await?;
+    send_registration_email(&state, &input, registration, &locale).await?;
 
     Ok(StatusCode::OK)
 }
 
+// Both outcomes answer 200 and send one email, so the response never reveals whether the address is taken.
+async fn send_registration_email(state: &AppState, input: &RegisterInput, registration: EmailRegistration, locale: &str) -> Result<(), AppError> {
+    let job_id = state.service.tsid_provider.gen().to_string();
+    match registration {
+        EmailRegistration::Created { confirm_token } => {
+            let email_confirm_url = Url::parse_with_params(
+                format!("{}auth/register/email/confirm", state.conf.web.origin.as_str()).as_str(),
+                &[("token", confirm_token)],
+            )
+            .unwrap()
+            .to_string();
+
+            state
+                .service
+                .email_send_job_creator
+                .create_email_confirm_job(
+                    &job_id,
+                    &input.name,
+                    &input.email,
+                    &state.conf.email.from_email_address,
+                    &email_confirm_url,
+                    locale,
+                )
+                .await?;
+        }
+        EmailRegistration::AlreadyRegistered => {
+            state
+                .service
+                .email_send_job_creator
+                .create_account_exists_job(
+                    &job_id,
+                    &input.email,
+                    &state.conf.email.from_email_address,
+                    &format!("{}auth/login", state.conf.web.origin.as_str()),
+                    locale,
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct RegisterInput {
     #[validate(length(min = 2))]
     pub name: String,
-    #[validate(email)]
     pub email: String,
-    #[validate(length(min = 4))]
     pub password: String,
+    pub invitation_code: Option<String>,
+    // Ids of the current legal documents the user agreed to on the sign-up form.
+    #[serde(default)]
+    pub legal_document_ids: Vec<String>,
 }
 
 #[utoipa::path(
@@ -72,11 +164,21 @@ pub struct RegisterInput {
         (status = 200)
     )
 )]
-pub async fn confirm(State(state): State<AppState>, ValidatedJson(input): ValidatedJson<ConfirmInput>) -> Result<Json<AuthToken>, AppError> {
-    let user_id = state.service.email_auth.confirm(&input.token).await?;
-    let auth_token = state.service.token.create(&user_id).await?;
+pub async fn confirm(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Locale(locale): Locale,
+    session_mode: SessionMode,
+    jar: CookieJar,
+    ValidatedJson(input): ValidatedJson<ConfirmInput>,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let user_id = state.service.email_auth.confirm(&input.token, &client).await?;
+    state.service.user.init_locale(&user_id, &locale).await?;
+    let (session_id, auth_token) = state.service.token.create(&user_id).await?;
+    sign_in::notify_new_sign_in(&state, &user_id, &session_id, &client).await;
+    let (jar, auth_token) = cookie::issue_session(&state, jar, session_mode, auth_token);
 
-    Ok(Json(auth_token))
+    Ok((jar, Json(auth_token)))
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
@@ -91,9 +193,14 @@ pub struct ConfirmInput {
         (status = 200)
     )
 )]
-pub async fn unregister(State(state): State<AppState>, user: User) -> Result<StatusCode, AppError> {
-    state.service.email_auth.unregister(user.id.as_str()).await?;
-    Ok(StatusCode::OK)
+pub async fn unregister(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    client: ClientInfo,
+    jar: CookieJar,
+) -> Result<(CookieJar, StatusCode), AppError> {
+    state.service.user.unregister(user.id.as_str(), &client).await?;
+    Ok((cookie::remove_session(jar), StatusCode::OK))
 }
 
 #[utoipa::path(
@@ -104,17 +211,54 @@ pub async fn unregister(State(state): State<AppState>, user: User) -> Result<Sta
         (status = 200, body = AuthToken)
     )
 )]
-async fn login(State(state): State<AppState>, ValidatedJson(input): ValidatedJson<LoginInput>) -> Result<Json<AuthToken>, AppError> {
-    let user_id = state.service.email_auth.login(&input.email, &input.password).await?;
-    let auth_token = state.service.token.create(&user_id).await?;
+async fn login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    session_mode: SessionMode,
+    jar: CookieJar,
+    ValidatedJson(input): ValidatedJson<LoginInput>,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let user_id = state.service.email_auth.login(&input.email, &input.password, &client).await?;
+    let (session_id, auth_token) = state.service.token.create(&user_id).await?;
+    sign_in::notify_new_sign_in(&state, &user_id, &session_id, &client).await;
+    let (jar, auth_token) = cookie::issue_session(&state, jar, session_mode, auth_token);
 
-    Ok(Json(auth_token))
+    Ok((jar, Json(auth_token)))
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct LoginInput {
     #[validate(email)]
     pub email: String,
-    #[validate(length(min = 8))]
+    #[validate(length(min = 1))]
+    pub password: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/email/reauthenticate",
+    request_body = ReauthenticateInput,
+    responses(
+        (status = 200, body = AuthToken)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn reauthenticate(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    client: ClientInfo,
+    ValidatedJson(input): ValidatedJson<ReauthenticateInput>,
+) -> Result<Json<AuthToken>, AppError> {
+    state.service.email_auth.reauthenticate(&user.id, &input.password, &client).await?;
+    let auth_token = state.service.token.create_elevated(&user.id)?;
+
+    Ok(Json(auth_token))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ReauthenticateInput {
+    #[validate(length(min = 1))]
     pub password: String,
 }
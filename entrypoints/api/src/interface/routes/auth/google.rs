@@ -3,24 +3,33 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use axum_extra::extract::cookie::{Cookie, SignedCookieJar};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SignedCookieJar};
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use opxs_auth::shared::model::{AuthToken, User};
+use opxs_auth::shared::model::{AuthToken, ClientInfo};
 use opxs_base::AppError;
 
-use crate::shared::state::AppState;
+use crate::{
+    interface::{
+        cookie::{self, SessionMode},
+        extractors::{Locale, NonImpersonatedUser, RecentlyAuthenticatedUser},
+        routes::auth::sign_in,
+    },
+    shared::state::{AppState, PreviousCookieKey},
+};
 
 #[allow(unused)]
 pub fn gen_service(state: AppState) -> Router {
     Router::new()
         .route("/nonce", get(nonce))
         .route("/register", post(register))
+        .route("/upgrade", post(upgrade))
         .route("/login", post(login))
         .route("/unregister", post(unregister))
+        .route("/reauthenticate", post(reauthenticate))
         .with_state(state)
 }
 
@@ -53,9 +62,14 @@ pub struct NonceOutput {
 pub async fn register(
     State(state): State<AppState>,
     jar: SignedCookieJar,
+    previous_jar: SignedCookieJar<PreviousCookieKey>,
+    session_jar: CookieJar,
+    session_mode: SessionMode,
+    client: ClientInfo,
+    Locale(locale): Locale,
     Json(input): Json<RegisterInput>,
-) -> Result<(SignedCookieJar, Json<AuthToken>), AppError> {
-    let cookie_nonce: Option<String> = jar.get("nonce").map(|cookie| cookie.value().to_owned());
+) -> Result<(SignedCookieJar, CookieJar, Json<AuthToken>), AppError> {
+    let cookie_nonce = get_nonce(&jar, &previous_jar);
     if cookie_nonce.is_none() {
         return Err(AppError::InvalidRequest(anyhow::anyhow!("Nonce not found")));
     }
@@ -65,18 +79,73 @@ pub async fn register(
     let user_id = state
         .service
         .google_auth
-        .register(&input.code, &input.redirect_uri, &cookie_nonce)
+        .register(
+            &input.code,
+            &input.redirect_uri,
+            &cookie_nonce,
+            input.invitation_code.as_deref(),
+            &input.legal_document_ids,
+            &client,
+        )
         .await?;
+    state.service.user.init_locale(&user_id, &locale).await?;
 
-    let auth_token = state.service.token.create(&user_id).await?;
+    let (session_id, auth_token) = state.service.token.create(&user_id).await?;
+    sign_in::notify_new_sign_in(&state, &user_id, &session_id, &client).await;
+    let (session_jar, auth_token) = cookie::issue_session(&state, session_jar, session_mode, auth_token);
 
-    Ok((jar, Json(auth_token)))
+    Ok((jar, session_jar, Json(auth_token)))
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct RegisterInput {
     pub redirect_uri: String,
     pub code: String,
+    pub invitation_code: Option<String>,
+    #[serde(default)]
+    pub legal_document_ids: Vec<String>,
+}
+
+// The guest keeps its id, so the current session stays valid and no new token is issued.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/google/upgrade",
+    request_body = RegisterInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn upgrade(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    jar: SignedCookieJar,
+    previous_jar: SignedCookieJar<PreviousCookieKey>,
+    client: ClientInfo,
+    Locale(locale): Locale,
+    Json(input): Json<RegisterInput>,
+) -> Result<(SignedCookieJar, StatusCode), AppError> {
+    let nonce = get_nonce(&jar, &previous_jar).ok_or(AppError::InvalidRequest(anyhow::anyhow!("Nonce not found")))?;
+    let jar = jar.remove(Cookie::named("nonce"));
+
+    state
+        .service
+        .google_auth
+        .upgrade_guest(
+            &user.id,
+            &input.code,
+            &input.redirect_uri,
+            &nonce,
+            input.invitation_code.as_deref(),
+            &input.legal_document_ids,
+            &client,
+        )
+        .await?;
+    state.service.user.init_locale(&user.id, &locale).await?;
+
+    Ok((jar, StatusCode::OK))
 }
 
 #[utoipa::path(
@@ -86,18 +155,28 @@ pub struct RegisterInput {
         (status = 200, body = AuthToken)
     )
 )]
-pub async fn login(State(state): State<AppState>, jar: SignedCookieJar, Json(input): Json<LoginInput>) -> Result<Json<AuthToken>, AppError> {
-    let nonce: Option<String> = jar.get("nonce").map(|cookie| cookie.value().to_owned());
+pub async fn login(
+    State(state): State<AppState>,
+    jar: SignedCookieJar,
+    previous_jar: SignedCookieJar<PreviousCookieKey>,
+    session_jar: CookieJar,
+    session_mode: SessionMode,
+    client: ClientInfo,
+    Json(input): Json<LoginInput>,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let nonce = get_nonce(&jar, &previous_jar);
     if nonce.is_none() {
         return Err(AppError::InvalidRequest(anyhow::anyhow!("Nonce not found")));
     }
     let nonce = nonce.unwrap();
 
-    let user_id = state.service.google_auth.login(&input.code, &input.redirect_uri, &nonce).await?;
+    let user_id = state.service.google_auth.login(&input.code, &input.redirect_uri, &nonce, &client).await?;
 
-    let auth_token = state.service.token.create(&user_id).await?;
+    let (session_id, auth_token) = state.service.token.create(&user_id).await?;
+    sign_in::notify_new_sign_in(&state, &user_id, &session_id, &client).await;
+    let (session_jar, auth_token) = cookie::issue_session(&state, session_jar, session_mode, auth_token);
 
-    Ok(Json(auth_token))
+    Ok((session_jar, Json(auth_token)))
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -106,6 +185,43 @@ pub struct LoginInput {
     pub code: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/google/reauthenticate",
+    request_body = LoginInput,
+    responses(
+        (status = 200, body = AuthToken)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn reauthenticate(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    jar: SignedCookieJar,
+    previous_jar: SignedCookieJar<PreviousCookieKey>,
+    client: ClientInfo,
+    Json(input): Json<LoginInput>,
+) -> Result<Json<AuthToken>, AppError> {
+    let nonce = get_nonce(&jar, &previous_jar).ok_or(AppError::InvalidRequest(anyhow::anyhow!("Nonce not found")))?;
+
+    state
+        .service
+        .google_auth
+        .reauthenticate(&user.id, &input.code, &input.redirect_uri, &nonce, &client)
+        .await?;
+    let auth_token = state.service.token.create_elevated(&user.id)?;
+
+    Ok(Json(auth_token))
+}
+
+fn get_nonce(jar: &SignedCookieJar, previous_jar: &SignedCookieJar<PreviousCookieKey>) -> Option<String> {
+    jar.get("nonce")
+        .or_else(|| previous_jar.get("nonce"))
+        .map(|cookie| cookie.value().to_owned())
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/auth/google/unregister",
@@ -113,7 +229,12 @@ pub struct LoginInput {
         (status = 200)
     )
 )]
-pub async fn unregister(State(state): State<AppState>, user: User) -> Result<StatusCode, AppError> {
-    state.service.google_auth.unregister(user.id.as_str()).await?;
-    Ok(StatusCode::OK)
+pub async fn unregister(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    client: ClientInfo,
+    jar: CookieJar,
+) -> Result<(CookieJar, StatusCode), AppError> {
+    state.service.user.unregister(user.id.as_str(), &client).await?;
+    Ok((cookie::remove_session(jar), StatusCode::OK))
 }
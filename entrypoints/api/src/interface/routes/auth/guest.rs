@@ -0,0 +1,50 @@
+use axum::{extract::State, routing::post, Json, Router};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{AuthToken, ClientInfo};
+use opxs_base::AppError;
+
+use crate::{
+    interface::{
+        cookie::{self, SessionMode},
+        extractors::ValidatedJson,
+    },
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/login", post(login)).with_state(state)
+}
+
+// Signs in as the guest bound to this device, creating it on first use.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/guest/login",
+    request_body = LoginInput,
+    responses(
+        (status = 200, body = AuthToken)
+    )
+)]
+pub async fn login(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    session_mode: SessionMode,
+    jar: CookieJar,
+    ValidatedJson(input): ValidatedJson<LoginInput>,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let user_id = state.service.guest.sign_in(&input.device_id, &client).await?;
+    let (_, auth_token) = state.service.token.create(&user_id).await?;
+    let (jar, auth_token) = cookie::issue_session(&state, jar, session_mode, auth_token);
+
+    Ok((jar, Json(auth_token)))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct LoginInput {
+    #[validate(length(min = 32, max = 255))]
+    pub device_id: String,
+}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use url::Url;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{Invitation, InvitationRedemption, User};
+use opxs_base::AppError;
+
+use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/", post(create).get(list))
+        .route("/redemptions", get(redemptions))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/invitation",
+    request_body = CreateInput,
+    responses(
+        (status = 200, body = Invitation)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    user: User,
+    ValidatedJson(input): ValidatedJson<CreateInput>,
+) -> Result<Json<Invitation>, AppError> {
+    let invitation = state
+        .service
+        .invitation
+        .create(&user, input.email.as_deref(), input.max_uses, input.expires_in_days)
+        .await?;
+
+    if let Some(email) = invitation.email.as_ref() {
+        let invitation_url = Url::parse_with_params(
+            format!("{}auth/register", state.conf.web.origin.as_str()).as_str(),
+            &[("invitation_code", invitation.code.as_str())],
+        )
+        .unwrap()
+        .to_string();
+
+        // Written in the invitee's language when the address already has an account, never the inviter's.
+        let locale = state.service.user.get_locale_by_email(email).await?;
+        let job_id = state.service.tsid_provider.gen().to_string();
+        state
+            .service
+            .email_send_job_creator
+            .create_invitation_job(&job_id, &user.name, email, &state.conf.email.from_email_address, &invitation_url, &locale)
+            .await?;
+    }
+
+    Ok(Json(invitation))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateInput {
+    #[validate(email)]
+    pub email: Option<String>,
+    #[validate(range(min = 1))]
+    pub max_uses: Option<i32>,
+    #[validate(range(min = 1))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/invitation",
+    responses(
+        (status = 200, body = [Invitation])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn list(State(state): State<AppState>, user: User) -> Result<Json<Vec<Invitation>>, AppError> {
+    let invitations = state.service.invitation.get_invitations(&user.id).await?;
+    Ok(Json(invitations))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/invitation/redemptions",
+    responses(
+        (status = 200, body = [InvitationRedemption])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn redemptions(State(state): State<AppState>, user: User) -> Result<Json<Vec<InvitationRedemption>>, AppError> {
+    let redemptions = state.service.invitation.get_redemptions(&user.id).await?;
+    Ok(Json(redemptions))
+}
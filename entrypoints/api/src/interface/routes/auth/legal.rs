@@ -0,0 +1,81 @@
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use hyper::StatusCode;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{ClientInfo, LegalDocument};
+use opxs_base::AppError;
+
+use crate::{
+    interface::extractors::{NonImpersonatedUser, ValidatedJson},
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/documents", get(documents))
+        .route("/pending", get(pending))
+        .route("/accept", post(accept))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/legal/documents",
+    responses(
+        (status = 200, body = [LegalDocument])
+    )
+)]
+pub async fn documents(State(state): State<AppState>) -> Result<Json<Vec<LegalDocument>>, AppError> {
+    let documents = state.service.legal.get_current_documents().await?;
+    Ok(Json(documents))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/legal/pending",
+    responses(
+        (status = 200, body = [LegalDocument])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn pending(State(state): State<AppState>, NonImpersonatedUser(user): NonImpersonatedUser) -> Result<Json<Vec<LegalDocument>>, AppError> {
+    let documents = state.service.legal.get_pending_documents(&user.id).await?;
+    Ok(Json(documents))
+}
+
+// Consent is the user's own act, so an administrator impersonating them can't accept on their behalf.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/legal/accept",
+    request_body = AcceptInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn accept(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    client: ClientInfo,
+    ValidatedJson(input): ValidatedJson<AcceptInput>,
+) -> Result<StatusCode, AppError> {
+    state.service.legal.accept(&user.id, &input.document_ids, &client).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AcceptInput {
+    #[validate(length(min = 1))]
+    pub document_ids: Vec<String>,
+}
@@ -0,0 +1,57 @@
+use axum::{extract::State, routing::get, Json, Router};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::User;
+use opxs_base::AppError;
+
+use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new().route("/", get(get_setting).put(update_setting)).with_state(state)
+}
+
+// The language emails are written in for this user; empty means the default one.
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/locale",
+    responses(
+        (status = 200, body = LocaleSetting)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn get_setting(State(state): State<AppState>, user: User) -> Result<Json<LocaleSetting>, AppError> {
+    let locale = state.service.user.get_locale(&user.id).await?;
+    Ok(Json(LocaleSetting { locale }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/auth/locale",
+    request_body = LocaleSetting,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn update_setting(
+    State(state): State<AppState>,
+    user: User,
+    ValidatedJson(input): ValidatedJson<LocaleSetting>,
+) -> Result<StatusCode, AppError> {
+    state.service.user.update_locale(&user.id, &input.locale).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct LocaleSetting {
+    #[validate(length(max = 35))]
+    pub locale: String,
+}
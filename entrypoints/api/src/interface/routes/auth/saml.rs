@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use serde::Deserialize;
+use url::Url;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{AuthToken, ClientInfo};
+use opxs_base::AppError;
+
+use crate::{
+    interface::{
+        cookie::{self, SessionMode},
+        extractors::ValidatedJson,
+        routes::auth::sign_in,
+    },
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/token", post(token))
+        .route("/:organization_id/metadata", get(metadata))
+        .route("/:organization_id/login", get(login))
+        .route("/:organization_id/acs", post(acs))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/saml/{organization_id}/metadata",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, content_type = "application/samlmetadata+xml")
+    )
+)]
+pub async fn metadata(State(state): State<AppState>, Path(organization_id): Path<String>) -> impl IntoResponse {
+    let xml = state.service.saml_auth.sp_metadata(&organization_id);
+    ([(header::CONTENT_TYPE, "application/samlmetadata+xml")], xml)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/saml/{organization_id}/login",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 303)
+    )
+)]
+pub async fn login(State(state): State<AppState>, Path(organization_id): Path<String>) -> Result<Redirect, AppError> {
+    let redirect_url = state.service.saml_auth.start(&organization_id).await?;
+    Ok(Redirect::to(&redirect_url))
+}
+
+// The IdP posts here from the browser, so the session is handed to the web app through a one-time code.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/saml/{organization_id}/acs",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    request_body(content = AcsInput, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303)
+    )
+)]
+pub async fn acs(
+    State(state): State<AppState>,
+    Path(organization_id): Path<String>,
+    client: ClientInfo,
+    Form(input): Form<AcsInput>,
+) -> Result<Redirect, AppError> {
+    let login_code = state
+        .service
+        .saml_auth
+        .acs(&organization_id, &input.saml_response, &input.relay_state, &client)
+        .await?;
+
+    let callback_url = Url::parse_with_params(
+        format!("{}auth/saml/callback", state.conf.web.origin.as_str()).as_str(),
+        &[("code", login_code)],
+    )
+    .unwrap()
+    .to_string();
+
+    Ok(Redirect::to(&callback_url))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AcsInput {
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: String,
+    #[serde(rename = "RelayState")]
+    pub relay_state: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/saml/token",
+    request_body = TokenInput,
+    responses(
+        (status = 200, body = AuthToken)
+    )
+)]
+pub async fn token(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    session_mode: SessionMode,
+    jar: CookieJar,
+    ValidatedJson(input): ValidatedJson<TokenInput>,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let user_id = state.service.saml_auth.exchange(&input.code).await?;
+    let (session_id, auth_token) = state.service.token.create(&user_id).await?;
+    sign_in::notify_new_sign_in(&state, &user_id, &session_id, &client).await;
+    let (jar, auth_token) = cookie::issue_session(&state, jar, session_mode, auth_token);
+
+    Ok((jar, Json(auth_token)))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct TokenInput {
+    pub code: String,
+}
@@ -0,0 +1,124 @@
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use url::Url;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{ClientInfo, User};
+use opxs_base::AppError;
+use opxs_email_send::NewSignInRequestParam;
+
+use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/revoke", post(revoke))
+        .route("/notification", get(get_notification).put(update_notification))
+        .with_state(state)
+}
+
+// Called right after an interactive sign-in; mails the owner when the client is new to the account.
+// The session already exists at this point, so a failure is only logged and never fails the sign-in.
+pub async fn notify_new_sign_in(state: &AppState, user_id: &str, session_id: &str, client: &ClientInfo) {
+    if let Err(e) = notify_new_sign_in_sub(state, user_id, session_id, client).await {
+        warn!("failed to notify new sign-in: {} {:?}", user_id, e);
+    }
+}
+
+async fn notify_new_sign_in_sub(state: &AppState, user_id: &str, session_id: &str, client: &ClientInfo) -> Result<(), AppError> {
+    let notice = match state.service.sign_in.record(user_id, session_id, client).await? {
+        Some(notice) => notice,
+        None => return Ok(()),
+    };
+
+    let revoke_url = Url::parse_with_params(
+        format!("{}auth/sign-in/revoke", state.conf.web.origin.as_str()).as_str(),
+        &[("code", notice.revoke_code)],
+    )
+    .unwrap()
+    .to_string();
+
+    let job_id = state.service.tsid_provider.gen().to_string();
+    let param = NewSignInRequestParam {
+        to_email_address: notice.email,
+        from_email_address: state.conf.email.from_email_address.clone(),
+        user_name: notice.user_name,
+        signed_in_at: notice.signed_in_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        ip_address: client.ip_address.clone().unwrap_or_else(|| "-".to_string()),
+        user_agent: client.user_agent.clone().unwrap_or_else(|| "-".to_string()),
+        revoke_url,
+        locale: notice.locale,
+    };
+    state.service.email_send_job_creator.create_new_sign_in_job(&job_id, &param).await?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/sign-in/revoke",
+    request_body = RevokeInput,
+    responses(
+        (status = 200)
+    )
+)]
+pub async fn revoke(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    ValidatedJson(input): ValidatedJson<RevokeInput>,
+) -> Result<StatusCode, AppError> {
+    state.service.sign_in.revoke(&input.code, &client).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct RevokeInput {
+    pub code: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/sign-in/notification",
+    responses(
+        (status = 200, body = NotificationSetting)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn get_notification(State(state): State<AppState>, user: User) -> Result<Json<NotificationSetting>, AppError> {
+    let enabled = state.service.sign_in.get_notification_enabled(&user.id).await?;
+    Ok(Json(NotificationSetting { enabled }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/auth/sign-in/notification",
+    request_body = NotificationSetting,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn update_notification(
+    State(state): State<AppState>,
+    user: User,
+    ValidatedJson(input): ValidatedJson<NotificationSetting>,
+) -> Result<StatusCode, AppError> {
+    state.service.sign_in.update_notification_enabled(&user.id, input.enabled).await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Validate)]
+pub struct NotificationSetting {
+    pub enabled: bool,
+}
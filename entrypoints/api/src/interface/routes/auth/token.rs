@@ -3,15 +3,22 @@ use axum::{
     routing::{delete, post},
     Json, Router,
 };
-use hyper::StatusCode;
+use axum_extra::extract::cookie::CookieJar;
+use hyper::{HeaderMap, StatusCode};
 use serde::Deserialize;
 use utoipa::ToSchema;
 use validator::Validate;
 
-use opxs_auth::shared::model::{AuthToken, User};
+use opxs_auth::shared::model::{AuthToken, ClientInfo};
 use opxs_base::AppError;
 
-use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+use crate::{
+    interface::{
+        cookie::{self, SessionMode},
+        extractors::{NonImpersonatedUser, ValidatedJson},
+    },
+    shared::state::AppState,
+};
 
 #[allow(unused)]
 pub fn gen_service(state: AppState) -> Router {
@@ -29,15 +36,33 @@ pub fn gen_service(state: AppState) -> Router {
         (status = 200, body = AuthToken)
     )
 )]
-pub async fn refresh_token(State(state): State<AppState>, ValidatedJson(input): ValidatedJson<RefreshInput>) -> Result<Json<AuthToken>, AppError> {
-    let auth_token = state.service.token.refresh(&input.refresh_token).await?;
+pub async fn refresh_token(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    headers: HeaderMap,
+    jar: CookieJar,
+    ValidatedJson(input): ValidatedJson<RefreshInput>,
+) -> Result<(CookieJar, Json<AuthToken>), AppError> {
+    let (session_mode, refresh_token) = match input.refresh_token {
+        Some(refresh_token) => (SessionMode::Body, refresh_token),
+        None => {
+            cookie::verify_csrf_token(&jar, &headers)?;
+            let refresh_token = cookie::get_refresh_token(&jar).ok_or(AppError::RefreshTokenNotFound)?;
+            (SessionMode::Cookie, refresh_token)
+        }
+    };
+
+    let auth_token = state.service.token.refresh(&refresh_token, &client).await?;
+    let (jar, auth_token) = cookie::issue_session(&state, jar, session_mode, auth_token);
 
-    Ok(Json(auth_token))
+    Ok((jar, Json(auth_token)))
 }
 
 #[derive(Deserialize, ToSchema, Validate)]
 pub struct RefreshInput {
-    pub refresh_token: String,
+    // Omitted in cookie mode, where the refresh token is read from the HttpOnly cookie.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
 }
 
 #[utoipa::path(
@@ -47,7 +72,12 @@ pub struct RefreshInput {
         (status = 200)
     )
 )]
-pub async fn delete_token(State(state): State<AppState>, user: User) -> Result<StatusCode, AppError> {
-    state.service.token.delete(user.id.as_str()).await?;
-    Ok(StatusCode::OK)
+pub async fn delete_token(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    client: ClientInfo,
+    jar: CookieJar,
+) -> Result<(CookieJar, StatusCode), AppError> {
+    state.service.token.delete(user.id.as_str(), &client).await?;
+    Ok((cookie::remove_session(jar), StatusCode::OK))
 }
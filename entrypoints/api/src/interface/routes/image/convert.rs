@@ -3,19 +3,25 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use chrono::NaiveDateTime;
+use opxs_auth::shared::model::{ClientInfo, User, UserRole};
 use opxs_base::AppError;
 use opxs_image_convert::{ImageConvertJobStatus, ImageFormat};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 use validator::Validate;
 
-use crate::{interface::extractors::ValidatedJson, shared::state::AppState};
+use crate::{
+    interface::extractors::{OptionalUser, OrganizationContext, ValidatedJson},
+    shared::state::AppState,
+};
 
 #[allow(unused)]
 pub fn gen_service(state: AppState) -> Router {
     Router::new()
         .route("/upload", post(upload))
         .route("/status", get(status))
+        .route("/jobs", get(jobs))
         .with_state(state)
 }
 
@@ -27,12 +33,31 @@ pub fn gen_service(state: AppState) -> Router {
         (status = 200, body = UploadOutput)
     )
 )]
-pub async fn upload(State(state): State<AppState>, ValidatedJson(input): ValidatedJson<UploadInput>) -> Result<Json<UploadOutput>, AppError> {
+pub async fn upload(
+    State(state): State<AppState>,
+    OptionalUser(user): OptionalUser,
+    client: ClientInfo,
+    OrganizationContext(organization_id): OrganizationContext,
+    ValidatedJson(input): ValidatedJson<UploadInput>,
+) -> Result<Json<UploadOutput>, AppError> {
+    if user.is_none() {
+        state.service.guest.throttle_anonymous_convert(&client).await?;
+    }
+
     let job_id = state.service.tsid_provider.gen().to_string();
+    let is_guest = user.as_ref().is_some_and(|n| n.role == UserRole::Guest);
+    let user_id = user.map(|n| n.id);
     let upload_uri = state
         .service
         .image_convert_job_creator
-        .create_image_convert_job(&job_id, &input.source_filename, &input.target_image_format)
+        .create_image_convert_job(
+            &job_id,
+            user_id.as_deref(),
+            organization_id.as_deref(),
+            &input.source_filename,
+            &input.target_image_format,
+            is_guest,
+        )
         .await?;
 
     Ok(Json(UploadOutput { job_id, upload_uri }))
@@ -74,3 +99,229 @@ pub struct StatusOutput {
     pub status: ImageConvertJobStatus,
     pub download_uri: Option<String>,
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/convert/file/jobs",
+    params(JobsInput),
+    responses(
+        (status = 200, body = [JobOutput])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn jobs(
+    State(state): State<AppState>,
+    user: User,
+    OrganizationContext(organization_id): OrganizationContext,
+    input: Query<JobsInput>,
+) -> Result<Json<Vec<JobOutput>>, AppError> {
+    let limit = input.limit.unwrap_or(50).clamp(1, 200);
+    let jobs = state
+        .service
+        .image_convert_job_creator
+        .get_jobs(&user.id, organization_id.as_deref(), limit)
+        .await?;
+
+    Ok(Json(
+        jobs.into_iter()
+            .map(|n| JobOutput {
+                job_id: n.id,
+                user_id: n.user_id,
+                organization_id: n.organization_id,
+                status: n.status,
+                created_at: n.created_at,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct JobsInput {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct JobOutput {
+    pub job_id: String,
+    pub user_id: Option<String>,
+    pub organization_id: Option<String>,
+    pub status: ImageConvertJobStatus,
+    pub created_at: NaiveDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        http::{
+            header::{AUTHORIZATION, CONTENT_TYPE},
+            Request, StatusCode,
+        },
+    };
+    use chrono::Duration;
+    use tower::ServiceExt;
+
+    use core_base::{
+        clock::{SystemClock, SystemClockUtc},
+        random_bytes::RandomBytesProviderImpl,
+        tsid::TsidProviderImpl,
+    };
+    use core_cloud::aws::{s3::S3ClientMock, sqs::SqsSenderMock};
+
+    use opxs_auth::shared::{
+        jwt,
+        model::{LegalDocumentType, UserAuthenticationType},
+    };
+    use opxs_base::{
+        AppConfig, AppInfo, AuthConfig, CookieConfig, CookieSecretConfig, EmailBulkConfig, EmailConfig, EmailPolicyConfig, EmailRetryConfig,
+        EmailTransportConfig, GoogleAuthConfig, GuestConfig, ImageConvertConfig, InvitationConfig, JwtConfig, JwtSecretConfig, OutboxConfig,
+        PasswordPolicyConfig, PostgresConfig, RegisterPolicyConfig, RunMode, S3Config, SesConfig, UnregisterConfig, WebConfig,
+    };
+
+    use crate::shared::service::AppService;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn upload_requires_legal_acceptance_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, "15.1").await;
+        let db = fixture.db.clone();
+
+        let info = AppInfo { mode: RunMode::Local };
+        let conf = AppConfig {
+            postgres: PostgresConfig {
+                url: fixture.container.connection_string.clone(),
+            },
+            web: WebConfig {
+                origin: "https://localhost.omnius-labs.com/".to_string(),
+                cookie: CookieConfig {
+                    secret: CookieSecretConfig {
+                        current: None,
+                        previous: None,
+                    },
+                },
+            },
+            auth: AuthConfig {
+                jwt: JwtConfig {
+                    secret: JwtSecretConfig {
+                        current: "current".to_string(),
+                        previous: "retired".to_string(),
+                    },
+                },
+                google: GoogleAuthConfig {
+                    client_id: "".to_string(),
+                    client_secret: "".to_string(),
+                },
+                unregister: UnregisterConfig { grace_period_days: 30 },
+                register_policy: RegisterPolicyConfig {
+                    email: EmailPolicyConfig {
+                        allowed_domains: vec![],
+                        denied_domains: vec![],
+                        deny_disposable: true,
+                    },
+                    password: PasswordPolicyConfig {
+                        min_length: 8,
+                        max_length: 128,
+                        min_score: 2,
+                        deny_breached: true,
+                    },
+                },
+                invitation: InvitationConfig {
+                    required: false,
+                    expires_in_days: 7,
+                    max_uses: 1,
+                },
+                guest: GuestConfig {
+                    convert_quota: 10,
+                    retention_days: 30,
+                    login_limit_per_hour: 20,
+                    anonymous_convert_limit_per_hour: 10,
+                },
+            },
+            email: EmailConfig {
+                from_email_address: "Opxs <no-reply@example.com>".to_string(),
+                ses: SesConfig {
+                    configuration_set_name: "".to_string(),
+                },
+                retry: EmailRetryConfig {
+                    max_retry_count: 5,
+                    base_delay_seconds: 30,
+                },
+                bulk: EmailBulkConfig { batch_size: 50 },
+                transport: EmailTransportConfig::Local,
+            },
+            image_convert: ImageConvertConfig {
+                s3: S3Config { bucket: "".to_string() },
+            },
+            outbox: OutboxConfig {
+                relay_interval_seconds: 10,
+                relay_batch_size: 100,
+                base_delay_seconds: 10,
+            },
+        };
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let service = Arc::new(AppService::new(
+            &info,
+            &conf,
+            db.clone(),
+            system_clock.clone(),
+            Arc::new(RandomBytesProviderImpl),
+            Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+            Arc::new(SqsSenderMock::new()),
+            Arc::new(SqsSenderMock::new()),
+            Arc::new(S3ClientMock::new()),
+        ));
+        let state = AppState::new_with_service(info, conf.clone(), db.clone(), service.clone()).unwrap();
+
+        // a signed-in user who hasn't accepted the current terms
+        let now = system_clock.now();
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind("test_user_id")
+        .bind("test_user_id")
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        service
+            .legal
+            .publish(&LegalDocumentType::TermsOfService, "1.0", "https://example.com/tos/1.0", None)
+            .await
+            .unwrap();
+        let access_token = jwt::sign(&conf.auth.jwt.secret.current, "test_user_id", Duration::minutes(30), now).unwrap();
+
+        let upload = |authorization: String| {
+            Request::builder()
+                .method("POST")
+                .uri("/upload")
+                .header(AUTHORIZATION, authorization)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"source_filename":"a.png","target_image_format":"jpg"}"#))
+                .unwrap()
+        };
+
+        // the pending acceptance is not swallowed into an anonymous upload
+        let res = gen_service(state.clone())
+            .oneshot(upload(format!("Bearer {}", access_token)))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        // neither is an invalid token
+        let res = gen_service(state.clone()).oneshot(upload("Bearer invalid".to_string())).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use opxs_base::AppError;
+use opxs_email_send::CapturedEmail;
+
+use crate::shared::state::AppState;
+
+// Only mounted in RunMode::Local, where the local transport captures mail instead of sending it,
+// so confirmation links can be followed without signing in.
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/captured-emails", get(captured_emails))
+        .route("/captured-emails/:id", get(captured_email))
+        .route("/captured-emails/:id/html", get(captured_email_html))
+        .route("/captured-emails/:id/eml", get(captured_email_eml))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/local/captured-emails",
+    params(CapturedEmailsInput),
+    responses(
+        (status = 200, body = [CapturedEmail])
+    )
+)]
+pub async fn captured_emails(State(state): State<AppState>, input: Query<CapturedEmailsInput>) -> Result<Json<Vec<CapturedEmail>>, AppError> {
+    let emails = state
+        .service
+        .captured_email_repository
+        .get_list(input.to_address.as_deref(), input.before.as_ref(), input.limit.unwrap_or(50))
+        .await?;
+
+    Ok(Json(emails))
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct CapturedEmailsInput {
+    pub to_address: Option<String>,
+    pub before: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/local/captured-emails/{id}",
+    params(
+        ("id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = CapturedEmail)
+    )
+)]
+pub async fn captured_email(State(state): State<AppState>, Path(id): Path<String>) -> Result<Json<CapturedEmail>, AppError> {
+    find(&state, &id).await.map(Json)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/local/captured-emails/{id}/html",
+    params(
+        ("id" = String, Path,)
+    ),
+    responses(
+        (status = 200, content_type = "text/html")
+    )
+)]
+pub async fn captured_email_html(State(state): State<AppState>, Path(id): Path<String>) -> Result<Html<String>, AppError> {
+    find(&state, &id).await.map(|n| Html(n.html_body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/local/captured-emails/{id}/eml",
+    params(
+        ("id" = String, Path,)
+    ),
+    responses(
+        (status = 200, content_type = "message/rfc822")
+    )
+)]
+pub async fn captured_email_eml(State(state): State<AppState>, Path(id): Path<String>) -> Result<impl IntoResponse, AppError> {
+    let email = find(&state, &id).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "message/rfc822".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.eml\"", email.id)),
+        ],
+        email.raw,
+    ))
+}
+
+async fn find(state: &AppState, id: &str) -> Result<CapturedEmail, AppError> {
+    state.service.captured_email_repository.get(id).await?.ok_or(AppError::NotFound)
+}
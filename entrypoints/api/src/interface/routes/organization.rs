@@ -0,0 +1,434 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+use validator::Validate;
+
+use opxs_auth::shared::model::{
+    AuthToken, ClientInfo, Organization, OrganizationInvitation, OrganizationMember, OrganizationMembership, OrganizationRole, SamlProvider,
+    ScimToken, User,
+};
+use opxs_base::AppError;
+
+use crate::{
+    interface::extractors::{NonImpersonatedUser, ValidatedJson},
+    shared::state::AppState,
+};
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/", post(create).get(list))
+        .route("/invitations/accept", post(accept))
+        .route("/:organization_id/members", get(members))
+        .route("/:organization_id/members/:user_id", put(update_member).delete(remove_member))
+        .route("/:organization_id/invitations", post(invite).get(invitations))
+        .route("/:organization_id/token", post(token))
+        .route("/:organization_id/saml", get(saml).put(update_saml).delete(delete_saml))
+        .route("/:organization_id/scim/tokens", post(create_scim_token).get(scim_tokens))
+        .route("/:organization_id/scim/tokens/:token_id", delete(delete_scim_token))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/organization",
+    request_body = CreateInput,
+    responses(
+        (status = 200, body = Organization)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    user: User,
+    ValidatedJson(input): ValidatedJson<CreateInput>,
+) -> Result<Json<Organization>, AppError> {
+    let organization = state.service.organization.create(&user, &input.name).await?;
+    Ok(Json(organization))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateInput {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/organization",
+    responses(
+        (status = 200, body = [OrganizationMembership])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn list(State(state): State<AppState>, user: User) -> Result<Json<Vec<OrganizationMembership>>, AppError> {
+    let memberships = state.service.organization.get_memberships(&user.id).await?;
+    Ok(Json(memberships))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/organization/{organization_id}/members",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = [OrganizationMember])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn members(
+    State(state): State<AppState>,
+    user: User,
+    Path(organization_id): Path<String>,
+) -> Result<Json<Vec<OrganizationMember>>, AppError> {
+    let members = state.service.organization.get_members(&organization_id, &user.id).await?;
+    Ok(Json(members))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/organization/{organization_id}/members/{user_id}",
+    params(
+        ("organization_id" = String, Path,),
+        ("user_id" = String, Path,)
+    ),
+    request_body = UpdateMemberInput,
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn update_member(
+    State(state): State<AppState>,
+    user: User,
+    client: ClientInfo,
+    Path((organization_id, user_id)): Path<(String, String)>,
+    ValidatedJson(input): ValidatedJson<UpdateMemberInput>,
+) -> Result<StatusCode, AppError> {
+    state
+        .service
+        .organization
+        .update_role(&organization_id, &user.id, &user_id, input.role, &client)
+        .await?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateMemberInput {
+    pub role: OrganizationRole,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/organization/{organization_id}/members/{user_id}",
+    params(
+        ("organization_id" = String, Path,),
+        ("user_id" = String, Path,)
+    ),
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn remove_member(
+    State(state): State<AppState>,
+    user: User,
+    Path((organization_id, user_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    state.service.organization.remove_member(&organization_id, &user.id, &user_id).await?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/organization/{organization_id}/invitations",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    request_body = InviteInput,
+    responses(
+        (status = 200, body = OrganizationInvitation)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn invite(
+    State(state): State<AppState>,
+    user: User,
+    Path(organization_id): Path<String>,
+    ValidatedJson(input): ValidatedJson<InviteInput>,
+) -> Result<Json<OrganizationInvitation>, AppError> {
+    let invitation = state
+        .service
+        .organization
+        .invite(&organization_id, &user, &input.email, input.role)
+        .await?;
+
+    let invitation_url = Url::parse_with_params(
+        format!("{}organization/join", state.conf.web.origin.as_str()).as_str(),
+        &[("code", invitation.code.as_str())],
+    )
+    .unwrap()
+    .to_string();
+
+    let locale = state.service.user.get_locale_by_email(&invitation.email).await?;
+    let job_id = state.service.tsid_provider.gen().to_string();
+    state
+        .service
+        .email_send_job_creator
+        .create_invitation_job(
+            &job_id,
+            &user.name,
+            &invitation.email,
+            &state.conf.email.from_email_address,
+            &invitation_url,
+            &locale,
+        )
+        .await?;
+
+    Ok(Json(invitation))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct InviteInput {
+    #[validate(email)]
+    pub email: String,
+    pub role: OrganizationRole,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/organization/{organization_id}/invitations",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = [OrganizationInvitation])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn invitations(
+    State(state): State<AppState>,
+    user: User,
+    Path(organization_id): Path<String>,
+) -> Result<Json<Vec<OrganizationInvitation>>, AppError> {
+    let invitations = state.service.organization.get_invitations(&organization_id, &user.id).await?;
+    Ok(Json(invitations))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/organization/invitations/accept",
+    request_body = AcceptInput,
+    responses(
+        (status = 200, body = OrganizationInvitation)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn accept(
+    State(state): State<AppState>,
+    user: User,
+    ValidatedJson(input): ValidatedJson<AcceptInput>,
+) -> Result<Json<OrganizationInvitation>, AppError> {
+    let invitation = state.service.organization.accept(&input.code, &user.id).await?;
+    Ok(Json(invitation))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct AcceptInput {
+    pub code: String,
+}
+
+// Not available under impersonation, since the issued token wouldn't carry the actor claim.
+#[utoipa::path(
+    post,
+    path = "/api/v1/organization/{organization_id}/token",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = AuthToken)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn token(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    Path(organization_id): Path<String>,
+) -> Result<Json<AuthToken>, AppError> {
+    let token = state.service.organization.issue_token(&organization_id, &user.id).await?;
+    Ok(Json(token))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/organization/{organization_id}/saml",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = SamlProvider)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn saml(State(state): State<AppState>, user: User, Path(organization_id): Path<String>) -> Result<Json<SamlProvider>, AppError> {
+    let provider = state.service.saml_auth.get_provider(&organization_id, &user.id).await?;
+    Ok(Json(provider))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/organization/{organization_id}/saml",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    request_body = UpdateSamlInput,
+    responses(
+        (status = 200, body = SamlProvider)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn update_saml(
+    State(state): State<AppState>,
+    user: User,
+    Path(organization_id): Path<String>,
+    ValidatedJson(input): ValidatedJson<UpdateSamlInput>,
+) -> Result<Json<SamlProvider>, AppError> {
+    let provider = state
+        .service
+        .saml_auth
+        .update_provider(
+            &organization_id,
+            &user.id,
+            &input.idp_entity_id,
+            &input.idp_sso_url,
+            &input.idp_certificate,
+        )
+        .await?;
+    Ok(Json(provider))
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateSamlInput {
+    #[validate(length(min = 1, max = 1024))]
+    pub idp_entity_id: String,
+    #[validate(url)]
+    pub idp_sso_url: String,
+    pub idp_certificate: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/organization/{organization_id}/saml",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn delete_saml(State(state): State<AppState>, user: User, Path(organization_id): Path<String>) -> Result<StatusCode, AppError> {
+    state.service.saml_auth.delete_provider(&organization_id, &user.id).await?;
+    Ok(StatusCode::OK)
+}
+
+// The token is only shown in this response.
+#[utoipa::path(
+    post,
+    path = "/api/v1/organization/{organization_id}/scim/tokens",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = CreateScimTokenOutput)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn create_scim_token(
+    State(state): State<AppState>,
+    NonImpersonatedUser(user): NonImpersonatedUser,
+    Path(organization_id): Path<String>,
+) -> Result<Json<CreateScimTokenOutput>, AppError> {
+    let (scim_token, token) = state.service.scim.create_token(&organization_id, &user.id).await?;
+    Ok(Json(CreateScimTokenOutput { scim_token, token }))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreateScimTokenOutput {
+    pub scim_token: ScimToken,
+    pub token: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/organization/{organization_id}/scim/tokens",
+    params(
+        ("organization_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = [ScimToken])
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn scim_tokens(State(state): State<AppState>, user: User, Path(organization_id): Path<String>) -> Result<Json<Vec<ScimToken>>, AppError> {
+    let tokens = state.service.scim.get_tokens(&organization_id, &user.id).await?;
+    Ok(Json(tokens))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/organization/{organization_id}/scim/tokens/{token_id}",
+    params(
+        ("organization_id" = String, Path,),
+        ("token_id" = String, Path,)
+    ),
+    responses(
+        (status = 200)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn delete_scim_token(
+    State(state): State<AppState>,
+    user: User,
+    Path((organization_id, token_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    state.service.scim.delete_token(&organization_id, &user.id, &token_id).await?;
+    Ok(StatusCode::OK)
+}
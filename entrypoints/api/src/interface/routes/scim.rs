@@ -0,0 +1,790 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use opxs_auth::{
+    scim::{ScimGroupInput, ScimUserFilter, ScimUserInput},
+    shared::model::{ScimGroup, ScimGroupMember, ScimUser},
+};
+use opxs_base::AppError;
+
+use crate::{
+    interface::extractors::{ScimOrganization, ValidatedJson},
+    shared::state::AppState,
+};
+
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+const DEFAULT_COUNT: i64 = 100;
+const MAX_COUNT: i64 = 1000;
+
+#[allow(unused)]
+pub fn gen_service(state: AppState) -> Router {
+    Router::new()
+        .route("/Users", get(list_users).post(create_user))
+        .route("/Users/:user_id", get(get_user).put(replace_user).patch(patch_user).delete(delete_user))
+        .route("/Groups", get(list_groups).post(create_group))
+        .route(
+            "/Groups/:group_id",
+            get(get_group).put(replace_group).patch(patch_group).delete(delete_group),
+        )
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/scim/v2/Users",
+    params(ListInput),
+    responses(
+        (status = 200, body = ListOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Query(input): Query<ListInput>,
+) -> Result<Response, ScimError> {
+    let filter = match input.filter.as_deref().map(parse_filter).transpose()? {
+        None => None,
+        Some((attribute, value)) if attribute.eq_ignore_ascii_case("userName") => Some(ScimUserFilter::UserName(value)),
+        Some((attribute, value)) if attribute.eq_ignore_ascii_case("externalId") => Some(ScimUserFilter::ExternalId(value)),
+        Some((attribute, _)) => return Err(ScimError::invalid_filter(&format!("Unsupported filter attribute: {}", attribute))),
+    };
+
+    let (start_index, count) = input.page();
+    let (total, users) = state
+        .service
+        .scim
+        .get_users(&organization_id, filter.as_ref(), start_index, count)
+        .await?;
+    let resources = users.into_iter().map(|u| user_output(&state, u)).collect();
+
+    Ok(scim_response(StatusCode::OK, list_output(total, start_index, resources)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/scim/v2/Users/{user_id}",
+    params(
+        ("user_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = UserOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn get_user(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(user_id): Path<String>,
+) -> Result<Response, ScimError> {
+    let user = state.service.scim.get_user(&organization_id, &user_id).await?;
+    Ok(scim_response(StatusCode::OK, user_output(&state, user)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/scim/v2/Users",
+    request_body(content = UserInput, content_type = "application/scim+json"),
+    responses(
+        (status = 201, body = UserOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn create_user(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    ValidatedJson(input): ValidatedJson<UserInput>,
+) -> Result<Response, ScimError> {
+    let user = state.service.scim.create_user(&organization_id, &input.into()).await?;
+    Ok(scim_response(StatusCode::CREATED, user_output(&state, user)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/scim/v2/Users/{user_id}",
+    params(
+        ("user_id" = String, Path,)
+    ),
+    request_body(content = UserInput, content_type = "application/scim+json"),
+    responses(
+        (status = 200, body = UserOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn replace_user(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(user_id): Path<String>,
+    ValidatedJson(input): ValidatedJson<UserInput>,
+) -> Result<Response, ScimError> {
+    let user = state.service.scim.replace_user(&organization_id, &user_id, &input.into()).await?;
+    Ok(scim_response(StatusCode::OK, user_output(&state, user)))
+}
+
+// Patches are applied to the current representation and stored as a full replace.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/scim/v2/Users/{user_id}",
+    params(
+        ("user_id" = String, Path,)
+    ),
+    request_body(content = PatchInput, content_type = "application/scim+json"),
+    responses(
+        (status = 200, body = UserOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn patch_user(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(user_id): Path<String>,
+    ValidatedJson(input): ValidatedJson<PatchInput>,
+) -> Result<Response, ScimError> {
+    let user = state.service.scim.get_user(&organization_id, &user_id).await?;
+    let mut user_input = ScimUserInput {
+        user_name: user.user_name,
+        external_id: user.external_id,
+        name: user.name,
+        active: user.active,
+    };
+    for operation in input.operations.iter() {
+        apply_user_operation(&mut user_input, operation)?;
+    }
+
+    let user = state.service.scim.replace_user(&organization_id, &user_id, &user_input).await?;
+    Ok(scim_response(StatusCode::OK, user_output(&state, user)))
+}
+
+// Users are deactivated rather than deleted, so their history and memberships stay intact.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/scim/v2/Users/{user_id}",
+    params(
+        ("user_id" = String, Path,)
+    ),
+    responses(
+        (status = 204)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(user_id): Path<String>,
+) -> Result<StatusCode, ScimError> {
+    state.service.scim.deactivate_user(&organization_id, &user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/scim/v2/Groups",
+    params(ListInput),
+    responses(
+        (status = 200, body = ListOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn list_groups(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Query(input): Query<ListInput>,
+) -> Result<Response, ScimError> {
+    let display_name = match input.filter.as_deref().map(parse_filter).transpose()? {
+        None => None,
+        Some((attribute, value)) if attribute.eq_ignore_ascii_case("displayName") => Some(value),
+        Some((attribute, _)) => return Err(ScimError::invalid_filter(&format!("Unsupported filter attribute: {}", attribute))),
+    };
+
+    let (start_index, count) = input.page();
+    let (total, groups) = state
+        .service
+        .scim
+        .get_groups(&organization_id, display_name.as_deref(), start_index, count)
+        .await?;
+    let resources = groups.into_iter().map(|(group, members)| group_output(&state, group, members)).collect();
+
+    Ok(scim_response(StatusCode::OK, list_output(total, start_index, resources)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/scim/v2/Groups/{group_id}",
+    params(
+        ("group_id" = String, Path,)
+    ),
+    responses(
+        (status = 200, body = GroupOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn get_group(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(group_id): Path<String>,
+) -> Result<Response, ScimError> {
+    let (group, members) = state.service.scim.get_group(&organization_id, &group_id).await?;
+    Ok(scim_response(StatusCode::OK, group_output(&state, group, members)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/scim/v2/Groups",
+    request_body(content = GroupInput, content_type = "application/scim+json"),
+    responses(
+        (status = 201, body = GroupOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn create_group(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    ValidatedJson(input): ValidatedJson<GroupInput>,
+) -> Result<Response, ScimError> {
+    let (group, members) = state.service.scim.create_group(&organization_id, &input.into()).await?;
+    Ok(scim_response(StatusCode::CREATED, group_output(&state, group, members)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/scim/v2/Groups/{group_id}",
+    params(
+        ("group_id" = String, Path,)
+    ),
+    request_body(content = GroupInput, content_type = "application/scim+json"),
+    responses(
+        (status = 200, body = GroupOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn replace_group(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(group_id): Path<String>,
+    ValidatedJson(input): ValidatedJson<GroupInput>,
+) -> Result<Response, ScimError> {
+    let (group, members) = state.service.scim.replace_group(&organization_id, &group_id, &input.into()).await?;
+    Ok(scim_response(StatusCode::OK, group_output(&state, group, members)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/scim/v2/Groups/{group_id}",
+    params(
+        ("group_id" = String, Path,)
+    ),
+    request_body(content = PatchInput, content_type = "application/scim+json"),
+    responses(
+        (status = 200, body = GroupOutput, content_type = "application/scim+json")
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn patch_group(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(group_id): Path<String>,
+    ValidatedJson(input): ValidatedJson<PatchInput>,
+) -> Result<Response, ScimError> {
+    let (group, members) = state.service.scim.get_group(&organization_id, &group_id).await?;
+    let mut group_input = ScimGroupInput {
+        display_name: group.display_name,
+        external_id: group.external_id,
+        member_ids: members.into_iter().map(|m| m.user_id).collect(),
+    };
+    for operation in input.operations.iter() {
+        apply_group_operation(&mut group_input, operation)?;
+    }
+
+    let (group, members) = state.service.scim.replace_group(&organization_id, &group_id, &group_input).await?;
+    Ok(scim_response(StatusCode::OK, group_output(&state, group, members)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/scim/v2/Groups/{group_id}",
+    params(
+        ("group_id" = String, Path,)
+    ),
+    responses(
+        (status = 204)
+    ),
+    security(
+        ("bearer_token" = [])
+    )
+)]
+pub async fn delete_group(
+    State(state): State<AppState>,
+    ScimOrganization(organization_id): ScimOrganization,
+    Path(group_id): Path<String>,
+) -> Result<StatusCode, ScimError> {
+    state.service.scim.delete_group(&organization_id, &group_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct ListInput {
+    pub filter: Option<String>,
+    pub start_index: Option<i64>,
+    pub count: Option<i64>,
+}
+
+impl ListInput {
+    // startIndex is 1-based per RFC 7644.
+    fn page(&self) -> (i64, i64) {
+        let start_index = self.start_index.unwrap_or(1).max(1);
+        let count = self.count.unwrap_or(DEFAULT_COUNT).clamp(0, MAX_COUNT);
+        (start_index, count)
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ListOutput {
+    pub schemas: Vec<String>,
+    pub total_results: i64,
+    pub start_index: i64,
+    pub items_per_page: i64,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<Value>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInput {
+    #[validate(length(min = 1, max = 255))]
+    pub user_name: String,
+    pub external_id: Option<String>,
+    pub display_name: Option<String>,
+    pub name: Option<NameInput>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NameInput {
+    pub formatted: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+}
+
+impl NameInput {
+    fn full_name(&self) -> Option<String> {
+        if let Some(formatted) = self.formatted.as_ref().filter(|v| !v.is_empty()) {
+            return Some(formatted.clone());
+        }
+        let parts: Vec<&str> = [&self.given_name, &self.family_name]
+            .into_iter()
+            .flatten()
+            .map(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+impl From<UserInput> for ScimUserInput {
+    fn from(input: UserInput) -> Self {
+        let name = input
+            .display_name
+            .filter(|v| !v.is_empty())
+            .or_else(|| input.name.as_ref().and_then(|n| n.full_name()))
+            .unwrap_or_else(|| input.user_name.clone());
+
+        ScimUserInput {
+            user_name: input.user_name,
+            external_id: input.external_id,
+            name,
+            active: input.active,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOutput {
+    pub schemas: Vec<String>,
+    pub id: String,
+    pub user_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub display_name: String,
+    pub name: NameOutput,
+    pub active: bool,
+    pub meta: MetaOutput,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NameOutput {
+    pub formatted: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInput {
+    #[validate(length(min = 1, max = 255))]
+    pub display_name: String,
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub members: Vec<MemberInput>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MemberInput {
+    pub value: String,
+}
+
+impl From<GroupInput> for ScimGroupInput {
+    fn from(input: GroupInput) -> Self {
+        ScimGroupInput {
+            display_name: input.display_name,
+            external_id: input.external_id,
+            member_ids: input.members.into_iter().map(|m| m.value).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupOutput {
+    pub schemas: Vec<String>,
+    pub id: String,
+    pub display_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub members: Vec<MemberOutput>,
+    pub meta: MetaOutput,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MemberOutput {
+    pub value: String,
+    pub display: String,
+    #[serde(rename = "$ref")]
+    pub reference: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaOutput {
+    pub resource_type: String,
+    pub created: String,
+    pub last_modified: String,
+    pub location: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct PatchInput {
+    #[serde(rename = "Operations")]
+    pub operations: Vec<PatchOperation>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct PatchOperation {
+    pub op: String,
+    pub path: Option<String>,
+    pub value: Option<Value>,
+}
+
+// Errors are rendered in the SCIM error format instead of the API's usual error body.
+pub struct ScimError {
+    status: StatusCode,
+    scim_type: Option<&'static str>,
+    detail: String,
+}
+
+impl ScimError {
+    fn invalid_filter(detail: &str) -> Self {
+        ScimError {
+            status: StatusCode::BAD_REQUEST,
+            scim_type: Some("invalidFilter"),
+            detail: detail.to_string(),
+        }
+    }
+
+    fn invalid_path(detail: &str) -> Self {
+        ScimError {
+            status: StatusCode::BAD_REQUEST,
+            scim_type: Some("invalidPath"),
+            detail: detail.to_string(),
+        }
+    }
+
+    fn invalid_value(detail: &str) -> Self {
+        ScimError {
+            status: StatusCode::BAD_REQUEST,
+            scim_type: Some("invalidValue"),
+            detail: detail.to_string(),
+        }
+    }
+}
+
+impl From<AppError> for ScimError {
+    fn from(e: AppError) -> Self {
+        let scim_type = match e {
+            AppError::Conflict(_) => Some("uniqueness"),
+            _ => None,
+        };
+        let detail = match &e {
+            AppError::Conflict(e) | AppError::InvalidRequest(e) => e.to_string(),
+            e => e.to_string(),
+        };
+        let status = e.into_response().status();
+
+        ScimError { status, scim_type, detail }
+    }
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        let mut body = serde_json::json!({
+            "schemas": [ERROR_SCHEMA],
+            "status": self.status.as_u16().to_string(),
+            "detail": self.detail,
+        });
+        if let Some(scim_type) = self.scim_type {
+            body["scimType"] = Value::from(scim_type);
+        }
+
+        scim_response(self.status, body)
+    }
+}
+
+fn scim_response<T: Serialize>(status: StatusCode, body: T) -> Response {
+    (status, [(header::CONTENT_TYPE, SCIM_CONTENT_TYPE)], Json(body)).into_response()
+}
+
+fn list_output<T: Serialize>(total: i64, start_index: i64, resources: Vec<T>) -> ListOutput {
+    let resources: Vec<Value> = resources.into_iter().filter_map(|r| serde_json::to_value(r).ok()).collect();
+
+    ListOutput {
+        schemas: vec![LIST_RESPONSE_SCHEMA.to_string()],
+        total_results: total,
+        start_index,
+        items_per_page: resources.len() as i64,
+        resources,
+    }
+}
+
+fn user_output(state: &AppState, user: ScimUser) -> UserOutput {
+    UserOutput {
+        schemas: vec![USER_SCHEMA.to_string()],
+        meta: meta_output(state, "User", &user.id, &user.created_at, &user.updated_at),
+        id: user.id,
+        user_name: user.user_name,
+        external_id: user.external_id,
+        display_name: user.name.clone(),
+        name: NameOutput { formatted: user.name },
+        active: user.active,
+    }
+}
+
+fn group_output(state: &AppState, group: ScimGroup, members: Vec<ScimGroupMember>) -> GroupOutput {
+    GroupOutput {
+        schemas: vec![GROUP_SCHEMA.to_string()],
+        meta: meta_output(state, "Group", &group.id, &group.created_at, &group.updated_at),
+        id: group.id,
+        display_name: group.display_name,
+        external_id: group.external_id,
+        members: members
+            .into_iter()
+            .map(|m| MemberOutput {
+                reference: resource_location(state, "User", &m.user_id),
+                value: m.user_id,
+                display: m.name,
+            })
+            .collect(),
+    }
+}
+
+fn meta_output(state: &AppState, resource_type: &str, id: &str, created_at: &NaiveDateTime, updated_at: &NaiveDateTime) -> MetaOutput {
+    MetaOutput {
+        resource_type: resource_type.to_string(),
+        created: created_at.and_utc().to_rfc3339(),
+        last_modified: updated_at.and_utc().to_rfc3339(),
+        location: resource_location(state, resource_type, id),
+    }
+}
+
+fn resource_location(state: &AppState, resource_type: &str, id: &str) -> String {
+    format!("{}api/v1/scim/v2/{}s/{}", state.conf.web.origin, resource_type, id)
+}
+
+// Only `<attribute> eq "<value>"` is supported, which covers what IdPs send when matching existing resources.
+fn parse_filter(filter: &str) -> Result<(String, String), ScimError> {
+    let invalid = || ScimError::invalid_filter(&format!("Unsupported filter: {}", filter));
+
+    let (attribute, rest) = filter.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let (operator, value) = rest.trim_start().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    if !operator.eq_ignore_ascii_case("eq") {
+        return Err(invalid());
+    }
+    let value = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"')).ok_or_else(invalid)?;
+
+    Ok((attribute.to_string(), value.replace("\\\"", "\"")))
+}
+
+fn apply_user_operation(user: &mut ScimUserInput, operation: &PatchOperation) -> Result<(), ScimError> {
+    let op = operation.op.to_ascii_lowercase();
+    match (op.as_str(), operation.path.as_deref(), operation.value.as_ref()) {
+        ("add" | "replace", Some(path), Some(value)) => set_user_attribute(user, path, value),
+        // Without a path the value is an object of attributes to set.
+        ("add" | "replace", None, Some(Value::Object(values))) => {
+            for (path, value) in values.iter() {
+                set_user_attribute(user, path, value)?;
+            }
+            Ok(())
+        }
+        ("remove", Some(path), _) if path.eq_ignore_ascii_case("externalId") => {
+            user.external_id = None;
+            Ok(())
+        }
+        ("remove", Some(path), _) => Err(ScimError::invalid_path(&format!("{} cannot be removed", path))),
+        _ => Err(ScimError::invalid_value(&format!("Unsupported operation: {}", operation.op))),
+    }
+}
+
+fn set_user_attribute(user: &mut ScimUserInput, path: &str, value: &Value) -> Result<(), ScimError> {
+    match path.to_ascii_lowercase().as_str() {
+        "active" => user.active = bool_value(value)?,
+        "username" => user.user_name = string_value(value)?,
+        "externalid" => user.external_id = Some(string_value(value)?),
+        "displayname" | "name.formatted" => user.name = string_value(value)?,
+        "name" => {
+            let name: NameInput = serde_json::from_value(value.clone()).map_err(|e| ScimError::invalid_value(&e.to_string()))?;
+            if let Some(full_name) = name.full_name() {
+                user.name = full_name;
+            }
+        }
+        _ => return Err(ScimError::invalid_path(&format!("Unsupported attribute: {}", path))),
+    }
+    Ok(())
+}
+
+fn apply_group_operation(group: &mut ScimGroupInput, operation: &PatchOperation) -> Result<(), ScimError> {
+    let op = operation.op.to_ascii_lowercase();
+    match (op.as_str(), operation.path.as_deref(), operation.value.as_ref()) {
+        ("add", Some(path), Some(value)) if path.eq_ignore_ascii_case("members") => {
+            for member_id in member_values(value)? {
+                if !group.member_ids.contains(&member_id) {
+                    group.member_ids.push(member_id);
+                }
+            }
+            Ok(())
+        }
+        ("replace", Some(path), Some(value)) if path.eq_ignore_ascii_case("members") => {
+            group.member_ids = member_values(value)?;
+            Ok(())
+        }
+        ("add" | "replace", Some(path), Some(value)) => set_group_attribute(group, path, value),
+        ("add" | "replace", None, Some(Value::Object(values))) => {
+            for (path, value) in values.iter() {
+                if path.eq_ignore_ascii_case("members") {
+                    group.member_ids = member_values(value)?;
+                } else {
+                    set_group_attribute(group, path, value)?;
+                }
+            }
+            Ok(())
+        }
+        ("remove", Some(path), value) if path.eq_ignore_ascii_case("members") => {
+            match value {
+                Some(value) => {
+                    let removed = member_values(value)?;
+                    group.member_ids.retain(|id| !removed.contains(id));
+                }
+                None => group.member_ids.clear(),
+            }
+            Ok(())
+        }
+        // e.g. members[value eq "<user_id>"]
+        ("remove", Some(path), _) if path.len() > 8 && path[..8].eq_ignore_ascii_case("members[") && path.ends_with(']') => {
+            let (attribute, member_id) = parse_filter(&path[8..path.len() - 1])?;
+            if !attribute.eq_ignore_ascii_case("value") {
+                return Err(ScimError::invalid_filter(&format!("Unsupported filter attribute: {}", attribute)));
+            }
+            group.member_ids.retain(|id| *id != member_id);
+            Ok(())
+        }
+        ("remove", Some(path), _) if path.eq_ignore_ascii_case("externalId") => {
+            group.external_id = None;
+            Ok(())
+        }
+        ("remove", Some(path), _) => Err(ScimError::invalid_path(&format!("{} cannot be removed", path))),
+        _ => Err(ScimError::invalid_value(&format!("Unsupported operation: {}", operation.op))),
+    }
+}
+
+fn set_group_attribute(group: &mut ScimGroupInput, path: &str, value: &Value) -> Result<(), ScimError> {
+    match path.to_ascii_lowercase().as_str() {
+        "displayname" => group.display_name = string_value(value)?,
+        "externalid" => group.external_id = Some(string_value(value)?),
+        _ => return Err(ScimError::invalid_path(&format!("Unsupported attribute: {}", path))),
+    }
+    Ok(())
+}
+
+fn member_values(value: &Value) -> Result<Vec<String>, ScimError> {
+    let members: Vec<MemberInput> = serde_json::from_value(value.clone()).map_err(|e| ScimError::invalid_value(&e.to_string()))?;
+    Ok(members.into_iter().map(|m| m.value).collect())
+}
+
+fn string_value(value: &Value) -> Result<String, ScimError> {
+    value
+        .as_str()
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+        .ok_or_else(|| ScimError::invalid_value(&format!("Expected a string: {}", value)))
+}
+
+// Some IdPs send booleans as "True"/"False" strings.
+fn bool_value(value: &Value) -> Result<bool, ScimError> {
+    match value {
+        Value::Bool(v) => Ok(*v),
+        Value::String(v) if v.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(v) if v.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(ScimError::invalid_value(&format!("Expected a boolean: {}", value))),
+    }
+}
@@ -1,13 +1,22 @@
-use axum::{extract::State, response::Redirect, routing::get, Json, Router};
+use axum::{
+    extract::State,
+    http::{header, HeaderName, HeaderValue, Method},
+    response::Redirect,
+    routing::get,
+    Json, Router,
+};
 use serde_json::Value;
 use tower_http::cors::CorsLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use opxs_base::AppError;
+use opxs_base::{AppError, RunMode};
 
 use crate::{
-    interface::routes::{auth, image},
+    interface::{
+        cookie, extractors,
+        routes::{admin, auth, image, local, organization, scim},
+    },
     shared::state::AppState,
 };
 
@@ -15,28 +24,49 @@ pub struct WebServer;
 
 impl WebServer {
     pub async fn serve(state: AppState) -> anyhow::Result<()> {
-        let cors = CorsLayer::new().allow_origin(tower_http::cors::Any);
+        // Credentials are required for the cookie session mode, which rules out a wildcard origin.
+        let origin = state.conf.web.origin.trim_end_matches('/').parse::<HeaderValue>()?;
+        let cors = CorsLayer::new()
+            .allow_origin(origin)
+            .allow_credentials(true)
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static(cookie::CSRF_TOKEN_HEADER_NAME),
+                HeaderName::from_static(cookie::SESSION_MODE_HEADER_NAME),
+                HeaderName::from_static(extractors::ORGANIZATION_ID_HEADER_NAME),
+            ]);
+
+        let mut v1 = Router::new()
+            .route("/health", get(health))
+            .with_state(state.clone())
+            .nest_service("/admin", admin::gen_service(state.clone()))
+            .nest_service("/auth", auth::gen_service(state.clone()))
+            .nest_service("/image", image::gen_service(state.clone()))
+            .nest_service("/organization", organization::gen_service(state.clone()))
+            .nest_service("/scim/v2", scim::gen_service(state.clone()));
+        if state.info.mode == RunMode::Local {
+            v1 = v1.nest_service("/local", local::gen_service(state.clone()));
+        }
 
         let app = Router::new()
             .route("/", get(|| async { Redirect::permanent("/api/docs") }))
             .merge(SwaggerUi::new("/api/docs").url("/api/api-doc/openapi.json", ApiDoc::openapi()))
             .nest_service(
                 "/api",
-                Router::new().route("/", get(|| async { Redirect::permanent("/api/docs") })).nest_service(
-                    "/v1",
-                    Router::new()
-                        .route("/health", get(health))
-                        .with_state(state.clone())
-                        .nest_service("/auth", auth::gen_service(state.clone()))
-                        .nest_service("/image", image::gen_service(state.clone())),
-                ),
+                Router::new()
+                    .route("/", get(|| async { Redirect::permanent("/api/docs") }))
+                    .nest_service("/v1", v1),
             )
             .layer(cors);
 
         let addr = std::net::SocketAddr::from(([0, 0, 0, 0], 8080));
 
         tracing::info!("listening on: http://localhost:8080/api/docs");
-        axum::Server::bind(&addr).serve(app.into_make_service()).await?;
+        axum::Server::bind(&addr)
+            .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .await?;
 
         Ok(())
     }
@@ -60,25 +90,169 @@ pub async fn health(State(state): State<AppState>) -> Result<Json<Value>, AppErr
     paths(
         health,
         auth::me,
+        auth::audit::logs,
+        auth::device::authorize,
+        auth::device::token,
+        auth::device::verify,
+        auth::device::approve,
+        auth::device::deny,
+        auth::invitation::create,
+        auth::invitation::list,
+        auth::invitation::redemptions,
+        auth::legal::documents,
+        auth::legal::pending,
+        auth::legal::accept,
+        auth::saml::metadata,
+        auth::saml::login,
+        auth::saml::acs,
+        auth::saml::token,
+        auth::sign_in::revoke,
+        auth::sign_in::get_notification,
+        auth::sign_in::update_notification,
+        auth::announcement::get_setting,
+        auth::announcement::update_setting,
+        auth::locale::get_setting,
+        auth::locale::update_setting,
         auth::email::register,
+        auth::email::upgrade,
         auth::email::login,
+        auth::email::reauthenticate,
         auth::google::nonce,
         auth::google::register,
+        auth::google::upgrade,
+        auth::guest::login,
         auth::google::login,
+        auth::google::reauthenticate,
         image::convert::upload,
         image::convert::status,
+        image::convert::jobs,
+        organization::create,
+        organization::list,
+        organization::members,
+        organization::update_member,
+        organization::remove_member,
+        organization::invite,
+        organization::invitations,
+        organization::accept,
+        organization::token,
+        organization::saml,
+        organization::update_saml,
+        organization::delete_saml,
+        organization::create_scim_token,
+        organization::scim_tokens,
+        organization::delete_scim_token,
+        scim::list_users,
+        scim::get_user,
+        scim::create_user,
+        scim::replace_user,
+        scim::patch_user,
+        scim::delete_user,
+        scim::list_groups,
+        scim::get_group,
+        scim::create_group,
+        scim::replace_group,
+        scim::patch_group,
+        scim::delete_group,
+        admin::audit::logs,
+        admin::email::suppressions,
+        admin::email::suppress,
+        admin::email::unsuppress,
+        admin::email::announce,
+        admin::email::job,
+        admin::email::attempts,
+        admin::email::pause,
+        admin::email::resume,
+        admin::email::cancel,
+        admin::impersonation::start,
+        admin::impersonation::stop,
+        admin::invitation::quota,
+        admin::legal::publish,
+        local::captured_emails,
+        local::captured_email,
+        local::captured_email_html,
+        local::captured_email_eml,
     ),
     components(
         schemas(
             auth::email::RegisterInput,
             auth::email::LoginInput,
+            auth::email::ReauthenticateInput,
+            opxs_base::FieldViolation,
             auth::google::NonceOutput,
             auth::google::RegisterInput,
             auth::google::LoginInput,
+            auth::guest::LoginInput,
             image::convert::UploadInput,
             image::convert::UploadOutput,
             image::convert::StatusInput,
             image::convert::StatusOutput,
+            image::convert::JobsInput,
+            image::convert::JobOutput,
+            organization::CreateInput,
+            organization::UpdateMemberInput,
+            organization::InviteInput,
+            organization::AcceptInput,
+            organization::UpdateSamlInput,
+            opxs_auth::shared::model::SamlProvider,
+            organization::CreateScimTokenOutput,
+            opxs_auth::shared::model::ScimToken,
+            scim::ListInput,
+            scim::ListOutput,
+            scim::UserInput,
+            scim::NameInput,
+            scim::UserOutput,
+            scim::NameOutput,
+            scim::GroupInput,
+            scim::MemberInput,
+            scim::GroupOutput,
+            scim::MemberOutput,
+            scim::MetaOutput,
+            scim::PatchInput,
+            scim::PatchOperation,
+            opxs_auth::shared::model::AuthToken,
+            opxs_auth::shared::model::Organization,
+            opxs_auth::shared::model::OrganizationRole,
+            opxs_auth::shared::model::OrganizationMembership,
+            opxs_auth::shared::model::OrganizationMember,
+            opxs_auth::shared::model::OrganizationInvitation,
+            auth::audit::LogsInput,
+            auth::device::AuthorizeInput,
+            auth::device::AuthorizeOutput,
+            auth::device::TokenInput,
+            auth::device::VerifyInput,
+            auth::saml::AcsInput,
+            auth::saml::TokenInput,
+            auth::sign_in::RevokeInput,
+            auth::sign_in::NotificationSetting,
+            auth::announcement::AnnouncementSetting,
+            auth::locale::LocaleSetting,
+            opxs_auth::shared::model::DeviceAuthorization,
+            opxs_auth::shared::model::DeviceAuthorizationStatus,
+            admin::audit::LogsInput,
+            admin::email::SuppressionsInput,
+            admin::email::SuppressInput,
+            opxs_email_send::EmailSendBlockedAddress,
+            admin::email::AnnounceInput,
+            opxs_email_send::EmailSendJobProgress,
+            opxs_email_send::EmailSendJobBatchProgress,
+            opxs_email_send::EmailSendJobBatchStatus,
+            admin::email::AttemptsInput,
+            opxs_email_send::EmailSendJobBatchDetailAttempt,
+            admin::impersonation::StartInput,
+            auth::invitation::CreateInput,
+            admin::invitation::QuotaInput,
+            opxs_auth::shared::model::Invitation,
+            opxs_auth::shared::model::InvitationRedemption,
+            auth::legal::AcceptInput,
+            admin::legal::PublishInput,
+            opxs_auth::shared::model::LegalDocument,
+            opxs_auth::shared::model::LegalDocumentType,
+            opxs_auth::shared::model::AuthAuditLog,
+            opxs_auth::shared::model::AuthAuditEventType,
+            opxs_auth::shared::model::AuthAuditAuthMethod,
+            opxs_auth::shared::model::AuthAuditOutcome,
+            local::CapturedEmailsInput,
+            opxs_email_send::CapturedEmail,
         )
     ),
     modifiers(&SecurityAddon),
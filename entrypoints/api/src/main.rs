@@ -42,6 +42,13 @@ async fn main() -> anyhow::Result<()> {
     migrator.migrate().await?;
 
     let state = AppState::new(info, conf).await?;
+
+    // Picks up outbox messages that weren't delivered right after their transaction committed.
+    let outbox_relay = state.service.outbox_relay.clone();
+    let relay_interval = std::time::Duration::from_secs(state.conf.outbox.relay_interval_seconds as u64);
+    let relay_batch_size = state.conf.outbox.relay_batch_size;
+    tokio::spawn(async move { outbox_relay.run(relay_interval, relay_batch_size).await });
+
     interface::WebServer::serve(state).await?;
 
     Ok(())
@@ -1,20 +1,33 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use chrono::Utc;
-use opxs_base::{AppConfig, AppInfo};
-use opxs_image_convert::{ImageConvertJobCreator, ImageConvertJobRepository};
+use opxs_base::{AppConfig, AppInfo, OutboxRelay, OutboxRepository};
+use opxs_image_convert::{ImageConvertJobCreator, ImageConvertJobRepository, IMAGE_CONVERT_QUEUE_URL};
 use sqlx::PgPool;
 
 use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider, tsid::TsidProvider};
 use core_cloud::aws::{s3::S3Client, sqs::SqsSender};
 use opxs_auth::{
+    audit::{AuthAuditLogger, AuthAuditRepo, AuthAuditService},
+    device::{DeviceAuthRepo, DeviceAuthService},
     email::{EmailAuthRepo, EmailAuthService},
-    provider::{GoogleAuthService, GoogleOAuth2ProviderImpl, ProviderAuthRepo},
+    guest::{GuestRepo, GuestService},
+    impersonation::ImpersonationService,
+    invitation::{InvitationRepo, InvitationService},
+    legal::{LegalRepo, LegalService},
+    organization::{OrganizationRepo, OrganizationService},
+    policy::RegisterPolicy,
+    provider::{GoogleAuthService, GoogleOAuth2ProviderImpl, ProviderAuthRepo, SamlAuthService, SamlRepo},
+    rate_limit::RateLimitRepo,
+    scim::{ScimRepo, ScimService},
     shared::kdf::{Kdf, KdfAlgorithm},
+    sign_in::{SignInRepo, SignInService},
     token::{TokenRepo, TokenService},
     user::{UserRepo, UserService},
 };
-use opxs_email_send::{EmailSendJobCreator, EmailSendJobRepository};
+use opxs_email_send::{
+    CapturedEmailRepository, EmailSendJobController, EmailSendJobCreator, EmailSendJobRepository, EmailSuppressionList, EMAIL_SEND_QUEUE_URL,
+};
 
 use crate::service::health::{repo::WorldRepo, service::HealthService};
 
@@ -23,14 +36,28 @@ pub struct AppService {
     pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
     pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
 
+    pub outbox_relay: Arc<OutboxRelay>,
     pub email_send_job_creator: EmailSendJobCreator,
+    pub email_send_job_controller: EmailSendJobController,
+    pub email_suppression: EmailSuppressionList,
+    pub captured_email_repository: Arc<CapturedEmailRepository>,
     pub image_convert_job_creator: ImageConvertJobCreator,
 
     pub health: HealthService,
     pub email_auth: EmailAuthService,
     pub google_auth: GoogleAuthService,
-    pub token: TokenService,
+    pub guest: GuestService,
+    pub saml_auth: SamlAuthService,
+    pub scim: ScimService,
+    pub token: Arc<TokenService>,
     pub user: UserService,
+    pub audit: AuthAuditService,
+    pub invitation: Arc<InvitationService>,
+    pub legal: Arc<LegalService>,
+    pub organization: OrganizationService,
+    pub impersonation: ImpersonationService,
+    pub device_auth: DeviceAuthService,
+    pub sign_in: SignInService,
 }
 
 impl AppService {
@@ -43,20 +70,105 @@ impl AppService {
         random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
         tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
         send_email_sqs_sender: Arc<dyn SqsSender + Send + Sync>,
+        image_convert_sqs_sender: Arc<dyn SqsSender + Send + Sync>,
         image_convert_s3_client: Arc<dyn S3Client + Send + Sync>,
     ) -> Self {
+        let email_send_job_repository = Arc::new(EmailSendJobRepository {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let outbox_relay = Arc::new(OutboxRelay {
+            outbox_repository: Arc::new(OutboxRepository {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            sqs_senders: HashMap::from([
+                (EMAIL_SEND_QUEUE_URL.to_string(), send_email_sqs_sender),
+                (IMAGE_CONVERT_QUEUE_URL.to_string(), image_convert_sqs_sender),
+            ]),
+            system_clock: system_clock.clone(),
+            base_delay_seconds: conf.outbox.base_delay_seconds,
+        });
+        let user_repo = Arc::new(UserRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let audit_repo = Arc::new(AuthAuditRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: audit_repo.clone(),
+        });
+        let invitation_service = Arc::new(InvitationService {
+            invitation_repo: Arc::new(InvitationRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: random_bytes_provider.clone(),
+            invitation_conf: conf.auth.invitation.clone(),
+        });
+        let legal_service = Arc::new(LegalService {
+            legal_repo: Arc::new(LegalRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            system_clock: system_clock.clone(),
+        });
+        let provider_repo = Arc::new(ProviderAuthRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let register_policy = Arc::new(RegisterPolicy::new(&conf.auth.register_policy));
+        let rate_limit_repo = Arc::new(RateLimitRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let organization_repo = Arc::new(OrganizationRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let token_repo = Arc::new(TokenRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let token_service = Arc::new(TokenService {
+            system_clock: system_clock.clone(),
+            random_bytes_provider: random_bytes_provider.clone(),
+            jwt_conf: conf.auth.jwt.clone(),
+            token_repo: token_repo.clone(),
+            audit_logger: audit_logger.clone(),
+        });
+
         Self {
             system_clock: system_clock.clone(),
             random_bytes_provider: random_bytes_provider.clone(),
             tsid_provider: tsid_provider.clone(),
 
+            outbox_relay: outbox_relay.clone(),
             email_send_job_creator: EmailSendJobCreator {
-                email_send_job_repository: Arc::new(EmailSendJobRepository {
-                    db: db.clone(),
-                    system_clock: system_clock.clone(),
-                }),
-                send_email_sqs_sender: send_email_sqs_sender.clone(),
+                email_send_job_repository: email_send_job_repository.clone(),
+                outbox_relay: outbox_relay.clone(),
+            },
+            email_send_job_controller: EmailSendJobController {
+                email_send_job_repository: email_send_job_repository.clone(),
+                outbox_relay,
+            },
+            email_suppression: EmailSuppressionList {
+                email_send_job_repository,
+                system_clock: system_clock.clone(),
             },
+            captured_email_repository: Arc::new(CapturedEmailRepository {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
 
             image_convert_job_creator: ImageConvertJobCreator {
                 image_convert_job_repository: Arc::new(ImageConvertJobRepository {
@@ -78,6 +190,8 @@ impl AppService {
                     system_clock: system_clock.clone(),
                     tsid_provider: tsid_provider.clone(),
                 }),
+                user_repo: user_repo.clone(),
+                audit_logger: audit_logger.clone(),
                 system_clock: system_clock.clone(),
                 random_bytes_provider: random_bytes_provider.clone(),
                 jwt_conf: conf.auth.jwt.clone(),
@@ -85,27 +199,98 @@ impl AppService {
                     algorithm: KdfAlgorithm::Pbkdf2HmacSha256,
                     iterations: 1024,
                 },
+                register_policy: register_policy.clone(),
+                invitation_service: invitation_service.clone(),
+                legal_service: legal_service.clone(),
             },
             google_auth: GoogleAuthService {
                 oauth2_provider: Arc::new(GoogleOAuth2ProviderImpl {}),
-                auth_repo: Arc::new(ProviderAuthRepo {
+                auth_repo: provider_repo.clone(),
+                user_repo: user_repo.clone(),
+                audit_logger: audit_logger.clone(),
+                invitation_service: invitation_service.clone(),
+                legal_service: legal_service.clone(),
+                auth_conf: conf.auth.clone(),
+            },
+            guest: GuestService {
+                guest_repo: Arc::new(GuestRepo {
                     db: db.clone(),
                     system_clock: system_clock.clone(),
                     tsid_provider: tsid_provider.clone(),
                 }),
-                auth_conf: conf.auth.clone(),
+                rate_limit_repo: rate_limit_repo.clone(),
+                audit_logger: audit_logger.clone(),
+                system_clock: system_clock.clone(),
+                guest_conf: conf.auth.guest.clone(),
             },
-            token: TokenService {
+            saml_auth: SamlAuthService {
+                saml_repo: Arc::new(SamlRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: tsid_provider.clone(),
+                }),
+                provider_repo,
+                organization_repo: organization_repo.clone(),
+                user_repo: user_repo.clone(),
+                audit_logger: audit_logger.clone(),
                 system_clock: system_clock.clone(),
                 random_bytes_provider: random_bytes_provider.clone(),
-                jwt_conf: conf.auth.jwt.clone(),
-                token_repo: Arc::new(TokenRepo {
+                sp_base_url: format!("{}api/v1/auth/saml/", conf.web.origin),
+            },
+            scim: ScimService {
+                scim_repo: Arc::new(ScimRepo {
                     db: db.clone(),
                     system_clock: system_clock.clone(),
+                    tsid_provider: tsid_provider.clone(),
                 }),
+                organization_repo: organization_repo.clone(),
+                random_bytes_provider: random_bytes_provider.clone(),
             },
+            token: token_service.clone(),
             user: UserService {
-                user_repo: Arc::new(UserRepo { db }),
+                user_repo: user_repo.clone(),
+                audit_logger: audit_logger.clone(),
+                system_clock: system_clock.clone(),
+                unregister_conf: conf.auth.unregister.clone(),
+            },
+            audit: AuthAuditService { audit_repo },
+            invitation: invitation_service,
+            legal: legal_service,
+            organization: OrganizationService {
+                organization_repo,
+                system_clock: system_clock.clone(),
+                random_bytes_provider: random_bytes_provider.clone(),
+                jwt_conf: conf.auth.jwt.clone(),
+                register_policy,
+                audit_logger: audit_logger.clone(),
+            },
+            device_auth: DeviceAuthService {
+                device_repo: Arc::new(DeviceAuthRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                }),
+                rate_limit_repo,
+                token_service: token_service.clone(),
+                audit_logger: audit_logger.clone(),
+                system_clock: system_clock.clone(),
+                random_bytes_provider: random_bytes_provider.clone(),
+            },
+            sign_in: SignInService {
+                sign_in_repo: Arc::new(SignInRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                }),
+                token_repo,
+                audit_logger: audit_logger.clone(),
+                system_clock: system_clock.clone(),
+                random_bytes_provider: random_bytes_provider.clone(),
+            },
+            impersonation: ImpersonationService {
+                user_repo,
+                token_service,
+                audit_logger,
+                system_clock: system_clock.clone(),
+                jwt_conf: conf.auth.jwt.clone(),
             },
         }
     }
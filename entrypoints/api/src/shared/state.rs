@@ -8,7 +8,9 @@ use sqlx::{postgres::PgPoolOptions, PgPool};
 use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
 use core_cloud::aws::{s3::S3ClientImpl, sqs::SqsSenderImpl};
 
-use opxs_base::{AppConfig, AppInfo};
+use opxs_base::{AppConfig, AppInfo, CookieSecretConfig, RunMode};
+use opxs_email_send::EMAIL_SEND_QUEUE_URL;
+use opxs_image_convert::IMAGE_CONVERT_QUEUE_URL;
 
 use super::service::AppService;
 
@@ -19,10 +21,17 @@ pub struct AppState {
     pub db: Arc<PgPool>,
     pub service: Arc<AppService>,
     cookie_key: cookie::Key,
+    previous_cookie_key: PreviousCookieKey,
 }
 
+// Signs nothing; only used to verify cookies signed before the key was rotated.
+#[derive(Clone)]
+pub struct PreviousCookieKey(cookie::Key);
+
 impl AppState {
     pub async fn new(info: AppInfo, conf: AppConfig) -> anyhow::Result<Self> {
+        let (cookie_key, previous_cookie_key) = Self::load_cookie_keys(&info.mode, &conf.web.cookie.secret)?;
+
         let db = Arc::new(
             PgPoolOptions::new()
                 .max_connections(100)
@@ -38,7 +47,12 @@ impl AppState {
         let sdk_config = aws_config::load_from_env().await;
         let send_email_sqs_sender = Arc::new(SqsSenderImpl {
             client: aws_sdk_sqs::Client::new(&sdk_config),
-            queue_url: "opxs-batch-email-send-sqs".to_string(),
+            queue_url: EMAIL_SEND_QUEUE_URL.to_string(),
+            delay_seconds: None,
+        });
+        let image_convert_sqs_sender = Arc::new(SqsSenderImpl {
+            client: aws_sdk_sqs::Client::new(&sdk_config),
+            queue_url: IMAGE_CONVERT_QUEUE_URL.to_string(),
             delay_seconds: None,
         });
         let image_convert_s3_client = Arc::new(S3ClientImpl {
@@ -54,6 +68,7 @@ impl AppState {
             random_bytes_provider,
             tsid_provider,
             send_email_sqs_sender,
+            image_convert_sqs_sender,
             image_convert_s3_client,
         ));
 
@@ -62,9 +77,45 @@ impl AppState {
             conf,
             db,
             service,
-            cookie_key: cookie::Key::generate(),
+            cookie_key,
+            previous_cookie_key,
         })
     }
+
+    #[cfg(test)]
+    pub fn new_with_service(info: AppInfo, conf: AppConfig, db: Arc<PgPool>, service: Arc<AppService>) -> anyhow::Result<Self> {
+        let (cookie_key, previous_cookie_key) = Self::load_cookie_keys(&info.mode, &conf.web.cookie.secret)?;
+
+        Ok(Self {
+            info,
+            conf,
+            db,
+            service,
+            cookie_key,
+            previous_cookie_key,
+        })
+    }
+
+    // A generated key logs everyone out on every restart and differs between tasks, so it is only allowed locally.
+    fn load_cookie_keys(mode: &RunMode, conf: &CookieSecretConfig) -> anyhow::Result<(cookie::Key, PreviousCookieKey)> {
+        let current = match (conf.current.as_deref(), mode) {
+            (Some(v), _) => Self::parse_cookie_key(v)?,
+            (None, RunMode::Local) => cookie::Key::generate(),
+            (None, _) => anyhow::bail!("cookie_secret_current is required outside of local mode"),
+        };
+        let previous = match conf.previous.as_deref() {
+            Some(v) => Self::parse_cookie_key(v)?,
+            None => current.clone(),
+        };
+
+        Ok((current, PreviousCookieKey(previous)))
+    }
+
+    fn parse_cookie_key(value: &str) -> anyhow::Result<cookie::Key> {
+        let bytes = hex::decode(value).map_err(|e| anyhow::anyhow!("invalid cookie secret: {}", e))?;
+        let key = cookie::Key::try_from(bytes.as_slice()).map_err(|e| anyhow::anyhow!("invalid cookie secret: {}", e))?;
+        Ok(key)
+    }
 }
 
 impl FromRef<AppState> for cookie::Key {
@@ -72,3 +123,45 @@ impl FromRef<AppState> for cookie::Key {
         state.cookie_key.clone()
     }
 }
+
+impl FromRef<AppState> for PreviousCookieKey {
+    fn from_ref(state: &AppState) -> Self {
+        state.previous_cookie_key.clone()
+    }
+}
+
+impl From<PreviousCookieKey> for cookie::Key {
+    fn from(key: PreviousCookieKey) -> Self {
+        key.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_cookie_keys_test() {
+        let secret = hex::encode(cookie::Key::generate().master());
+        let conf = CookieSecretConfig {
+            current: Some(secret.clone()),
+            previous: None,
+        };
+        let (current, previous) = AppState::load_cookie_keys(&RunMode::Dev, &conf).unwrap();
+        assert_eq!(current.master(), cookie::Key::from(previous).master());
+        assert_eq!(hex::encode(current.master()), secret);
+
+        let conf = CookieSecretConfig {
+            current: None,
+            previous: None,
+        };
+        assert!(AppState::load_cookie_keys(&RunMode::Local, &conf).is_ok());
+        assert!(AppState::load_cookie_keys(&RunMode::Dev, &conf).is_err());
+
+        let conf = CookieSecretConfig {
+            current: Some("not-hex".to_string()),
+            previous: None,
+        };
+        assert!(AppState::load_cookie_keys(&RunMode::Local, &conf).is_err());
+    }
+}
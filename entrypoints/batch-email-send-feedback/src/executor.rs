@@ -0,0 +1,277 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+use opxs_email_send::{EmailSendJobBatchDetailStatus, EmailSendJobRepository, EmailSendLogEventType};
+
+use crate::message::{Bounce, Complaint, Delivery, Mail, SesNotification};
+
+pub struct Executor {
+    pub email_send_job_repository: Arc<EmailSendJobRepository>,
+}
+
+impl Executor {
+    pub async fn execute(&self, ms: &[SesNotification]) -> anyhow::Result<()> {
+        for m in ms.iter() {
+            self.execute_one(m).await?;
+        }
+        Ok(())
+    }
+
+    async fn execute_one(&self, m: &SesNotification) -> anyhow::Result<()> {
+        match m.notification_type.as_str() {
+            "Bounce" => {
+                let bounce = m.bounce.as_ref().ok_or(anyhow::anyhow!("bounce is not found"))?;
+                self.execute_bounce(&m.mail, bounce).await
+            }
+            "Complaint" => {
+                let complaint = m.complaint.as_ref().ok_or(anyhow::anyhow!("complaint is not found"))?;
+                self.execute_complaint(&m.mail, complaint).await
+            }
+            "Delivery" => {
+                let delivery = m.delivery.as_ref().ok_or(anyhow::anyhow!("delivery is not found"))?;
+                self.execute_delivery(&m.mail, delivery).await
+            }
+            // Configuration sets may also publish Send, Open, Click and other events we don't track.
+            _ => {
+                info!("skip notification: {}", m.notification_type);
+                Ok(())
+            }
+        }
+    }
+
+    // Only hard bounces are suppressed; SES has already retried a soft bounce before reporting it.
+    async fn execute_bounce(&self, mail: &Mail, bounce: &Bounce) -> anyhow::Result<()> {
+        let created_at = parse_timestamp(&bounce.timestamp)?;
+        let event_detail = serde_json::to_string(bounce)?;
+        let reason = format!("Bounce: {}/{}", bounce.bounce_type, bounce.bounce_sub_type);
+
+        for r in bounce.bounced_recipients.iter() {
+            self.email_send_job_repository
+                .create_log(
+                    &mail.message_id,
+                    &r.email_address,
+                    EmailSendLogEventType::Bounce,
+                    Some(&event_detail),
+                    &created_at,
+                )
+                .await?;
+            if bounce.bounce_type == "Permanent" {
+                self.email_send_job_repository
+                    .create_blocked_address(&r.email_address, &reason, None, &created_at)
+                    .await?;
+            }
+        }
+
+        let failed_reason = match bounce.bounced_recipients.iter().find_map(|r| r.diagnostic_code.as_deref()) {
+            Some(diagnostic_code) => format!("{} ({})", reason, diagnostic_code),
+            None => reason,
+        };
+        self.email_send_job_repository
+            .update_status_by_message_id(&mail.message_id, EmailSendJobBatchDetailStatus::Failed, Some(&failed_reason))
+            .await?;
+
+        Ok(())
+    }
+
+    // The message was delivered, so the detail keeps its status; the recipient just never gets mail again.
+    async fn execute_complaint(&self, mail: &Mail, complaint: &Complaint) -> anyhow::Result<()> {
+        let created_at = parse_timestamp(&complaint.timestamp)?;
+        let event_detail = serde_json::to_string(complaint)?;
+        let reason = format!("Complaint: {}", complaint.complaint_feedback_type.as_deref().unwrap_or("unknown"));
+
+        for r in complaint.complained_recipients.iter() {
+            self.email_send_job_repository
+                .create_log(
+                    &mail.message_id,
+                    &r.email_address,
+                    EmailSendLogEventType::Complaint,
+                    Some(&event_detail),
+                    &created_at,
+                )
+                .await?;
+            self.email_send_job_repository
+                .create_blocked_address(&r.email_address, &reason, None, &created_at)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn execute_delivery(&self, mail: &Mail, delivery: &Delivery) -> anyhow::Result<()> {
+        let created_at = parse_timestamp(&delivery.timestamp)?;
+        let event_detail = serde_json::to_string(delivery)?;
+
+        for email_address in delivery.recipients.iter() {
+            self.email_send_job_repository
+                .create_log(
+                    &mail.message_id,
+                    email_address,
+                    EmailSendLogEventType::Delivery,
+                    Some(&event_detail),
+                    &created_at,
+                )
+                .await?;
+        }
+
+        self.email_send_job_repository
+            .update_status_by_message_id(&mail.message_id, EmailSendJobBatchDetailStatus::Completed, None)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn parse_timestamp(s: &str) -> anyhow::Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(s)?.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use core_base::{
+        clock::SystemClockUtc,
+        random_bytes::RandomBytesProviderImpl,
+        tsid::{TsidProvider, TsidProviderImpl},
+    };
+
+    use opxs_base::{OutboxRelay, OutboxRepository};
+    use opxs_email_send::EmailSendJobCreator;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, "15.1").await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+
+        let email_send_job_repository = Arc::new(EmailSendJobRepository {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        // Nothing is sent from this test, so the relay has no queues and leaves the messages pending.
+        let job_creator = EmailSendJobCreator {
+            email_send_job_repository: email_send_job_repository.clone(),
+            outbox_relay: Arc::new(OutboxRelay {
+                outbox_repository: Arc::new(OutboxRepository {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                }),
+                sqs_senders: HashMap::new(),
+                system_clock: system_clock.clone(),
+                base_delay_seconds: 10,
+            }),
+        };
+        let executor = Executor {
+            email_send_job_repository: email_send_job_repository.clone(),
+        };
+
+        // bounce
+        let m: SesNotification = serde_json::from_str(include_str!("message/fixtures/bounce_permanent.json")).unwrap();
+        let job_id = tsid_provider.gen().to_string();
+        job_creator
+            .create_email_confirm_job(
+                &job_id,
+                "test_name",
+                "recipient1@example.com",
+                "sender@example.com",
+                "https://example.com",
+                "ja",
+            )
+            .await
+            .unwrap();
+        sqlx::query("UPDATE email_send_job_batch_details SET message_id = $2 WHERE job_id = $1")
+            .bind(&job_id)
+            .bind(&m.mail.message_id)
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+
+        executor.execute(&[m]).await.unwrap();
+        let m: SesNotification = serde_json::from_str(include_str!("message/fixtures/bounce_permanent.json")).unwrap();
+        executor.execute(&[m]).await.unwrap();
+
+        let details = email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+        assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Failed));
+        assert_eq!(
+            details[0].failed_reason.as_deref(),
+            Some("Bounce: Permanent/General (smtp; 550 user unknown)")
+        );
+        let logs = email_send_job_repository
+            .get_logs(details[0].message_id.as_deref().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(logs.iter().all(|l| l.event_type == EmailSendLogEventType::Bounce));
+        assert_eq!(
+            email_send_job_repository
+                .get_blocked_addresses("recipient1@example.com")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            email_send_job_repository
+                .get_blocked_addresses("recipient2@example.com")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // soft bounce isn't suppressed
+        let m: SesNotification = serde_json::from_str(include_str!("message/fixtures/bounce_transient.json")).unwrap();
+        executor.execute(&[m]).await.unwrap();
+        assert!(email_send_job_repository
+            .get_blocked_addresses("recipient@example.com")
+            .await
+            .unwrap()
+            .is_empty());
+
+        // complaint
+        let m: SesNotification = serde_json::from_str(include_str!("message/fixtures/complaint.json")).unwrap();
+        let message_id = m.mail.message_id.clone();
+        executor.execute(&[m]).await.unwrap();
+        let logs = email_send_job_repository.get_logs(&message_id).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].event_type, EmailSendLogEventType::Complaint);
+        let blocked = email_send_job_repository.get_blocked_addresses("recipient1@example.com").await.unwrap();
+        assert_eq!(blocked.len(), 2);
+        assert_eq!(blocked[1].reason.as_deref(), Some("Complaint: abuse"));
+
+        // delivery
+        let m: SesNotification = serde_json::from_str(include_str!("message/fixtures/delivery.json")).unwrap();
+        let job_id = tsid_provider.gen().to_string();
+        job_creator
+            .create_email_confirm_job(
+                &job_id,
+                "test_name",
+                "success@simulator.amazonses.com",
+                "john@example.com",
+                "https://example.com",
+                "ja",
+            )
+            .await
+            .unwrap();
+        sqlx::query("UPDATE email_send_job_batch_details SET message_id = $2 WHERE job_id = $1")
+            .bind(&job_id)
+            .bind(&m.mail.message_id)
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+        executor.execute(&[m]).await.unwrap();
+        let details = email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+        assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Completed));
+
+        // untracked events
+        let m: SesNotification = serde_json::from_str(include_str!("message/fixtures/event_open.json")).unwrap();
+        executor.execute(&[m]).await.unwrap();
+    }
+}
@@ -1,48 +1,44 @@
+use std::sync::Arc;
+
 use aws_lambda_events::event::sns::SnsEvent;
+use chrono::Duration;
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use sqlx::postgres::PgPoolOptions;
 use tracing::info;
 
-use opxs_base::AppInfo;
+use core_base::clock::SystemClockUtc;
+
+use opxs_base::{AppConfig, AppInfo};
+use opxs_email_send::EmailSendJobRepository;
 
+mod executor;
 mod message;
 
+use executor::*;
 use message::*;
 
-async fn handler_sub(_ms: &[SesNotification]) -> Result<(), Error> {
+const APPLICATION_NAME: &str = "opxs-batch-email-send-feedback";
+
+async fn handler_sub(ms: &[SesNotification]) -> Result<(), Error> {
     let info = AppInfo::new()?;
     info!("info: {}", info);
 
-    todo!();
-
-    // let sdk_config = aws_config::load_from_env().await;
-    // let secret_reader = Box::new(SecretsReaderImpl {
-    //     client: aws_sdk_secretsmanager::Client::new(&sdk_config),
-    // });
-    // let conf = AppConfig::load(&info.mode, secret_reader).await?;
-    // let db = Arc::new(
-    //     PgPoolOptions::new()
-    //         .max_connections(100)
-    //         .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
-    //         .connect(&conf.postgres.url)
-    //         .await?,
-    // );
-    // let system_clock = Arc::new(SystemClockUtc {});
-    // let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
-
-    // let executor = Executor {
-    //     email_send_job_repository: Arc::new(EmailSendJobRepository {
-    //         db: db.clone(),
-    //         system_clock,
-    //         tsid_provider,
-    //     }),
-    //     ses_sender: Arc::new(SesSenderImpl {
-    //         client: aws_sdk_sesv2::Client::new(&aws_config::load_from_env().await),
-    //         configuration_set_name: Some(conf.ses.configuration_set_name),
-    //     }),
-    // };
-    // executor.execute(ms).await?;
-
-    // Ok(())
+    let conf = AppConfig::load(APPLICATION_NAME, &info.mode).await?;
+    let db = Arc::new(
+        PgPoolOptions::new()
+            .max_connections(100)
+            .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
+            .connect(&conf.postgres.url)
+            .await?,
+    );
+    let system_clock = Arc::new(SystemClockUtc {});
+
+    let executor = Executor {
+        email_send_job_repository: Arc::new(EmailSendJobRepository { db, system_clock }),
+    };
+    executor.execute(ms).await?;
+
+    Ok(())
 }
 
 async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<(), Error> {
@@ -51,7 +47,7 @@ async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<(), Error> {
     let mut ms: Vec<SesNotification> = Vec::new();
 
     if let Ok(event) = serde_json::from_value::<SnsEvent>(event.clone()) {
-        info!("sqs event");
+        info!("sns event");
         for v in event.records.into_iter().map(|n| n.sns.message).collect::<Vec<_>>() {
             let m = serde_json::from_str::<SesNotification>(&v)?;
             ms.push(m);
@@ -1,9 +1,13 @@
 // https://docs.aws.amazon.com/ses/latest/dg/notification-contents.html
+// https://docs.aws.amazon.com/ses/latest/dg/event-publishing-retrieving-sns-contents.html
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SesNotification {
+    // Configuration set event destinations name this field `eventType`.
+    #[serde(alias = "eventType")]
     pub notification_type: String,
     pub mail: Mail,
     pub bounce: Option<Bounce>,
@@ -12,18 +16,20 @@ pub struct SesNotification {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Mail {
     pub timestamp: String,
     pub message_id: String,
     pub source: String,
-    pub source_arn: String,
-    pub source_ip: String,
-    pub sending_account_id: String,
-    pub caller_identity: String,
+    pub source_arn: Option<String>,
+    pub source_ip: Option<String>,
+    pub sending_account_id: Option<String>,
+    pub caller_identity: Option<String>,
     pub destination: Vec<String>,
-    pub headers_truncated: bool,
+    pub headers_truncated: Option<bool>,
+    #[serde(default)]
     pub headers: Vec<Header>,
-    pub common_headers: CommonHeaders,
+    pub common_headers: Option<CommonHeaders>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -33,55 +39,109 @@ pub struct Header {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CommonHeaders {
+    #[serde(default)]
     pub from: Vec<String>,
-    pub date: String,
+    pub date: Option<String>,
+    #[serde(default)]
     pub to: Vec<String>,
-    pub message_id: String,
-    pub subject: String,
+    pub message_id: Option<String>,
+    pub subject: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Bounce {
     pub bounce_type: String,
-    pub bounce_subtype: String,
+    pub bounce_sub_type: String,
     pub bounced_recipients: Vec<BouncedRecipient>,
     pub timestamp: String,
     pub feedback_id: String,
-    pub remote_mta_ip: String,
-    pub reporting_mta: String,
+    pub remote_mta_ip: Option<String>,
+    #[serde(rename = "reportingMTA")]
+    pub reporting_mta: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct BouncedRecipient {
     pub email_address: String,
-    pub action: String,
-    pub status: String,
-    pub diagnostic_code: String,
+    pub action: Option<String>,
+    pub status: Option<String>,
+    pub diagnostic_code: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Complaint {
     pub complained_recipients: Vec<ComplainedRecipient>,
     pub timestamp: String,
     pub feedback_id: String,
-    pub complaint_sub_type: String,
-    pub user_agent: String,
-    pub complaint_feedback_type: String,
-    pub arrival_date: String,
+    pub complaint_sub_type: Option<String>,
+    pub user_agent: Option<String>,
+    pub complaint_feedback_type: Option<String>,
+    pub arrival_date: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ComplainedRecipient {
     pub email_address: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Delivery {
     pub timestamp: String,
     pub processing_time_millis: i64,
     pub recipients: Vec<String>,
     pub smtp_response: String,
-    pub reporting_mta: String,
-    pub remote_mta_ip: String,
+    #[serde(rename = "reportingMTA")]
+    pub reporting_mta: Option<String>,
+    pub remote_mta_ip: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let m: SesNotification = serde_json::from_str(include_str!("fixtures/bounce_permanent.json")).unwrap();
+        assert_eq!(m.notification_type, "Bounce");
+        assert_eq!(m.mail.message_id, "0000014644fe5ef6-9a483358-9170-4cb4-a269-f5dcdf415321-000000");
+        let bounce = m.bounce.unwrap();
+        assert_eq!(bounce.bounce_type, "Permanent");
+        assert_eq!(bounce.bounce_sub_type, "General");
+        assert_eq!(bounce.bounced_recipients[0].email_address, "recipient1@example.com");
+        assert_eq!(bounce.reporting_mta.as_deref(), Some("dsn; a27-23.smtp-out.us-west-2.amazonses.com"));
+
+        let m: SesNotification = serde_json::from_str(include_str!("fixtures/bounce_transient.json")).unwrap();
+        let bounce = m.bounce.unwrap();
+        assert_eq!(bounce.bounce_type, "Transient");
+        assert_eq!(bounce.bounced_recipients[0].diagnostic_code, None);
+
+        let m: SesNotification = serde_json::from_str(include_str!("fixtures/complaint.json")).unwrap();
+        assert_eq!(m.notification_type, "Complaint");
+        let complaint = m.complaint.unwrap();
+        assert_eq!(complaint.complained_recipients[0].email_address, "recipient1@example.com");
+        assert_eq!(complaint.complaint_feedback_type.as_deref(), Some("abuse"));
+        assert_eq!(complaint.complaint_sub_type, None);
+
+        let m: SesNotification = serde_json::from_str(include_str!("fixtures/delivery.json")).unwrap();
+        assert_eq!(m.notification_type, "Delivery");
+        let delivery = m.delivery.unwrap();
+        assert_eq!(delivery.recipients, vec!["success@simulator.amazonses.com".to_string()]);
+        assert_eq!(delivery.processing_time_millis, 546);
+        assert_eq!(m.mail.common_headers.unwrap().subject.as_deref(), Some("Message sent from Amazon SES"));
+
+        let m: SesNotification = serde_json::from_str(include_str!("fixtures/event_bounce.json")).unwrap();
+        assert_eq!(m.notification_type, "Bounce");
+        assert_eq!(m.bounce.unwrap().bounced_recipients[0].email_address, "recipient@example.com");
+
+        let m: SesNotification = serde_json::from_str(include_str!("fixtures/event_open.json")).unwrap();
+        assert_eq!(m.notification_type, "Open");
+        assert!(m.bounce.is_none() && m.complaint.is_none() && m.delivery.is_none());
+    }
 }
@@ -6,11 +6,14 @@ use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use sqlx::postgres::PgPoolOptions;
 use tracing::info;
 
-use core_base::clock::SystemClockUtc;
-use core_cloud::aws::ses::SesSenderImpl;
+use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+use core_cloud::aws::sqs::{SqsSender, SqsSenderImpl};
 
-use opxs_base::{AppConfig, AppInfo};
-use opxs_email_send::{EmailSendJobBatchSqsMessage, EmailSendJobRepository, Executor};
+use opxs_base::{AppConfig, AppInfo, EmailTransportConfig};
+use opxs_email_send::{
+    retry_delay_seconds, CapturedEmailRepository, EmailSendJobBatchSqsMessage, EmailSendJobRepository, EmailTransport, Executor, LocalEmailTransport,
+    SesEmailTransport, SmtpEmailTransport, EMAIL_SEND_QUEUE_URL,
+};
 
 const APPLICATION_NAME: &str = "opxs-batch-email-send";
 
@@ -28,15 +31,39 @@ async fn handler_sub(ms: &[EmailSendJobBatchSqsMessage]) -> Result<(), Error> {
     );
     let system_clock = Arc::new(SystemClockUtc {});
 
+    let sdk_config = aws_config::load_from_env().await;
+    let retry_sqs_senders = (1..=conf.email.retry.max_retry_count)
+        .map(|n| -> Arc<dyn SqsSender + Send + Sync> {
+            Arc::new(SqsSenderImpl {
+                client: aws_sdk_sqs::Client::new(&sdk_config),
+                queue_url: EMAIL_SEND_QUEUE_URL.to_string(),
+                delay_seconds: Some(retry_delay_seconds(n, conf.email.retry.base_delay_seconds)),
+            })
+        })
+        .collect();
+
+    let email_transport: Arc<dyn EmailTransport + Send + Sync> = match &conf.email.transport {
+        EmailTransportConfig::Ses => Arc::new(SesEmailTransport {
+            client: aws_sdk_sesv2::Client::new(&sdk_config),
+            configuration_set_name: Some(conf.email.ses.configuration_set_name.clone()),
+        }),
+        EmailTransportConfig::Smtp(smtp) => Arc::new(SmtpEmailTransport::new(&smtp.url)?),
+        EmailTransportConfig::Local => Arc::new(LocalEmailTransport {
+            captured_email_repository: Arc::new(CapturedEmailRepository {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+        }),
+    };
+
     let executor = Executor {
         email_send_job_repository: Arc::new(EmailSendJobRepository {
             db: db.clone(),
             system_clock,
         }),
-        ses_sender: Arc::new(SesSenderImpl {
-            client: aws_sdk_sesv2::Client::new(&aws_config::load_from_env().await),
-            configuration_set_name: Some(conf.email.ses.configuration_set_name),
-        }),
+        email_transport,
+        retry_sqs_senders,
     };
     executor.execute(ms).await?;
 
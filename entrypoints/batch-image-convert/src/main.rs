@@ -10,7 +10,7 @@ use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, ts
 use core_cloud::aws::s3::S3ClientImpl;
 
 use opxs_base::{AppConfig, AppInfo};
-use opxs_image_convert::{Executor, ImageConvertJobRepository, ImageConvertJobSqsMessage, ImageConverterImpl};
+use opxs_image_convert::{Executor, ImageConvertJobKeyMessage, ImageConvertJobRepository, ImageConvertJobSqsMessage, ImageConverterImpl};
 
 const APPLICATION_NAME: &str = "opxs-batch-image-convert";
 
@@ -55,9 +55,13 @@ async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<(), Error> {
         info!("sqs event");
         for v in event.records.into_iter().flat_map(|n| n.body).collect::<Vec<_>>() {
             info!("{:?}", v);
-            let m = serde_json::from_str::<ImageConvertJobSqsMessage>(&v)?;
-            for v in m.records {
-                let p = Path::new(&v.s3.object.key);
+            // S3 upload events, or the fallback message the API wrote to the outbox with the job.
+            let keys: Vec<String> = match serde_json::from_str::<ImageConvertJobSqsMessage>(&v) {
+                Ok(m) => m.records.into_iter().map(|n| n.s3.object.key).collect(),
+                Err(_) => vec![serde_json::from_str::<ImageConvertJobKeyMessage>(&v)?.key],
+            };
+            for key in keys {
+                let p = Path::new(&key);
                 let job_id = p.file_name().ok_or(anyhow::anyhow!("file name is not found"))?;
                 job_ids.push(job_id.to_str().unwrap().to_string());
             }
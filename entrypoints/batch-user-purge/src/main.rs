@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use chrono::Duration;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use sqlx::postgres::PgPoolOptions;
+use tracing::info;
+
+use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+use opxs_auth::{
+    audit::{AuthAuditLogger, AuthAuditRepo},
+    guest::{GuestRepo, GuestService},
+    rate_limit::RateLimitRepo,
+    user::{UserRepo, UserService},
+};
+use opxs_base::{AppConfig, AppInfo};
+use opxs_image_convert::{ImageConvertJobCleaner, ImageConvertJobRepository, S3ObjectRemoverImpl};
+
+const APPLICATION_NAME: &str = "opxs-batch-user-purge";
+const PURGE_LIMIT: i64 = 100;
+
+async fn handler_sub() -> Result<(), Error> {
+    let info = AppInfo::new()?;
+    info!("info: {}", info);
+
+    let conf = AppConfig::load(APPLICATION_NAME, &info.mode).await?;
+    let db = Arc::new(
+        PgPoolOptions::new()
+            .max_connections(100)
+            .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
+            .connect(&conf.postgres.url)
+            .await?,
+    );
+    let system_clock = Arc::new(SystemClockUtc {});
+    let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+
+    let audit_logger = Arc::new(AuthAuditLogger {
+        audit_repo: Arc::new(AuthAuditRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        }),
+    });
+    let user_service = UserService {
+        user_repo: Arc::new(UserRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        }),
+        audit_logger: audit_logger.clone(),
+        system_clock: system_clock.clone(),
+        unregister_conf: conf.auth.unregister.clone(),
+    };
+    let guest_service = GuestService {
+        guest_repo: Arc::new(GuestRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        }),
+        rate_limit_repo: Arc::new(RateLimitRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        }),
+        audit_logger,
+        system_clock: system_clock.clone(),
+        guest_conf: conf.auth.guest.clone(),
+    };
+    let image_convert_job_cleaner = ImageConvertJobCleaner {
+        image_convert_job_repository: Arc::new(ImageConvertJobRepository {
+            db: db.clone(),
+            system_clock,
+            tsid_provider,
+        }),
+        s3_object_remover: Arc::new(S3ObjectRemoverImpl {
+            client: aws_sdk_s3::Client::new(&aws_config::load_from_env().await),
+            bucket: conf.image_convert.s3.bucket,
+        }),
+    };
+
+    // Guests nobody has used within the retention period are scheduled first and then removed the same way, jobs included.
+    let guest_user_ids = guest_service.schedule_purge(PURGE_LIMIT).await?;
+    info!("stale guests: {:?}", guest_user_ids);
+
+    let user_ids = user_service.get_purge_target_user_ids(PURGE_LIMIT).await?;
+    info!("purge targets: {:?}", user_ids);
+
+    // Jobs aren't tied to users by a foreign key, so they are only cleaned once the user is actually gone.
+    for user_id in user_ids.iter() {
+        if !user_service.purge(user_id).await? {
+            info!("deletion cancelled, skipped: {}", user_id);
+            continue;
+        }
+        image_convert_job_cleaner.clean_by_user_id(user_id).await?;
+    }
+
+    Ok(())
+}
+
+async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<(), Error> {
+    let (event, _context) = event.into_parts();
+    info!("event: {:?}", event);
+
+    handler_sub().await?;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    if cfg!(debug_assertions) {
+        tracing_subscriber::fmt().with_max_level(tracing::Level::TRACE).with_target(false).init();
+    } else {
+        tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .with_target(false)
+            .json()
+            .init();
+    }
+
+    info!("----- start -----");
+    run(service_fn(handler)).await
+}
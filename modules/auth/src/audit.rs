@@ -0,0 +1,7 @@
+mod logger;
+mod repo;
+mod service;
+
+pub use logger::*;
+pub use repo::*;
+pub use service::*;
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use tracing::error;
+
+use opxs_base::AppError;
+
+use crate::shared::model::{AuthAuditAuthMethod, AuthAuditEventType, AuthAuditOutcome, ClientInfo};
+
+use super::AuthAuditRepo;
+
+pub struct AuthAuditEvent<'a> {
+    pub user_id: Option<&'a str>,
+    pub actor_user_id: Option<&'a str>,
+    pub event_type: AuthAuditEventType,
+    pub auth_method: Option<AuthAuditAuthMethod>,
+    pub client: &'a ClientInfo,
+    pub detail: Option<serde_json::Value>,
+}
+
+pub struct AuthAuditLogger {
+    pub audit_repo: Arc<AuthAuditRepo>,
+}
+
+impl AuthAuditLogger {
+    // A failed operation is always reported with its own error, so a broken audit write is only traced in that case.
+    pub async fn write<T>(&self, event: AuthAuditEvent<'_>, res: &Result<T, AppError>) -> Result<(), AppError> {
+        let (outcome, error) = match res {
+            Ok(_) => (AuthAuditOutcome::Success, None),
+            Err(e) => (AuthAuditOutcome::Failure, Some(e.to_string())),
+        };
+
+        let mut detail = event.detail.unwrap_or_default();
+        if let Some(error) = error.as_ref() {
+            detail["error"] = json!(error);
+        }
+        let detail = if detail.is_null() { None } else { Some(detail.to_string()) };
+
+        let res = self
+            .audit_repo
+            .create_log(
+                event.user_id,
+                event.actor_user_id,
+                &event.event_type,
+                event.auth_method.as_ref(),
+                &outcome,
+                event.client,
+                detail.as_deref(),
+            )
+            .await;
+
+        match (res, error) {
+            (Err(e), Some(_)) => {
+                error!("failed to write auth audit log: {:?}", e);
+                Ok(())
+            }
+            (res, _) => res,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use chrono::{NaiveDateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::shared::model::{AuthAuditAuthMethod, AuthAuditEventType, AuthAuditLog, AuthAuditOutcome, ClientInfo};
+
+pub struct AuthAuditRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl AuthAuditRepo {
+    pub async fn create_log(
+        &self,
+        user_id: Option<&str>,
+        actor_user_id: Option<&str>,
+        event_type: &AuthAuditEventType,
+        auth_method: Option<&AuthAuditAuthMethod>,
+        outcome: &AuthAuditOutcome,
+        client: &ClientInfo,
+        detail: Option<&str>,
+    ) -> Result<(), AppError> {
+        let id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+INSERT INTO auth_audit_logs (id, user_id, actor_user_id, event_type, auth_method, outcome, ip_address, user_agent, detail, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);
+"#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(actor_user_id)
+        .bind(event_type)
+        .bind(auth_method)
+        .bind(outcome)
+        .bind(client.ip_address.as_deref())
+        .bind(client.user_agent.as_deref())
+        .bind(detail)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    pub async fn get_logs(
+        &self,
+        user_id: Option<&str>,
+        actor_user_id: Option<&str>,
+        event_type: Option<&AuthAuditEventType>,
+        before: Option<&NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<AuthAuditLog>, AppError> {
+        let res: Vec<AuthAuditLog> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM auth_audit_logs
+    WHERE ($1::VARCHAR IS NULL OR user_id = $1)
+        AND ($2::VARCHAR IS NULL OR actor_user_id = $2)
+        AND ($3::auth_audit_event_type IS NULL OR event_type = $3)
+        AND ($4::TIMESTAMP IS NULL OR created_at < $4)
+    ORDER BY created_at DESC
+    LIMIT $5;
+"#,
+        )
+        .bind(user_id)
+        .bind(actor_user_id)
+        .bind(event_type)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+}
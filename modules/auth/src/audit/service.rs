@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+
+use opxs_base::AppError;
+
+use crate::shared::model::{AuthAuditEventType, AuthAuditLog};
+
+use super::AuthAuditRepo;
+
+const MAX_LIMIT: i64 = 200;
+
+pub struct AuthAuditService {
+    pub audit_repo: Arc<AuthAuditRepo>,
+}
+
+impl AuthAuditService {
+    pub async fn get_user_logs(&self, user_id: &str, before: Option<&NaiveDateTime>, limit: i64) -> Result<Vec<AuthAuditLog>, AppError> {
+        self.audit_repo
+            .get_logs(Some(user_id), None, None, before, limit.clamp(1, MAX_LIMIT))
+            .await
+    }
+
+    pub async fn get_logs(
+        &self,
+        user_id: Option<&str>,
+        actor_user_id: Option<&str>,
+        event_type: Option<&AuthAuditEventType>,
+        before: Option<&NaiveDateTime>,
+        limit: i64,
+    ) -> Result<Vec<AuthAuditLog>, AppError> {
+        self.audit_repo
+            .get_logs(user_id, actor_user_id, event_type, before, limit.clamp(1, MAX_LIMIT))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::{
+        audit::{AuthAuditEvent, AuthAuditLogger},
+        shared::{
+            self,
+            model::{AuthAuditAuthMethod, AuthAuditOutcome, ClientInfo},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let audit_repo = Arc::new(AuthAuditRepo {
+            db: db.clone(),
+            system_clock: Arc::new(SystemClockUtc {}),
+            tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+        });
+        let audit_logger = AuthAuditLogger {
+            audit_repo: audit_repo.clone(),
+        };
+        let audit_service = AuthAuditService { audit_repo };
+
+        let client = ClientInfo {
+            ip_address: Some("192.0.2.1".to_string()),
+            user_agent: Some("test_user_agent".to_string()),
+        };
+
+        // login success
+        let res: Result<(), AppError> = Ok(());
+        audit_logger
+            .write(
+                AuthAuditEvent {
+                    user_id: Some("test_user_id"),
+                    actor_user_id: None,
+                    event_type: AuthAuditEventType::Login,
+                    auth_method: Some(AuthAuditAuthMethod::Email),
+                    client: &client,
+                    detail: None,
+                },
+                &res,
+            )
+            .await
+            .unwrap();
+
+        // login failure
+        let res: Result<(), AppError> = Err(AppError::WrongPassword);
+        audit_logger
+            .write(
+                AuthAuditEvent {
+                    user_id: None,
+                    actor_user_id: None,
+                    event_type: AuthAuditEventType::Login,
+                    auth_method: Some(AuthAuditAuthMethod::Email),
+                    client: &client,
+                    detail: Some(json!({ "email": "test@example.com" })),
+                },
+                &res,
+            )
+            .await
+            .unwrap();
+
+        let logs = audit_service.get_user_logs("test_user_id", None, 10).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].outcome, AuthAuditOutcome::Success);
+        assert_eq!(logs[0].ip_address, client.ip_address);
+
+        let logs = audit_service
+            .get_logs(None, None, Some(&AuthAuditEventType::Login), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].outcome, AuthAuditOutcome::Failure);
+        assert!(logs[0].detail.as_ref().unwrap().contains("test@example.com"));
+
+        // append-only
+        sqlx::query("DELETE FROM auth_audit_logs").execute(db.as_ref()).await.unwrap();
+        let logs = audit_service.get_logs(None, None, None, None, 10).await.unwrap();
+        assert_eq!(logs.len(), 2);
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::clock::SystemClock;
+
+use opxs_base::AppError;
+
+use crate::shared::model::{DeviceAuthorization, DeviceAuthorizationStatus};
+
+pub struct DeviceAuthRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl DeviceAuthRepo {
+    pub async fn create_authorization(
+        &self,
+        device_code: &str,
+        user_code: &str,
+        client_id: Option<&str>,
+        interval_seconds: i32,
+        expires_at: &DateTime<Utc>,
+    ) -> Result<DeviceAuthorization, AppError> {
+        let now = self.system_clock.now();
+
+        let authorization: DeviceAuthorization = sqlx::query_as(
+            r#"
+INSERT INTO device_authorizations (device_code, user_code, client_id, status, interval_seconds, expires_at, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING *;
+"#,
+        )
+        .bind(device_code)
+        .bind(user_code)
+        .bind(client_id)
+        .bind(DeviceAuthorizationStatus::Pending)
+        .bind(interval_seconds)
+        .bind(expires_at)
+        .bind(now)
+        .bind(now)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(authorization)
+    }
+
+    pub async fn get_pending_by_user_code(&self, user_code: &str) -> Result<Option<DeviceAuthorization>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<DeviceAuthorization> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM device_authorizations
+    WHERE user_code = $1 AND status = 'Pending' AND expires_at > $2;
+"#,
+        )
+        .bind(user_code)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn decide(&self, user_code: &str, user_id: &str, status: DeviceAuthorizationStatus) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE device_authorizations
+    SET status = $3, user_id = $2, updated_at = $4
+    WHERE user_code = $1 AND status = 'Pending' AND expires_at > $4;
+"#,
+        )
+        .bind(user_code)
+        .bind(user_id)
+        .bind(status)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::DeviceCodeExpired);
+        }
+
+        Ok(())
+    }
+
+    // Records the poll only when the interval has passed, in one statement so concurrent polls can't both get through.
+    pub async fn try_update_polled_at(&self, device_code: &str) -> Result<Option<DeviceAuthorization>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<DeviceAuthorization> = sqlx::query_as(
+            r#"
+UPDATE device_authorizations
+    SET last_polled_at = $2, updated_at = $2
+    WHERE device_code = $1 AND (last_polled_at IS NULL OR last_polled_at + interval_seconds * INTERVAL '1 second' <= $2)
+    RETURNING *;
+"#,
+        )
+        .bind(device_code)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    // Returns false when there is no such device code.
+    pub async fn slow_down(&self, device_code: &str, extra_seconds: i32) -> Result<bool, AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE device_authorizations
+    SET last_polled_at = $2, interval_seconds = interval_seconds + $3, updated_at = $2
+    WHERE device_code = $1;
+"#,
+        )
+        .bind(device_code)
+        .bind(now)
+        .bind(extra_seconds)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    // Flips Approved to Consumed so that only one poll can redeem the approval.
+    pub async fn consume(&self, device_code: &str) -> Result<Option<String>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<(Option<String>,)> = sqlx::query_as(
+            r#"
+UPDATE device_authorizations
+    SET status = 'Consumed', updated_at = $2
+    WHERE device_code = $1 AND status = 'Approved' AND expires_at > $2
+    RETURNING user_id;
+"#,
+        )
+        .bind(device_code)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.and_then(|(user_id,)| user_id))
+    }
+
+    pub async fn delete_expired(&self, before: &DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+DELETE FROM device_authorizations
+    WHERE expires_at < $1;
+"#,
+        )
+        .bind(before)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
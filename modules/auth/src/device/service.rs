@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+
+use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
+
+use opxs_base::AppError;
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    rate_limit::RateLimitRepo,
+    shared::model::{AuthAuditAuthMethod, AuthAuditEventType, AuthToken, ClientInfo, DeviceAuthorization, DeviceAuthorizationStatus},
+    token::TokenService,
+};
+
+use super::DeviceAuthRepo;
+
+const EXPIRES_IN_MINUTES: i64 = 10;
+const INTERVAL_SECONDS: i32 = 5;
+const SLOW_DOWN_SECONDS: i32 = 5;
+const AUTHORIZE_LIMIT_PER_HOUR: i32 = 30;
+// Consonants only, so codes can't spell words and are easy to read off a TV screen.
+const USER_CODE_CHARS: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+pub struct DeviceAuthService {
+    pub device_repo: Arc<DeviceAuthRepo>,
+    pub rate_limit_repo: Arc<RateLimitRepo>,
+    pub token_service: Arc<TokenService>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
+}
+
+impl DeviceAuthService {
+    // Unauthenticated, so each address gets a fixed number of codes per hour to keep the table from being flooded.
+    pub async fn authorize(&self, client_id: Option<&str>, client: &ClientInfo) -> Result<DeviceAuthorization, AppError> {
+        let now = self.system_clock.now();
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            let window = Duration::hours(1);
+            self.rate_limit_repo.delete_expired(&(now - window)).await?;
+            self.rate_limit_repo
+                .hit(&format!("device_authorize:{}", ip_address), window, AUTHORIZE_LIMIT_PER_HOUR)
+                .await?;
+        }
+        self.device_repo.delete_expired(&(now - Duration::days(1))).await?;
+
+        let device_code = hex::encode(self.random_bytes_provider.get_bytes(32));
+        let user_code = self.gen_user_code();
+        let expires_at = now + Duration::minutes(EXPIRES_IN_MINUTES);
+
+        self.device_repo
+            .create_authorization(&device_code, &user_code, client_id, INTERVAL_SECONDS, &expires_at)
+            .await
+    }
+
+    pub async fn get_pending(&self, user_code: &str) -> Result<DeviceAuthorization, AppError> {
+        self.device_repo
+            .get_pending_by_user_code(&normalize_user_code(user_code))
+            .await?
+            .ok_or(AppError::DeviceCodeExpired)
+    }
+
+    pub async fn approve(&self, user_code: &str, user_id: &str) -> Result<(), AppError> {
+        self.device_repo
+            .decide(&normalize_user_code(user_code), user_id, DeviceAuthorizationStatus::Approved)
+            .await
+    }
+
+    pub async fn deny(&self, user_code: &str, user_id: &str) -> Result<(), AppError> {
+        self.device_repo
+            .decide(&normalize_user_code(user_code), user_id, DeviceAuthorizationStatus::Denied)
+            .await
+    }
+
+    // Only a redeemed approval is audited; pending and throttled polls would just be noise.
+    pub async fn poll(&self, device_code: &str, client: &ClientInfo) -> Result<AuthToken, AppError> {
+        let (user_id, auth_token) = self.poll_sub(device_code).await?;
+
+        let res: Result<(), AppError> = Ok(());
+        let event = AuthAuditEvent {
+            user_id: Some(&user_id),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Login,
+            auth_method: Some(AuthAuditAuthMethod::DeviceCode),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+
+        Ok(auth_token)
+    }
+
+    async fn poll_sub(&self, device_code: &str) -> Result<(String, AuthToken), AppError> {
+        let authorization = match self.device_repo.try_update_polled_at(device_code).await? {
+            Some(authorization) => authorization,
+            None if self.device_repo.slow_down(device_code, SLOW_DOWN_SECONDS).await? => return Err(AppError::SlowDown),
+            None => return Err(AppError::DeviceCodeExpired),
+        };
+        let now = self.system_clock.now().naive_utc();
+
+        if authorization.expires_at <= now {
+            return Err(AppError::DeviceCodeExpired);
+        }
+
+        match authorization.status {
+            DeviceAuthorizationStatus::Pending => Err(AppError::AuthorizationPending),
+            DeviceAuthorizationStatus::Denied => Err(AppError::AccessDenied),
+            DeviceAuthorizationStatus::Consumed => Err(AppError::DeviceCodeExpired),
+            DeviceAuthorizationStatus::Approved => {
+                let user_id = self.device_repo.consume(device_code).await?.ok_or(AppError::DeviceCodeExpired)?;
+                let auth_token = self.token_service.create_delegated(&user_id).await?;
+                Ok((user_id, auth_token))
+            }
+        }
+    }
+
+    fn gen_user_code(&self) -> String {
+        let code: String = self
+            .random_bytes_provider
+            .get_bytes(8)
+            .iter()
+            .map(|b| USER_CODE_CHARS[*b as usize % USER_CODE_CHARS.len()] as char)
+            .collect();
+        format!("{}-{}", &code[..4], &code[4..])
+    }
+}
+
+// Accepts what people actually type: lowercase, missing or extra separators.
+fn normalize_user_code(user_code: &str) -> String {
+    let code: String = user_code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if code.len() == 8 {
+        format!("{}-{}", &code[..4], &code[4..])
+    } else {
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use opxs_base::{JwtConfig, JwtSecretConfig};
+
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{
+            self,
+            model::{UserAuthenticationType, UserRole},
+        },
+        token::TokenRepo,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: Arc::new(AuthAuditRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+            }),
+        });
+        let device_service = DeviceAuthService {
+            device_repo: Arc::new(DeviceAuthRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            rate_limit_repo: Arc::new(RateLimitRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            token_service: Arc::new(TokenService {
+                system_clock: system_clock.clone(),
+                random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+                jwt_conf: JwtConfig {
+                    secret: JwtSecretConfig {
+                        current: "current".to_string(),
+                        previous: "previous".to_string(),
+                    },
+                },
+                token_repo: Arc::new(TokenRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+                }),
+                audit_logger: audit_logger.clone(),
+            }),
+            audit_logger,
+            system_clock: system_clock.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+        };
+        let client = ClientInfo::default();
+        let user_id = "test_user_id";
+
+        // create user
+        let now = system_clock.now();
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(user_id)
+        .bind("test_user_name")
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+
+        // pending and slow down
+        let authorization = device_service.authorize(Some("opxs-cli"), &client).await.unwrap();
+        assert!(matches!(
+            device_service.poll(&authorization.device_code, &client).await,
+            Err(AppError::AuthorizationPending)
+        ));
+        assert!(matches!(
+            device_service.poll(&authorization.device_code, &client).await,
+            Err(AppError::SlowDown)
+        ));
+        assert!(matches!(device_service.poll("unknown", &client).await, Err(AppError::DeviceCodeExpired)));
+
+        // concurrent polls within one interval are throttled all but once
+        sqlx::query("UPDATE device_authorizations SET last_polled_at = NULL")
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+        let (a, b) = tokio::join!(
+            device_service.poll(&authorization.device_code, &client),
+            device_service.poll(&authorization.device_code, &client)
+        );
+        let pending = [&a, &b].iter().filter(|n| matches!(n, Err(AppError::AuthorizationPending))).count();
+        let slow_down = [&a, &b].iter().filter(|n| matches!(n, Err(AppError::SlowDown))).count();
+        assert_eq!((pending, slow_down), (1, 1));
+
+        // approve with a loosely typed user code
+        let typed_user_code = authorization.user_code.replace('-', "").to_lowercase();
+        device_service.get_pending(&typed_user_code).await.unwrap();
+        device_service.approve(&typed_user_code, user_id).await.unwrap();
+        assert!(matches!(
+            device_service.deny(&authorization.user_code, user_id).await,
+            Err(AppError::DeviceCodeExpired)
+        ));
+
+        sqlx::query("UPDATE device_authorizations SET last_polled_at = NULL")
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+        let token = device_service.poll(&authorization.device_code, &client).await.unwrap();
+        assert!(!token.refresh_token.is_empty());
+
+        // single use
+        sqlx::query("UPDATE device_authorizations SET last_polled_at = NULL")
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+        assert!(matches!(
+            device_service.poll(&authorization.device_code, &client).await,
+            Err(AppError::DeviceCodeExpired)
+        ));
+
+        // deny
+        let authorization = device_service.authorize(None, &client).await.unwrap();
+        device_service.deny(&authorization.user_code, user_id).await.unwrap();
+        assert!(matches!(
+            device_service.poll(&authorization.device_code, &client).await,
+            Err(AppError::AccessDenied)
+        ));
+
+        // authorize is limited per address
+        let client = ClientInfo {
+            ip_address: Some("192.0.2.1".to_string()),
+            user_agent: None,
+        };
+        for _ in 0..AUTHORIZE_LIMIT_PER_HOUR {
+            device_service.authorize(None, &client).await.unwrap();
+        }
+        assert!(matches!(device_service.authorize(None, &client).await, Err(AppError::TooManyRequests)));
+    }
+
+    #[test]
+    fn normalize_user_code_test() {
+        assert_eq!(normalize_user_code("bcdf-ghjk"), "BCDF-GHJK");
+        assert_eq!(normalize_user_code(" BCDFGHJK "), "BCDF-GHJK");
+        assert_eq!(normalize_user_code("BCD"), "BCD");
+    }
+}
@@ -7,7 +7,10 @@ use core_base::{clock::SystemClock, tsid::TsidProvider};
 
 use opxs_base::AppError;
 
-use crate::shared::model::{EmailUser, UserAuthenticationType, UserRole};
+use crate::{
+    legal::LegalRepo,
+    shared::model::{EmailUser, LegalConsent, UserAuthenticationType, UserRole},
+};
 
 pub struct EmailAuthRepo {
     pub db: Arc<PgPool>,
@@ -16,7 +19,15 @@ pub struct EmailAuthRepo {
 }
 
 impl EmailAuthRepo {
-    pub async fn create_user(&self, name: &str, email: &str, password_hash: &str, salt: &str) -> Result<String, AppError> {
+    pub async fn create_user(
+        &self,
+        name: &str,
+        email: &str,
+        normalized_email: &str,
+        password_hash: &str,
+        salt: &str,
+        consent: &LegalConsent<'_>,
+    ) -> Result<String, AppError> {
         let user_id = self.tsid_provider.gen().to_string();
         let now = self.system_clock.now();
 
@@ -38,20 +49,23 @@ INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
         .await
         .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
-        sqlx::query(
+        let res = sqlx::query(
             r#"
-INSERT INTO user_auth_emails (user_id, email, password_hash, salt, created_at, updated_at)
-    VALUES ($1, $2, $3, $4, $5, $6)
-    ON CONFLICT (email)
+INSERT INTO user_auth_emails (user_id, email, normalized_email, password_hash, salt, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    ON CONFLICT (normalized_email)
     DO UPDATE SET
         user_id = $1,
-        password_hash = $3,
-        salt = $4,
-        updated_at = $6;
+        email = $2,
+        password_hash = $4,
+        salt = $5,
+        updated_at = $7
+    WHERE user_auth_emails.email_verified = false;
 "#,
         )
         .bind(&user_id)
         .bind(email)
+        .bind(normalized_email)
         .bind(password_hash)
         .bind(salt)
         .bind(now)
@@ -60,22 +74,124 @@ INSERT INTO user_auth_emails (user_id, email, password_hash, salt, created_at, u
         .await
         .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
+        // A verified account already owns another spelling of this address.
+        if res.rows_affected() < 1 {
+            return Err(AppError::DuplicateEmail);
+        }
+
+        LegalRepo::insert_acceptances(&mut tx, &user_id, consent, &now).await?;
+
         tx.commit().await?;
 
         Ok(user_id)
     }
 
-    pub async fn delete_user(&self, id: &str) -> Result<(), AppError> {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn attach_guest_email(
+        &self,
+        user_id: &str,
+        name: &str,
+        email: &str,
+        normalized_email: &str,
+        password_hash: &str,
+        salt: &str,
+        consent: &LegalConsent<'_>,
+    ) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
         let mut tx = self.db.begin().await?;
 
-        let queries = vec![
-            sqlx::query("DELETE FROM users WHERE id = $1").bind(id),
-            sqlx::query("DELETE FROM user_auth_emails WHERE user_id = $1").bind(id),
-            sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1").bind(id),
-        ];
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET name = $2, updated_at = $3
+    WHERE id = $1 AND role = $4;
+"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(now)
+        .bind(UserRole::Guest)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
-        for query in queries {
-            query.execute(&mut tx).await.map_err(|e| AppError::UnexpectedError(e.into()))?;
+        if res.rows_affected() < 1 {
+            return Err(AppError::Forbidden);
+        }
+
+        // A guest may retry with another address before confirming the first one.
+        sqlx::query("DELETE FROM user_auth_emails WHERE user_id = $1 AND email_verified = false")
+            .bind(user_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        let res = sqlx::query(
+            r#"
+INSERT INTO user_auth_emails (user_id, email, normalized_email, password_hash, salt, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    ON CONFLICT (normalized_email)
+    DO UPDATE SET
+        user_id = $1,
+        email = $2,
+        password_hash = $4,
+        salt = $5,
+        updated_at = $7
+    WHERE user_auth_emails.email_verified = false;
+"#,
+        )
+        .bind(user_id)
+        .bind(email)
+        .bind(normalized_email)
+        .bind(password_hash)
+        .bind(salt)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        // A verified account already owns another spelling of this address.
+        if res.rows_affected() < 1 {
+            return Err(AppError::DuplicateEmail);
+        }
+
+        LegalRepo::insert_acceptances(&mut tx, user_id, consent, &now).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // No-op for accounts that were never guests.
+    pub async fn promote_guest(&self, user_id: &str) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET authentication_type = $2, role = $3, updated_at = $5
+    WHERE id = $1 AND role = $4;
+"#,
+        )
+        .bind(user_id)
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(UserRole::Guest)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() > 0 {
+            sqlx::query("DELETE FROM user_guests WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut tx)
+                .await
+                .map_err(|e| AppError::UnexpectedError(e.into()))?;
         }
 
         tx.commit().await?;
@@ -103,6 +219,26 @@ SELECT EXISTS (
         Ok(existed)
     }
 
+    pub async fn exist_user_by_normalized_email(&self, normalized_email: &str) -> Result<bool, AppError> {
+        let (existed,): (bool,) = sqlx::query_as(
+            r#"
+SELECT EXISTS (
+    SELECT u.id
+        FROM users u
+        JOIN user_auth_emails e on u.id = e.user_id
+        WHERE e.normalized_email = $1 AND e.email_verified = true
+        LIMIT 1
+);
+"#,
+        )
+        .bind(normalized_email)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(existed)
+    }
+
     pub async fn get_user(&self, email: &str) -> Result<EmailUser, AppError> {
         let user: Option<EmailUser> = sqlx::query_as(
             r#"
@@ -125,6 +261,24 @@ SELECT u.id, u.name, u.role, e.email, e.password_hash, e.salt, u.created_at, u.u
         Ok(user.unwrap())
     }
 
+    pub async fn get_user_by_user_id(&self, user_id: &str) -> Result<EmailUser, AppError> {
+        let user: Option<EmailUser> = sqlx::query_as(
+            r#"
+SELECT u.id, u.name, u.role, e.email, e.password_hash, e.salt, u.created_at, u.updated_at
+    FROM users u
+    JOIN user_auth_emails e on u.id = e.user_id
+    WHERE u.id = $1 AND e.email_verified = true
+    LIMIT 1;
+"#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        user.ok_or(AppError::UserNotFound)
+    }
+
     pub async fn update_email_verified(&self, email: &str, email_verified: bool) -> Result<(), AppError> {
         let now = self.system_clock.now();
 
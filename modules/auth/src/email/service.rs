@@ -1,36 +1,161 @@
 use std::sync::Arc;
 
 use chrono::{Duration, Utc};
+use serde_json::json;
 
 use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
 
 use opxs_base::{AppError, JwtConfig};
 
-use crate::shared::{jwt, kdf::Kdf};
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    invitation::InvitationService,
+    legal::LegalService,
+    policy::RegisterPolicy,
+    shared::{
+        jwt,
+        kdf::Kdf,
+        model::{AuthAuditAuthMethod, AuthAuditEventType, ClientInfo, LegalConsent, User, UserRole},
+    },
+    user::UserRepo,
+};
 
 use super::EmailAuthRepo;
 
+const DUMMY_CREDENTIAL: [u8; 32] = [0; 32];
+
+pub enum EmailRegistration {
+    Created { confirm_token: String },
+    // The address already has an account; the caller should notify its owner instead of confirming.
+    AlreadyRegistered,
+}
+
 #[derive(Clone)]
 pub struct EmailAuthService {
     pub auth_repo: Arc<EmailAuthRepo>,
+    pub user_repo: Arc<UserRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
     pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
     pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
     pub jwt_conf: JwtConfig,
     pub kdf: Kdf,
+    pub register_policy: Arc<RegisterPolicy>,
+    pub invitation_service: Arc<InvitationService>,
+    pub legal_service: Arc<LegalService>,
 }
 
 impl EmailAuthService {
-    pub async fn register(&self, name: &str, email: &str, password: &str) -> Result<String, AppError> {
-        if self.auth_repo.exist_user(email).await? {
-            return Err(AppError::DuplicateEmail);
+    pub async fn register(
+        &self,
+        name: &str,
+        email: &str,
+        password: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<EmailRegistration, AppError> {
+        let res = self
+            .register_sub(None, name, email, password, invitation_code, legal_document_ids, client)
+            .await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|(user_id, _)| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Register,
+            auth_method: Some(AuthAuditAuthMethod::Email),
+            client,
+            detail: Some(json!({ "email": email })),
+        };
+        self.audit_logger.write(event, &res).await?;
+
+        match res {
+            Ok((_, confirm_token)) => Ok(EmailRegistration::Created { confirm_token }),
+            Err(AppError::DuplicateEmail) => Ok(EmailRegistration::AlreadyRegistered),
+            Err(e) => Err(e),
         }
+    }
+
+    // Attaches an unverified address to a guest; the guest becomes a regular account when it is confirmed.
+    pub async fn upgrade_guest(
+        &self,
+        guest: &User,
+        name: &str,
+        email: &str,
+        password: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<EmailRegistration, AppError> {
+        let res = self
+            .register_sub(Some(guest), name, email, password, invitation_code, legal_document_ids, client)
+            .await;
+        let event = AuthAuditEvent {
+            user_id: Some(guest.id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Register,
+            auth_method: Some(AuthAuditAuthMethod::Email),
+            client,
+            detail: Some(json!({ "email": email, "guest": true })),
+        };
+        self.audit_logger.write(event, &res).await?;
+
+        match res {
+            Ok((_, confirm_token)) => Ok(EmailRegistration::Created { confirm_token }),
+            Err(AppError::DuplicateEmail) => Ok(EmailRegistration::AlreadyRegistered),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Every step that costs time runs before the duplicate check, so new and existing addresses take the same path.
+    async fn register_sub(
+        &self,
+        guest: Option<&User>,
+        name: &str,
+        email: &str,
+        password: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<(String, String), AppError> {
+        if guest.is_some_and(|g| g.role != UserRole::Guest) {
+            return Err(AppError::Forbidden);
+        }
+        let normalized_email = self.register_policy.check(name, email, password)?;
+        self.legal_service.check(legal_document_ids).await?;
 
         let salt = self.kdf.gen_salt()?;
         let password_hash = self.kdf.derive(password, &salt)?;
 
-        self.auth_repo
-            .create_user(name, email, &hex::encode(password_hash), &hex::encode(salt))
-            .await?;
+        let consent = LegalConsent {
+            document_ids: legal_document_ids,
+            client,
+        };
+        let invitation = self.invitation_service.reserve(invitation_code, Some(email)).await?;
+        let res = match self.auth_repo.exist_user_by_normalized_email(&normalized_email).await {
+            Ok(true) => Err(AppError::DuplicateEmail),
+            Ok(false) => match guest {
+                Some(guest) => self
+                    .auth_repo
+                    .attach_guest_email(
+                        &guest.id,
+                        name,
+                        email,
+                        &normalized_email,
+                        &hex::encode(password_hash),
+                        &hex::encode(salt),
+                        &consent,
+                    )
+                    .await
+                    .map(|_| guest.id.clone()),
+                None => {
+                    self.auth_repo
+                        .create_user(name, email, &normalized_email, &hex::encode(password_hash), &hex::encode(salt), &consent)
+                        .await
+                }
+            },
+            Err(e) => Err(e),
+        };
+        self.invitation_service.complete(invitation.as_ref(), &res).await?;
+        let user_id = res?;
 
         let now = self.system_clock.now();
 
@@ -38,20 +163,61 @@ impl EmailAuthService {
         let expires_in = Duration::minutes(30);
         let token = jwt::sign(&self.jwt_conf.secret.current, &sub, expires_in, now)?;
 
-        Ok(token)
+        Ok((user_id, token))
     }
 
-    pub async fn unregister(&self, id: &str) -> Result<(), AppError> {
-        self.auth_repo.delete_user(id).await?;
-        Ok(())
+    pub async fn login(&self, email: &str, password: &str, client: &ClientInfo) -> Result<String, AppError> {
+        let res = self.login_sub(email, password).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|user_id| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Login,
+            auth_method: Some(AuthAuditAuthMethod::Email),
+            client,
+            detail: Some(json!({ "email": email })),
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
     }
 
-    pub async fn login(&self, email: &str, password: &str) -> Result<String, AppError> {
-        if !self.auth_repo.exist_user(email).await? {
-            return Err(AppError::UserNotFound);
+    async fn login_sub(&self, email: &str, password: &str) -> Result<String, AppError> {
+        let user = match self.auth_repo.get_user(email).await {
+            Ok(user) => user,
+            Err(AppError::UserNotFound) => {
+                // Burn the same KDF work as a real verification so unknown addresses can't be told apart by timing.
+                self.kdf.verify(password, &DUMMY_CREDENTIAL, &DUMMY_CREDENTIAL)?;
+                return Err(AppError::UserNotFound);
+            }
+            Err(e) => return Err(e),
+        };
+        let salt = hex::decode(user.salt).map_err(|e| AppError::UnexpectedError(e.into()))?;
+        let password_hash = hex::decode(user.password_hash).map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if !self.kdf.verify(password, &salt, &password_hash)? {
+            return Err(AppError::WrongPassword);
         }
 
-        let user = self.auth_repo.get_user(email).await?;
+        self.user_repo.cancel_deletion(&user.id).await?;
+
+        Ok(user.id)
+    }
+
+    pub async fn reauthenticate(&self, user_id: &str, password: &str, client: &ClientInfo) -> Result<(), AppError> {
+        let res = self.reauthenticate_sub(user_id, password).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Reauthenticate,
+            auth_method: Some(AuthAuditAuthMethod::Email),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn reauthenticate_sub(&self, user_id: &str, password: &str) -> Result<(), AppError> {
+        let user = self.auth_repo.get_user_by_user_id(user_id).await?;
         let salt = hex::decode(user.salt).map_err(|e| AppError::UnexpectedError(e.into()))?;
         let password_hash = hex::decode(user.password_hash).map_err(|e| AppError::UnexpectedError(e.into()))?;
 
@@ -59,10 +225,24 @@ impl EmailAuthService {
             return Err(AppError::WrongPassword);
         }
 
-        Ok(user.id)
+        Ok(())
     }
 
-    pub async fn confirm(&self, token: &str) -> Result<String, AppError> {
+    pub async fn confirm(&self, token: &str, client: &ClientInfo) -> Result<String, AppError> {
+        let res = self.confirm_sub(token).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|user_id| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Confirm,
+            auth_method: Some(AuthAuditAuthMethod::Email),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn confirm_sub(&self, token: &str) -> Result<String, AppError> {
         let now = self.system_clock.now();
         let claims = jwt::verify(&self.jwt_conf.secret.current, token, now)?;
 
@@ -70,6 +250,7 @@ impl EmailAuthService {
         self.auth_repo.update_email_verified(&email, true).await?;
 
         let user = self.auth_repo.get_user(&email).await?;
+        self.auth_repo.promote_guest(&user.id).await?;
 
         Ok(user.id)
     }
@@ -77,51 +258,73 @@ impl EmailAuthService {
 
 #[cfg(test)]
 mod tests {
-    use chrono::Duration;
-    use sqlx::postgres::PgPoolOptions;
-
     use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
-    use core_migration::postgres::PostgresMigrator;
-    use core_testkit::containers::postgres::PostgresContainer;
 
-    use opxs_base::JwtSecretConfig;
+    use opxs_base::{EmailPolicyConfig, InvitationConfig, JwtSecretConfig, PasswordPolicyConfig, RegisterPolicyConfig, UnregisterConfig};
 
-    use crate::shared::{self, kdf::KdfAlgorithm};
+    use crate::{
+        audit::AuthAuditRepo,
+        invitation::InvitationRepo,
+        legal::LegalRepo,
+        shared::{self, kdf::KdfAlgorithm, model::AuthAuditOutcome},
+        user::UserService,
+    };
 
     use super::*;
 
     #[tokio::test]
     async fn simple_test() {
         let docker = testcontainers::clients::Cli::default();
-        let container = PostgresContainer::new(&docker, shared::POSTGRES_VERSION);
-
-        let db = Arc::new(
-            PgPoolOptions::new()
-                .max_connections(100)
-                .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
-                .connect(&container.connection_string)
-                .await
-                .unwrap(),
-        );
-
-        let migrations_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../conf/migrations");
-        let migrator = PostgresMigrator::new(&container.connection_string, migrations_path, "opxs-api", "")
-            .await
-            .unwrap();
-        migrator.migrate().await.unwrap();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
 
         let user_name = "user_name";
-        let user_email = "user_email";
-        let password = "password";
+        let user_email = "user@example.com";
+        let password = "kq8zm3xp-Vw";
 
         let system_clock = Arc::new(SystemClockUtc {});
         let random_bytes_provider = Arc::new(RandomBytesProviderImpl {});
         let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
         let auth_repo = Arc::new(EmailAuthRepo {
-            db,
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let user_repo = Arc::new(UserRepo {
+            db: db.clone(),
             system_clock: system_clock.clone(),
-            tsid_provider,
         });
+        let audit_repo = Arc::new(AuthAuditRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let invitation_service = Arc::new(InvitationService {
+            invitation_repo: Arc::new(InvitationRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: random_bytes_provider.clone(),
+            invitation_conf: InvitationConfig {
+                required: false,
+                expires_in_days: 7,
+                max_uses: 1,
+            },
+        });
+        let legal_service = Arc::new(LegalService {
+            legal_repo: Arc::new(LegalRepo {
+                db,
+                system_clock: system_clock.clone(),
+                tsid_provider,
+            }),
+            system_clock: system_clock.clone(),
+        });
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: audit_repo.clone(),
+        });
+        let client = ClientInfo::default();
         let jwt_conf = JwtConfig {
             secret: JwtSecretConfig {
                 current: "a".to_string(),
@@ -135,28 +338,122 @@ mod tests {
 
         let auth_service = EmailAuthService {
             auth_repo: auth_repo.clone(),
-            system_clock,
+            user_repo: user_repo.clone(),
+            audit_logger: audit_logger.clone(),
+            system_clock: system_clock.clone(),
             random_bytes_provider,
             jwt_conf,
             kdf,
+            register_policy: Arc::new(RegisterPolicy::new(&RegisterPolicyConfig {
+                email: EmailPolicyConfig {
+                    allowed_domains: vec![],
+                    denied_domains: vec![],
+                    deny_disposable: true,
+                },
+                password: PasswordPolicyConfig {
+                    min_length: 8,
+                    max_length: 128,
+                    min_score: 2,
+                    deny_breached: true,
+                },
+            })),
+            invitation_service,
+            legal_service,
         };
 
         // register
-        let token = auth_service.register(user_name, user_email, password).await.unwrap();
-        assert!(matches!(auth_service.login(user_email, password).await, Err(AppError::UserNotFound)));
-        auth_service.confirm(&token).await.unwrap();
+        let EmailRegistration::Created { confirm_token: token } =
+            auth_service.register(user_name, user_email, password, None, &[], &client).await.unwrap()
+        else {
+            panic!("user should be created");
+        };
+        assert!(matches!(
+            auth_service.login(user_email, password, &client).await,
+            Err(AppError::UserNotFound)
+        ));
+        auth_service.confirm(&token, &client).await.unwrap();
 
         // login
-        assert!(auth_service.login(user_email, password).await.is_ok());
+        assert!(auth_service.login(user_email, password, &client).await.is_ok());
+
+        // reauthenticate
+        let user_id = auth_service.login(user_email, password, &client).await.unwrap();
+        assert!(auth_service.reauthenticate(&user_id, password, &client).await.is_ok());
+        assert!(matches!(
+            auth_service.reauthenticate(&user_id, "wrong-password", &client).await,
+            Err(AppError::WrongPassword)
+        ));
+
+        // register duplicate
+        assert!(matches!(
+            auth_service
+                .register(user_name, "User+other@Example.com", password, None, &[], &client)
+                .await,
+            Ok(EmailRegistration::AlreadyRegistered)
+        ));
+        assert!(matches!(
+            auth_service
+                .register(user_name, "other@example.com", "password", None, &[], &client)
+                .await,
+            Err(AppError::PolicyViolation(_))
+        ));
+        // even when the existence check is raced, a verified address isn't taken over by another spelling
+        let normalized_email = auth_service.register_policy.email.normalize(user_email).unwrap();
+        assert!(matches!(
+            auth_repo
+                .create_user(
+                    user_name,
+                    "User+race@Example.com",
+                    &normalized_email,
+                    "password_hash",
+                    "salt",
+                    &LegalConsent {
+                        document_ids: &[],
+                        client: &client,
+                    },
+                )
+                .await,
+            Err(AppError::DuplicateEmail)
+        ));
 
         // get user
         let user = auth_repo.get_user(user_email).await.unwrap();
         assert_eq!(user.name, user_name.to_string());
 
-        // unregister
-        assert!(auth_service.unregister(user.id.as_str()).await.is_ok());
+        // unregister and restore by login
+        let user_service = UserService {
+            user_repo: user_repo.clone(),
+            audit_logger: audit_logger.clone(),
+            system_clock: system_clock.clone(),
+            unregister_conf: UnregisterConfig { grace_period_days: 30 },
+        };
+        assert!(user_service.unregister(user.id.as_str(), &client).await.is_ok());
+        assert!(matches!(user_service.get_user(user.id.as_str()).await, Err(AppError::UserNotFound)));
+        assert!(auth_service.login(user_email, password, &client).await.is_ok());
+        assert!(user_service.get_user(user.id.as_str()).await.is_ok());
+
+        // unregister and purge
+        let user_service = UserService {
+            user_repo,
+            audit_logger,
+            system_clock,
+            unregister_conf: UnregisterConfig { grace_period_days: 0 },
+        };
+        assert!(user_service.unregister(user.id.as_str(), &client).await.is_ok());
+        assert!(user_service.purge(user.id.as_str()).await.is_ok());
 
         // login
-        assert!(matches!(auth_service.login(user_email, password).await, Err(AppError::UserNotFound)));
+        assert!(matches!(
+            auth_service.login(user_email, password, &client).await,
+            Err(AppError::UserNotFound)
+        ));
+
+        // audit logs
+        let logs = audit_repo
+            .get_logs(None, None, Some(&AuthAuditEventType::Login), None, 100)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 5);
+        assert_eq!(logs.iter().filter(|log| log.outcome == AuthAuditOutcome::Failure).count(), 2);
     }
 }
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::shared::model::{UserAuthenticationType, UserRole};
+
+const GUEST_USER_NAME: &str = "Guest";
+
+pub struct GuestRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl GuestRepo {
+    pub async fn create_guest(&self, device_id_hash: &str, convert_quota: i32) -> Result<String, AppError> {
+        let user_id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(&user_id)
+        .bind(GUEST_USER_NAME)
+        .bind(UserAuthenticationType::Guest)
+        .bind(UserRole::Guest)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO user_guests (user_id, device_id_hash, convert_quota, last_seen_at, created_at)
+    VALUES ($1, $2, $3, $4, $5)
+"#,
+        )
+        .bind(&user_id)
+        .bind(device_id_hash)
+        .bind(convert_quota)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+
+    pub async fn touch_guest(&self, device_id_hash: &str) -> Result<Option<String>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<(String,)> = sqlx::query_as(
+            r#"
+UPDATE user_guests
+    SET last_seen_at = $2
+    WHERE device_id_hash = $1
+    RETURNING user_id;
+"#,
+        )
+        .bind(device_id_hash)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.map(|(user_id,)| user_id))
+    }
+
+    // A guest still refreshing its session is in use even if it hasn't signed in again.
+    // Stale guests are scheduled for deletion right away, so they are purged like any other unregistered user.
+    pub async fn schedule_stale_deletion(&self, last_seen_before: &DateTime<Utc>, limit: i64) -> Result<Vec<String>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Vec<(String,)> = sqlx::query_as(
+            r#"
+UPDATE users
+    SET deletion_requested_at = $3, deletion_scheduled_at = $3, updated_at = $3
+    WHERE deletion_scheduled_at IS NULL AND id IN (
+        SELECT g.user_id
+            FROM user_guests g
+            WHERE g.last_seen_at <= $1
+                AND NOT EXISTS (SELECT 1 FROM refresh_tokens t WHERE t.user_id = g.user_id AND t.updated_at > $1)
+            ORDER BY g.last_seen_at
+            LIMIT $2
+    )
+    RETURNING id;
+"#,
+        )
+        .bind(last_seen_before)
+        .bind(limit)
+        .bind(now)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.into_iter().map(|(user_id,)| user_id).collect())
+    }
+}
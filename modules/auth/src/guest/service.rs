@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+
+use core_base::clock::SystemClock;
+
+use opxs_base::{AppError, GuestConfig};
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    rate_limit::RateLimitRepo,
+    shared::{
+        model::{AuthAuditAuthMethod, AuthAuditEventType, ClientInfo},
+        sha256_hex,
+    },
+};
+
+use super::GuestRepo;
+
+pub struct GuestService {
+    pub guest_repo: Arc<GuestRepo>,
+    pub rate_limit_repo: Arc<RateLimitRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub guest_conf: GuestConfig,
+}
+
+impl GuestService {
+    // The device id is a random secret generated and kept by the client, so it identifies the guest like a credential.
+    pub async fn sign_in(&self, device_id: &str, client: &ClientInfo) -> Result<String, AppError> {
+        // Any new device id creates a user, so sign-ins are throttled per client IP before anything is written.
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            let window = Duration::hours(1);
+            self.rate_limit_repo.delete_expired(&(self.system_clock.now() - window)).await?;
+            self.rate_limit_repo
+                .hit(&format!("guest_login:{}", ip_address), window, self.guest_conf.login_limit_per_hour)
+                .await?;
+        }
+
+        let res = self.sign_in_sub(device_id).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|(user_id, _)| user_id.as_str()),
+            actor_user_id: None,
+            event_type: match res {
+                Ok((_, true)) => AuthAuditEventType::Register,
+                _ => AuthAuditEventType::Login,
+            },
+            auth_method: Some(AuthAuditAuthMethod::Guest),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res.map(|(user_id, _)| user_id)
+    }
+
+    async fn sign_in_sub(&self, device_id: &str) -> Result<(String, bool), AppError> {
+        let device_id_hash = sha256_hex(device_id);
+
+        if let Some(user_id) = self.guest_repo.touch_guest(&device_id_hash).await? {
+            return Ok((user_id, false));
+        }
+
+        let user_id = self.guest_repo.create_guest(&device_id_hash, self.guest_conf.convert_quota).await?;
+
+        Ok((user_id, true))
+    }
+
+    // Uploads without a session aren't bound to a quota, so they are throttled per client IP the same way as guest sign-ins.
+    pub async fn throttle_anonymous_convert(&self, client: &ClientInfo) -> Result<(), AppError> {
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            let window = Duration::hours(1);
+            self.rate_limit_repo.delete_expired(&(self.system_clock.now() - window)).await?;
+            self.rate_limit_repo
+                .hit(
+                    &format!("anonymous_convert:{}", ip_address),
+                    window,
+                    self.guest_conf.anonymous_convert_limit_per_hour,
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn schedule_purge(&self, limit: i64) -> Result<Vec<String>, AppError> {
+        let last_seen_before = self.system_clock.now() - Duration::days(self.guest_conf.retention_days);
+        self.guest_repo.schedule_stale_deletion(&last_seen_before, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use opxs_base::{EmailPolicyConfig, InvitationConfig, JwtConfig, JwtSecretConfig, PasswordPolicyConfig, RegisterPolicyConfig};
+
+    use crate::{
+        audit::AuthAuditRepo,
+        email::{EmailAuthRepo, EmailAuthService, EmailRegistration},
+        invitation::{InvitationRepo, InvitationService},
+        legal::{LegalRepo, LegalService},
+        policy::RegisterPolicy,
+        shared::{
+            self,
+            kdf::{Kdf, KdfAlgorithm},
+            model::UserRole,
+        },
+        user::UserRepo,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let random_bytes_provider = Arc::new(RandomBytesProviderImpl {});
+        let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+        let user_repo = Arc::new(UserRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: Arc::new(AuthAuditRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+        });
+        let guest_service = GuestService {
+            guest_repo: Arc::new(GuestRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            rate_limit_repo: Arc::new(RateLimitRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            audit_logger: audit_logger.clone(),
+            system_clock: system_clock.clone(),
+            guest_conf: GuestConfig {
+                convert_quota: 2,
+                retention_days: 30,
+                login_limit_per_hour: 2,
+                anonymous_convert_limit_per_hour: 2,
+            },
+        };
+        let email_service = EmailAuthService {
+            auth_repo: Arc::new(EmailAuthRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            user_repo: user_repo.clone(),
+            audit_logger: audit_logger.clone(),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: random_bytes_provider.clone(),
+            jwt_conf: JwtConfig {
+                secret: JwtSecretConfig {
+                    current: "a".to_string(),
+                    previous: "b".to_string(),
+                },
+            },
+            kdf: Kdf {
+                algorithm: KdfAlgorithm::Pbkdf2HmacSha256,
+                iterations: 10,
+            },
+            register_policy: Arc::new(RegisterPolicy::new(&RegisterPolicyConfig {
+                email: EmailPolicyConfig {
+                    allowed_domains: vec![],
+                    denied_domains: vec![],
+                    deny_disposable: true,
+                },
+                password: PasswordPolicyConfig {
+                    min_length: 8,
+                    max_length: 128,
+                    min_score: 2,
+                    deny_breached: true,
+                },
+            })),
+            invitation_service: Arc::new(InvitationService {
+                invitation_repo: Arc::new(InvitationRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: tsid_provider.clone(),
+                }),
+                system_clock: system_clock.clone(),
+                random_bytes_provider,
+                invitation_conf: InvitationConfig {
+                    required: false,
+                    expires_in_days: 7,
+                    max_uses: 1,
+                },
+            }),
+            legal_service: Arc::new(LegalService {
+                legal_repo: Arc::new(LegalRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: tsid_provider.clone(),
+                }),
+                system_clock: system_clock.clone(),
+            }),
+        };
+        let client = ClientInfo::default();
+        let device_id = "0123456789abcdef0123456789abcdef";
+
+        // the same device signs in as the same guest
+        let user_id = guest_service.sign_in(device_id, &client).await.unwrap();
+        assert_eq!(guest_service.sign_in(device_id, &client).await.unwrap(), user_id);
+        assert_ne!(guest_service.sign_in("another-device", &client).await.unwrap(), user_id);
+        assert_eq!(user_repo.get_user(&user_id).await.unwrap().role, UserRole::Guest);
+
+        // upgrade in place; the guest stays a guest until the address is confirmed
+        let guest = user_repo.get_user(&user_id).await.unwrap();
+        let EmailRegistration::Created { confirm_token } = email_service
+            .upgrade_guest(&guest, "user_name", "guest@example.com", "kq8zm3xp-Vw", None, &[], &client)
+            .await
+            .unwrap()
+        else {
+            panic!("email should be attached");
+        };
+        assert_eq!(user_repo.get_user(&user_id).await.unwrap().role, UserRole::Guest);
+        assert_eq!(email_service.confirm(&confirm_token, &client).await.unwrap(), user_id);
+        let user = user_repo.get_user(&user_id).await.unwrap();
+        assert_eq!(user.role, UserRole::User);
+        assert_eq!(user.name, "user_name");
+        assert_eq!(email_service.login("guest@example.com", "kq8zm3xp-Vw", &client).await.unwrap(), user_id);
+
+        // an upgraded account is no longer a guest
+        assert!(matches!(
+            email_service
+                .upgrade_guest(&user, "user_name", "other@example.com", "kq8zm3xp-Vw", None, &[], &client)
+                .await,
+            Err(AppError::Forbidden)
+        ));
+        assert_ne!(guest_service.sign_in(device_id, &client).await.unwrap(), user_id);
+
+        // sign-ins from the same IP are throttled
+        let client = ClientInfo {
+            ip_address: Some("192.0.2.1".to_string()),
+            user_agent: None,
+        };
+        guest_service.sign_in(device_id, &client).await.unwrap();
+        guest_service.sign_in(device_id, &client).await.unwrap();
+        assert!(matches!(guest_service.sign_in(device_id, &client).await, Err(AppError::TooManyRequests)));
+        let other_client = ClientInfo {
+            ip_address: Some("192.0.2.2".to_string()),
+            user_agent: None,
+        };
+        guest_service.sign_in(device_id, &other_client).await.unwrap();
+
+        // anonymous uploads are throttled per IP, independently of sign-ins
+        guest_service.throttle_anonymous_convert(&client).await.unwrap();
+        guest_service.throttle_anonymous_convert(&client).await.unwrap();
+        assert!(matches!(
+            guest_service.throttle_anonymous_convert(&client).await,
+            Err(AppError::TooManyRequests)
+        ));
+        guest_service.throttle_anonymous_convert(&other_client).await.unwrap();
+
+        // stale guests
+        let guest_service = GuestService {
+            guest_conf: GuestConfig {
+                convert_quota: 2,
+                retention_days: -1,
+                login_limit_per_hour: 2,
+                anonymous_convert_limit_per_hour: 2,
+            },
+            ..guest_service
+        };
+        let user_ids = guest_service.schedule_purge(100).await.unwrap();
+        assert_eq!(user_ids.len(), 2);
+        assert!(!user_ids.contains(&user_id));
+        assert!(guest_service.schedule_purge(100).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use core_base::clock::SystemClock;
+
+use opxs_base::{AppError, JwtConfig};
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    shared::{
+        jwt,
+        model::{AuthAuditAuthMethod, AuthAuditEventType, AuthToken, ClientInfo, User, UserRole},
+    },
+    token::TokenService,
+    user::UserRepo,
+};
+
+const ACCESS_TOKEN_EXPIRES_IN_MINUTES: i64 = 15;
+
+pub struct ImpersonationService {
+    pub user_repo: Arc<UserRepo>,
+    pub token_service: Arc<TokenService>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub jwt_conf: JwtConfig,
+}
+
+impl ImpersonationService {
+    pub async fn start(&self, admin: &User, user_id: &str, reason: Option<&str>, client: &ClientInfo) -> Result<AuthToken, AppError> {
+        let res = self.start_sub(admin, user_id).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: Some(&admin.id),
+            event_type: AuthAuditEventType::ImpersonationStart,
+            auth_method: Some(AuthAuditAuthMethod::AccessToken),
+            client,
+            detail: reason.map(|reason| json!({ "reason": reason })),
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    // The token carries no refresh token, so impersonation ends on its own once it expires.
+    async fn start_sub(&self, admin: &User, user_id: &str) -> Result<AuthToken, AppError> {
+        if admin.role != UserRole::Admin {
+            return Err(AppError::Forbidden);
+        }
+        if admin.id == user_id {
+            return Err(AppError::InvalidRequest(anyhow::anyhow!("Cannot impersonate yourself")));
+        }
+        let user = self.user_repo.get_user(user_id).await?;
+        if user.role == UserRole::Admin {
+            return Err(AppError::Forbidden);
+        }
+
+        let now = self.system_clock.now();
+        let expires_in = Duration::minutes(ACCESS_TOKEN_EXPIRES_IN_MINUTES);
+        let access_token = jwt::sign_for_impersonation(&self.jwt_conf.secret.current, &user.id, &admin.id, expires_in, now)?;
+
+        Ok(AuthToken {
+            expires_in: expires_in.num_seconds() as i32,
+            access_token,
+            refresh_token: String::new(),
+        })
+    }
+
+    pub async fn stop(&self, user_id: &str, actor_user_id: &str, client: &ClientInfo) -> Result<AuthToken, AppError> {
+        let res = self.stop_sub(actor_user_id).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: Some(actor_user_id),
+            event_type: AuthAuditEventType::ImpersonationStop,
+            auth_method: Some(AuthAuditAuthMethod::AccessToken),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn stop_sub(&self, actor_user_id: &str) -> Result<AuthToken, AppError> {
+        let admin = self.user_repo.get_user(actor_user_id).await?;
+        if admin.role != UserRole::Admin {
+            return Err(AppError::Forbidden);
+        }
+        self.token_service.create_delegated(&admin.id).await
+    }
+
+    pub async fn log_request(&self, user_id: &str, actor_user_id: &str, method: &str, path: &str, client: &ClientInfo) -> Result<(), AppError> {
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: Some(actor_user_id),
+            event_type: AuthAuditEventType::ImpersonatedRequest,
+            auth_method: Some(AuthAuditAuthMethod::AccessToken),
+            client,
+            detail: Some(json!({ "method": method, "path": path })),
+        };
+        self.audit_logger.write(event, &Ok::<(), AppError>(())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use opxs_base::JwtSecretConfig;
+
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{self, model::UserAuthenticationType},
+        token::TokenRepo,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let jwt_conf = JwtConfig {
+            secret: JwtSecretConfig {
+                current: "current".to_string(),
+                previous: "previous".to_string(),
+            },
+        };
+        let audit_repo = Arc::new(AuthAuditRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+        });
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: audit_repo.clone(),
+        });
+        let impersonation_service = ImpersonationService {
+            user_repo: Arc::new(UserRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            token_service: Arc::new(TokenService {
+                system_clock: system_clock.clone(),
+                random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+                jwt_conf: jwt_conf.clone(),
+                token_repo: Arc::new(TokenRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+                }),
+                audit_logger: audit_logger.clone(),
+            }),
+            audit_logger,
+            system_clock: system_clock.clone(),
+            jwt_conf,
+        };
+        let client = ClientInfo::default();
+
+        // create users
+        let now = system_clock.now();
+        let mut users = vec![];
+        for (id, role) in [("admin_user_id", UserRole::Admin), ("test_user_id", UserRole::User)] {
+            sqlx::query(
+                r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+            )
+            .bind(id)
+            .bind(id)
+            .bind(UserAuthenticationType::Email)
+            .bind(&role)
+            .bind(now)
+            .bind(now)
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+            users.push(User {
+                id: id.to_string(),
+                name: id.to_string(),
+                role,
+                created_at: now.naive_utc(),
+                updated_at: now.naive_utc(),
+            });
+        }
+        let (admin, user) = (&users[0], &users[1]);
+
+        // start
+        assert!(matches!(
+            impersonation_service.start(user, &admin.id, None, &client).await,
+            Err(AppError::Forbidden)
+        ));
+        let token = impersonation_service
+            .start(admin, &user.id, Some("support ticket"), &client)
+            .await
+            .unwrap();
+        assert!(token.refresh_token.is_empty());
+        let claims = jwt::verify("current", &token.access_token, system_clock.now()).unwrap();
+        assert_eq!(claims.sub, user.id);
+        assert_eq!(claims.act.unwrap().sub, admin.id);
+
+        // request
+        impersonation_service
+            .log_request(&user.id, &admin.id, "GET", "/api/v1/auth/me", &client)
+            .await
+            .unwrap();
+
+        // stop
+        let token = impersonation_service.stop(&user.id, &admin.id, &client).await.unwrap();
+        let claims = jwt::verify("current", &token.access_token, system_clock.now()).unwrap();
+        assert_eq!(claims.sub, admin.id);
+        assert!(claims.act.is_none());
+
+        let logs = audit_repo.get_logs(Some(&user.id), Some(&admin.id), None, None, 10).await.unwrap();
+        assert_eq!(logs.len(), 3);
+        assert!(logs.iter().any(|n| n.event_type == AuthAuditEventType::ImpersonatedRequest));
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::shared::model::{Invitation, InvitationRedemption};
+
+pub struct InvitationRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl InvitationRepo {
+    pub async fn create_invitation(
+        &self,
+        inviter_user_id: &str,
+        code: &str,
+        email: Option<&str>,
+        max_uses: i32,
+        expires_at: &DateTime<Utc>,
+    ) -> Result<Invitation, AppError> {
+        let id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let invitation: Invitation = sqlx::query_as(
+            r#"
+INSERT INTO invitations (id, code, inviter_user_id, email, max_uses, expires_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    RETURNING *;
+"#,
+        )
+        .bind(id)
+        .bind(code)
+        .bind(inviter_user_id)
+        .bind(email)
+        .bind(max_uses)
+        .bind(expires_at)
+        .bind(now)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(invitation)
+    }
+
+    pub async fn get_invitations(&self, inviter_user_id: &str) -> Result<Vec<Invitation>, AppError> {
+        let res: Vec<Invitation> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM invitations
+    WHERE inviter_user_id = $1
+    ORDER BY created_at DESC;
+"#,
+        )
+        .bind(inviter_user_id)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn consume_quota(&self, user_id: &str) -> Result<(), AppError> {
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET invitation_quota = invitation_quota - 1
+    WHERE id = $1 AND invitation_quota > 0;
+"#,
+        )
+        .bind(user_id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(())
+    }
+
+    pub async fn update_quota(&self, user_id: &str, quota: i32) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET invitation_quota = $2, updated_at = $3
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .bind(quota)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    // Takes one use of the invitation up front so concurrent registrations can't exceed max_uses.
+    pub async fn reserve(&self, code: &str, email: Option<&str>) -> Result<Invitation, AppError> {
+        let now = self.system_clock.now();
+
+        let invitation: Option<Invitation> = sqlx::query_as(
+            r#"
+UPDATE invitations
+    SET use_count = use_count + 1
+    WHERE code = $1
+        AND use_count < max_uses
+        AND expires_at > $2
+        AND (email IS NULL OR LOWER(email) = LOWER($3))
+    RETURNING *;
+"#,
+        )
+        .bind(code)
+        .bind(now)
+        .bind(email)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        invitation.ok_or(AppError::InvalidInvitation)
+    }
+
+    pub async fn release(&self, invitation_id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+UPDATE invitations
+    SET use_count = use_count - 1
+    WHERE id = $1 AND use_count > 0;
+"#,
+        )
+        .bind(invitation_id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    pub async fn create_redemption(&self, invitation: &Invitation, invitee_user_id: &str) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+INSERT INTO invitation_redemptions (invitation_id, inviter_user_id, invitee_user_id, created_at)
+    VALUES ($1, $2, $3, $4);
+"#,
+        )
+        .bind(&invitation.id)
+        .bind(&invitation.inviter_user_id)
+        .bind(invitee_user_id)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    pub async fn get_redemptions(&self, inviter_user_id: &str) -> Result<Vec<InvitationRedemption>, AppError> {
+        let res: Vec<InvitationRedemption> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM invitation_redemptions
+    WHERE inviter_user_id = $1
+    ORDER BY created_at DESC;
+"#,
+        )
+        .bind(inviter_user_id)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+
+use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
+
+use opxs_base::{AppError, InvitationConfig};
+
+use crate::shared::model::{Invitation, InvitationRedemption, User, UserRole};
+
+use super::InvitationRepo;
+
+const MAX_EXPIRES_IN_DAYS: i64 = 90;
+
+pub struct InvitationService {
+    pub invitation_repo: Arc<InvitationRepo>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
+    pub invitation_conf: InvitationConfig,
+}
+
+impl InvitationService {
+    pub async fn create(
+        &self,
+        inviter: &User,
+        email: Option<&str>,
+        max_uses: Option<i32>,
+        expires_in_days: Option<i64>,
+    ) -> Result<Invitation, AppError> {
+        let max_uses = max_uses.unwrap_or(self.invitation_conf.max_uses);
+        let expires_in_days = expires_in_days.unwrap_or(self.invitation_conf.expires_in_days);
+        if max_uses < 1 || !(1..=MAX_EXPIRES_IN_DAYS).contains(&expires_in_days) {
+            return Err(AppError::InvalidRequest(anyhow::anyhow!("Invalid invitation parameter")));
+        }
+
+        if inviter.role != UserRole::Admin {
+            self.invitation_repo.consume_quota(&inviter.id).await?;
+        }
+
+        let code = hex::encode(self.random_bytes_provider.get_bytes(12));
+        let expires_at = self.system_clock.now() + Duration::days(expires_in_days);
+
+        self.invitation_repo
+            .create_invitation(&inviter.id, &code, email, max_uses, &expires_at)
+            .await
+    }
+
+    pub async fn get_invitations(&self, user_id: &str) -> Result<Vec<Invitation>, AppError> {
+        self.invitation_repo.get_invitations(user_id).await
+    }
+
+    pub async fn get_redemptions(&self, user_id: &str) -> Result<Vec<InvitationRedemption>, AppError> {
+        self.invitation_repo.get_redemptions(user_id).await
+    }
+
+    pub async fn update_quota(&self, user_id: &str, quota: i32) -> Result<(), AppError> {
+        if quota < 0 {
+            return Err(AppError::InvalidRequest(anyhow::anyhow!("Invalid quota")));
+        }
+        self.invitation_repo.update_quota(user_id, quota).await
+    }
+
+    // Called before a new user is created. A code is mandatory only in invite-only mode, but is honored whenever given.
+    pub async fn reserve(&self, code: Option<&str>, email: Option<&str>) -> Result<Option<Invitation>, AppError> {
+        match code {
+            Some(code) => Ok(Some(self.invitation_repo.reserve(code, email).await?)),
+            None if self.invitation_conf.required => Err(AppError::InvalidInvitation),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn complete(&self, invitation: Option<&Invitation>, res: &Result<String, AppError>) -> Result<(), AppError> {
+        let Some(invitation) = invitation else {
+            return Ok(());
+        };
+
+        match res {
+            Ok(invitee_user_id) => self.invitation_repo.create_redemption(invitation, invitee_user_id).await,
+            Err(_) => self.invitation_repo.release(&invitation.id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::shared::{self, model::UserAuthenticationType};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let invitation_service = InvitationService {
+            invitation_repo: Arc::new(InvitationRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+            invitation_conf: InvitationConfig {
+                required: true,
+                expires_in_days: 7,
+                max_uses: 1,
+            },
+        };
+
+        let now = system_clock.now();
+        let inviter = User {
+            id: "inviter_user_id".to_string(),
+            name: "inviter_user_name".to_string(),
+            role: UserRole::User,
+            created_at: now.naive_utc(),
+            updated_at: now.naive_utc(),
+        };
+
+        // create user
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(&inviter.id)
+        .bind(&inviter.name)
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+
+        // quota
+        assert!(matches!(
+            invitation_service.create(&inviter, None, None, None).await,
+            Err(AppError::Forbidden)
+        ));
+        invitation_service.update_quota(&inviter.id, 1).await.unwrap();
+        let invitation = invitation_service
+            .create(&inviter, Some("invitee@example.com"), None, None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            invitation_service.create(&inviter, None, None, None).await,
+            Err(AppError::Forbidden)
+        ));
+
+        // required
+        assert!(matches!(invitation_service.reserve(None, None).await, Err(AppError::InvalidInvitation)));
+
+        // email mismatch
+        assert!(matches!(
+            invitation_service.reserve(Some(&invitation.code), Some("other@example.com")).await,
+            Err(AppError::InvalidInvitation)
+        ));
+
+        // failed registration releases the use
+        let reserved = invitation_service
+            .reserve(Some(&invitation.code), Some("Invitee@example.com"))
+            .await
+            .unwrap();
+        invitation_service
+            .complete(reserved.as_ref(), &Err(AppError::DuplicateEmail))
+            .await
+            .unwrap();
+
+        // redeem
+        let reserved = invitation_service
+            .reserve(Some(&invitation.code), Some("invitee@example.com"))
+            .await
+            .unwrap();
+        invitation_service
+            .complete(reserved.as_ref(), &Ok("invitee_user_id".to_string()))
+            .await
+            .unwrap();
+        let redemptions = invitation_service.get_redemptions(&inviter.id).await.unwrap();
+        assert_eq!(redemptions.len(), 1);
+        assert_eq!(redemptions[0].invitee_user_id, "invitee_user_id".to_string());
+
+        // max uses
+        assert!(matches!(
+            invitation_service.reserve(Some(&invitation.code), Some("invitee@example.com")).await,
+            Err(AppError::InvalidInvitation)
+        ));
+    }
+}
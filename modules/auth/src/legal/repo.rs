@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::shared::model::{LegalConsent, LegalDocument, LegalDocumentType};
+
+pub struct LegalRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl LegalRepo {
+    pub async fn create_document(
+        &self,
+        document_type: &LegalDocumentType,
+        version: &str,
+        url: &str,
+        published_at: &DateTime<Utc>,
+    ) -> Result<Option<LegalDocument>, AppError> {
+        let id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let document: Option<LegalDocument> = sqlx::query_as(
+            r#"
+INSERT INTO legal_documents (id, document_type, version, url, published_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (document_type, version) DO NOTHING
+    RETURNING *;
+"#,
+        )
+        .bind(id)
+        .bind(document_type)
+        .bind(version)
+        .bind(url)
+        .bind(published_at)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(document)
+    }
+
+    // The latest published version of each document type; versions published in the future aren't in effect yet.
+    pub async fn get_current_documents(&self) -> Result<Vec<LegalDocument>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Vec<LegalDocument> = sqlx::query_as(
+            r#"
+SELECT DISTINCT ON (document_type) *
+    FROM legal_documents
+    WHERE published_at <= $1
+    ORDER BY document_type, published_at DESC;
+"#,
+        )
+        .bind(now)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_pending_documents(&self, user_id: &str) -> Result<Vec<LegalDocument>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Vec<LegalDocument> = sqlx::query_as(
+            r#"
+SELECT d.*
+    FROM (
+        SELECT DISTINCT ON (document_type) *
+            FROM legal_documents
+            WHERE published_at <= $2
+            ORDER BY document_type, published_at DESC
+    ) AS d
+    WHERE NOT EXISTS (
+        SELECT 1 FROM legal_acceptances AS a WHERE a.user_id = $1 AND a.document_id = d.id
+    );
+"#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn create_acceptances(&self, user_id: &str, consent: &LegalConsent<'_>) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+        Self::insert_acceptances(&mut tx, user_id, consent, &now).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Takes the caller's transaction so an account is never created without the consent it signed up with.
+    pub async fn insert_acceptances(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: &str,
+        consent: &LegalConsent<'_>,
+        now: &DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+INSERT INTO legal_acceptances (user_id, document_id, ip_address, user_agent, accepted_at)
+    SELECT $1, id, $3, $4, $5
+        FROM legal_documents
+        WHERE id = ANY($2)
+    ON CONFLICT (user_id, document_id) DO NOTHING;
+"#,
+        )
+        .bind(user_id)
+        .bind(consent.document_ids)
+        .bind(&consent.client.ip_address)
+        .bind(&consent.client.user_agent)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use core_base::clock::SystemClock;
+
+use opxs_base::AppError;
+
+use crate::shared::model::{ClientInfo, LegalConsent, LegalDocument, LegalDocumentType};
+
+use super::LegalRepo;
+
+pub struct LegalService {
+    pub legal_repo: Arc<LegalRepo>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl LegalService {
+    pub async fn publish(
+        &self,
+        document_type: &LegalDocumentType,
+        version: &str,
+        url: &str,
+        published_at: Option<&DateTime<Utc>>,
+    ) -> Result<LegalDocument, AppError> {
+        let published_at = published_at.copied().unwrap_or_else(|| self.system_clock.now());
+        self.legal_repo
+            .create_document(document_type, version, url, &published_at)
+            .await?
+            .ok_or_else(|| AppError::Conflict(anyhow::anyhow!("Document version is already published")))
+    }
+
+    pub async fn get_current_documents(&self) -> Result<Vec<LegalDocument>, AppError> {
+        self.legal_repo.get_current_documents().await
+    }
+
+    pub async fn get_pending_documents(&self, user_id: &str) -> Result<Vec<LegalDocument>, AppError> {
+        self.legal_repo.get_pending_documents(user_id).await
+    }
+
+    pub async fn ensure_accepted(&self, user_id: &str) -> Result<(), AppError> {
+        if !self.legal_repo.get_pending_documents(user_id).await?.is_empty() {
+            return Err(AppError::LegalAcceptanceRequired);
+        }
+        Ok(())
+    }
+
+    // Called before a new user is created, so a registration that skips a current document is rejected up front.
+    // The acceptances themselves are written by the repo that creates the user.
+    pub async fn check(&self, document_ids: &[String]) -> Result<(), AppError> {
+        let documents = self.legal_repo.get_current_documents().await?;
+        if documents.iter().any(|d| !document_ids.contains(&d.id)) {
+            return Err(AppError::LegalAcceptanceRequired);
+        }
+        Self::ensure_in_effect(&documents, document_ids)
+    }
+
+    pub async fn accept(&self, user_id: &str, document_ids: &[String], client: &ClientInfo) -> Result<(), AppError> {
+        let documents = self.legal_repo.get_current_documents().await?;
+        Self::ensure_in_effect(&documents, document_ids)?;
+        self.legal_repo.create_acceptances(user_id, &LegalConsent { document_ids, client }).await
+    }
+
+    // Only versions in effect can be accepted; an outdated version would not prove consent to the current terms.
+    fn ensure_in_effect(documents: &[LegalDocument], document_ids: &[String]) -> Result<(), AppError> {
+        if document_ids.iter().any(|id| !documents.iter().any(|d| &d.id == id)) {
+            return Err(AppError::InvalidRequest(anyhow::anyhow!("Document is not in effect")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::{email::EmailAuthRepo, shared};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+        let auth_repo = EmailAuthRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        };
+        let legal_service = LegalService {
+            legal_repo: Arc::new(LegalRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider,
+            }),
+            system_clock: system_clock.clone(),
+        };
+        let client = ClientInfo {
+            ip_address: Some("192.0.2.1".to_string()),
+            user_agent: None,
+        };
+
+        let user_id = auth_repo
+            .create_user(
+                "user_name",
+                "user@example.com",
+                "user@example.com",
+                "",
+                "",
+                &LegalConsent {
+                    document_ids: &[],
+                    client: &client,
+                },
+            )
+            .await
+            .unwrap();
+
+        // nothing to accept yet
+        assert!(legal_service.check(&[]).await.is_ok());
+        assert!(legal_service.ensure_accepted(&user_id).await.is_ok());
+
+        let tos = legal_service
+            .publish(&LegalDocumentType::TermsOfService, "1.0", "https://example.com/tos/1.0", None)
+            .await
+            .unwrap();
+        let privacy = legal_service
+            .publish(&LegalDocumentType::PrivacyPolicy, "1.0", "https://example.com/privacy/1.0", None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            legal_service
+                .publish(&LegalDocumentType::TermsOfService, "1.0", "https://example.com/tos/1.0", None)
+                .await,
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(
+            legal_service.check(&[tos.id.clone()]).await,
+            Err(AppError::LegalAcceptanceRequired)
+        ));
+        assert!(legal_service.check(&[tos.id.clone(), privacy.id.clone()]).await.is_ok());
+
+        // acceptance
+        assert!(matches!(
+            legal_service.ensure_accepted(&user_id).await,
+            Err(AppError::LegalAcceptanceRequired)
+        ));
+        legal_service
+            .accept(&user_id, &[tos.id.clone(), privacy.id.clone()], &client)
+            .await
+            .unwrap();
+        assert!(legal_service.ensure_accepted(&user_id).await.is_ok());
+
+        // a scheduled version takes effect only when published
+        let scheduled_at = system_clock.now() + Duration::days(1);
+        let scheduled = legal_service
+            .publish(
+                &LegalDocumentType::TermsOfService,
+                "3.0",
+                "https://example.com/tos/3.0",
+                Some(&scheduled_at),
+            )
+            .await
+            .unwrap();
+        assert!(legal_service.ensure_accepted(&user_id).await.is_ok());
+        assert!(legal_service.accept(&user_id, &[scheduled.id], &client).await.is_err());
+
+        // a new version requires re-acceptance
+        let new_tos = legal_service
+            .publish(&LegalDocumentType::TermsOfService, "2.0", "https://example.com/tos/2.0", None)
+            .await
+            .unwrap();
+        let pending = legal_service.get_pending_documents(&user_id).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, new_tos.id);
+        assert!(matches!(
+            legal_service.ensure_accepted(&user_id).await,
+            Err(AppError::LegalAcceptanceRequired)
+        ));
+        assert!(legal_service.accept(&user_id, &[tos.id], &client).await.is_err());
+        legal_service.accept(&user_id, &[new_tos.id], &client).await.unwrap();
+        assert!(legal_service.ensure_accepted(&user_id).await.is_ok());
+    }
+}
@@ -1,5 +1,16 @@
+pub mod audit;
+pub mod device;
 pub mod email;
+pub mod guest;
+pub mod impersonation;
+pub mod invitation;
+pub mod legal;
+pub mod organization;
+pub mod policy;
 pub mod provider;
+pub mod rate_limit;
+pub mod scim;
 pub mod shared;
+pub mod sign_in;
 pub mod token;
 pub mod user;
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::shared::model::{Organization, OrganizationInvitation, OrganizationMember, OrganizationMembership, OrganizationRole};
+
+pub struct OrganizationRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl OrganizationRepo {
+    pub async fn create_organization(&self, name: &str, created_by: &str) -> Result<Organization, AppError> {
+        let id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        let organization: Organization = sqlx::query_as(
+            r#"
+INSERT INTO organizations (id, name, created_by, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING *;
+"#,
+        )
+        .bind(&id)
+        .bind(name)
+        .bind(created_by)
+        .bind(now)
+        .bind(now)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO organization_members (organization_id, user_id, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5);
+"#,
+        )
+        .bind(&id)
+        .bind(created_by)
+        .bind(OrganizationRole::Owner)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        tx.commit().await?;
+
+        Ok(organization)
+    }
+
+    pub async fn get_memberships(&self, user_id: &str) -> Result<Vec<OrganizationMembership>, AppError> {
+        let res: Vec<OrganizationMembership> = sqlx::query_as(
+            r#"
+SELECT m.organization_id, o.name AS organization_name, m.role
+    FROM organization_members m
+    JOIN organizations o ON o.id = m.organization_id
+    WHERE m.user_id = $1
+    ORDER BY o.created_at;
+"#,
+        )
+        .bind(user_id)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_role(&self, organization_id: &str, user_id: &str) -> Result<Option<OrganizationRole>, AppError> {
+        let res: Option<(OrganizationRole,)> = sqlx::query_as(
+            r#"
+SELECT role
+    FROM organization_members
+    WHERE organization_id = $1 AND user_id = $2;
+"#,
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.map(|(role,)| role))
+    }
+
+    pub async fn get_members(&self, organization_id: &str) -> Result<Vec<OrganizationMember>, AppError> {
+        let res: Vec<OrganizationMember> = sqlx::query_as(
+            r#"
+SELECT m.organization_id, m.user_id, u.name, m.role, m.created_at, m.updated_at
+    FROM organization_members m
+    JOIN users u ON u.id = m.user_id
+    WHERE m.organization_id = $1
+    ORDER BY m.created_at;
+"#,
+        )
+        .bind(organization_id)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn count_owners(&self, organization_id: &str) -> Result<i64, AppError> {
+        let (count,): (i64,) = sqlx::query_as(
+            r#"
+SELECT COUNT(*)
+    FROM organization_members
+    WHERE organization_id = $1 AND role = 'Owner';
+"#,
+        )
+        .bind(organization_id)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(count)
+    }
+
+    pub async fn update_role(&self, organization_id: &str, user_id: &str, role: OrganizationRole) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE organization_members
+    SET role = $3, updated_at = $4
+    WHERE organization_id = $1 AND user_id = $2;
+"#,
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .bind(role)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_member(&self, organization_id: &str, user_id: &str) -> Result<(), AppError> {
+        let res = sqlx::query(
+            r#"
+DELETE FROM organization_members
+    WHERE organization_id = $1 AND user_id = $2;
+"#,
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_invitation(
+        &self,
+        organization_id: &str,
+        code: &str,
+        email: &str,
+        role: OrganizationRole,
+        invited_by: &str,
+        expires_at: &DateTime<Utc>,
+    ) -> Result<OrganizationInvitation, AppError> {
+        let id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let invitation: OrganizationInvitation = sqlx::query_as(
+            r#"
+INSERT INTO organization_invitations (id, organization_id, code, email, role, invited_by, expires_at, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    RETURNING *;
+"#,
+        )
+        .bind(id)
+        .bind(organization_id)
+        .bind(code)
+        .bind(email)
+        .bind(role)
+        .bind(invited_by)
+        .bind(expires_at)
+        .bind(now)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(invitation)
+    }
+
+    pub async fn get_invitations(&self, organization_id: &str) -> Result<Vec<OrganizationInvitation>, AppError> {
+        let res: Vec<OrganizationInvitation> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM organization_invitations
+    WHERE organization_id = $1
+    ORDER BY created_at DESC;
+"#,
+        )
+        .bind(organization_id)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_invitation_by_code(&self, code: &str) -> Result<Option<OrganizationInvitation>, AppError> {
+        let res: Option<OrganizationInvitation> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM organization_invitations
+    WHERE code = $1;
+"#,
+        )
+        .bind(code)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_verified_normalized_email(&self, user_id: &str) -> Result<Option<String>, AppError> {
+        let res: Option<(String,)> = sqlx::query_as(
+            r#"
+SELECT normalized_email
+    FROM user_auth_emails
+    WHERE user_id = $1 AND email_verified = true;
+"#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.map(|(normalized_email,)| normalized_email))
+    }
+
+    pub async fn accept_invitation(&self, code: &str, user_id: &str) -> Result<OrganizationInvitation, AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        let invitation: Option<OrganizationInvitation> = sqlx::query_as(
+            r#"
+UPDATE organization_invitations
+    SET accepted_at = $2
+    WHERE code = $1 AND accepted_at IS NULL AND expires_at > $2
+    RETURNING *;
+"#,
+        )
+        .bind(code)
+        .bind(now)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+        let invitation = invitation.ok_or(AppError::InvalidInvitation)?;
+
+        // An existing member keeps their current role.
+        sqlx::query(
+            r#"
+INSERT INTO organization_members (organization_id, user_id, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (organization_id, user_id) DO NOTHING;
+"#,
+        )
+        .bind(&invitation.organization_id)
+        .bind(user_id)
+        .bind(invitation.role)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        tx.commit().await?;
+
+        Ok(invitation)
+    }
+}
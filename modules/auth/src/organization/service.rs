@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
+
+use opxs_base::{AppError, JwtConfig};
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    policy::RegisterPolicy,
+    shared::{
+        jwt,
+        model::{
+            AuthAuditAuthMethod, AuthAuditEventType, AuthToken, ClientInfo, Organization, OrganizationInvitation, OrganizationMember,
+            OrganizationMembership, OrganizationRole, User, UserRole,
+        },
+    },
+};
+
+use super::OrganizationRepo;
+
+const INVITATION_EXPIRES_IN_DAYS: i64 = 7;
+const ACCESS_TOKEN_EXPIRES_IN_HOURS: i64 = 1;
+
+pub struct OrganizationService {
+    pub organization_repo: Arc<OrganizationRepo>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
+    pub jwt_conf: JwtConfig,
+    pub register_policy: Arc<RegisterPolicy>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+}
+
+impl OrganizationService {
+    pub async fn create(&self, user: &User, name: &str) -> Result<Organization, AppError> {
+        if user.role == UserRole::Guest {
+            return Err(AppError::Forbidden);
+        }
+        self.organization_repo.create_organization(name, &user.id).await
+    }
+
+    pub async fn get_memberships(&self, user_id: &str) -> Result<Vec<OrganizationMembership>, AppError> {
+        self.organization_repo.get_memberships(user_id).await
+    }
+
+    // Non-members get Forbidden rather than NotFound so organization ids can't be probed.
+    pub async fn get_role(&self, organization_id: &str, user_id: &str) -> Result<OrganizationRole, AppError> {
+        self.organization_repo
+            .get_role(organization_id, user_id)
+            .await?
+            .ok_or(AppError::Forbidden)
+    }
+
+    pub async fn get_members(&self, organization_id: &str, user_id: &str) -> Result<Vec<OrganizationMember>, AppError> {
+        self.get_role(organization_id, user_id).await?;
+        self.organization_repo.get_members(organization_id).await
+    }
+
+    pub async fn invite(
+        &self,
+        organization_id: &str,
+        inviter: &User,
+        email: &str,
+        role: OrganizationRole,
+    ) -> Result<OrganizationInvitation, AppError> {
+        let inviter_role = self.get_role(organization_id, &inviter.id).await?;
+        if !inviter_role.can_manage_members() || (role == OrganizationRole::Owner && inviter_role != OrganizationRole::Owner) {
+            return Err(AppError::Forbidden);
+        }
+
+        let code = hex::encode(self.random_bytes_provider.get_bytes(16));
+        let expires_at = self.system_clock.now() + Duration::days(INVITATION_EXPIRES_IN_DAYS);
+
+        self.organization_repo
+            .create_invitation(organization_id, &code, email, role, &inviter.id, &expires_at)
+            .await
+    }
+
+    pub async fn get_invitations(&self, organization_id: &str, user_id: &str) -> Result<Vec<OrganizationInvitation>, AppError> {
+        if !self.get_role(organization_id, user_id).await?.can_manage_members() {
+            return Err(AppError::Forbidden);
+        }
+        self.organization_repo.get_invitations(organization_id).await
+    }
+
+    // Only the invitee can accept: the invited address must match the user's verified email after normalization.
+    pub async fn accept(&self, code: &str, user_id: &str) -> Result<OrganizationInvitation, AppError> {
+        let invitation = self
+            .organization_repo
+            .get_invitation_by_code(code)
+            .await?
+            .ok_or(AppError::InvalidInvitation)?;
+        let invitee_email = self.register_policy.email.normalize(&invitation.email);
+        let user_email = self.organization_repo.get_verified_normalized_email(user_id).await?;
+        if invitee_email.is_none() || invitee_email != user_email {
+            return Err(AppError::InvalidInvitation);
+        }
+
+        self.organization_repo.accept_invitation(code, user_id).await
+    }
+
+    pub async fn update_role(
+        &self,
+        organization_id: &str,
+        actor_id: &str,
+        user_id: &str,
+        role: OrganizationRole,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        let res = self.update_role_sub(organization_id, actor_id, user_id, role).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: Some(actor_id),
+            event_type: AuthAuditEventType::RoleChange,
+            auth_method: Some(AuthAuditAuthMethod::AccessToken),
+            client,
+            detail: Some(json!({ "organization_id": organization_id, "role": role })),
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn update_role_sub(&self, organization_id: &str, actor_id: &str, user_id: &str, role: OrganizationRole) -> Result<(), AppError> {
+        let actor_role = self.get_role(organization_id, actor_id).await?;
+        let current_role = self
+            .organization_repo
+            .get_role(organization_id, user_id)
+            .await?
+            .ok_or(AppError::UserNotFound)?;
+
+        let touches_owner = role == OrganizationRole::Owner || current_role == OrganizationRole::Owner;
+        if !actor_role.can_manage_members() || (touches_owner && actor_role != OrganizationRole::Owner) {
+            return Err(AppError::Forbidden);
+        }
+        if current_role == OrganizationRole::Owner && role != OrganizationRole::Owner {
+            self.ensure_other_owner(organization_id).await?;
+        }
+
+        self.organization_repo.update_role(organization_id, user_id, role).await
+    }
+
+    // Members may always leave; removing someone else needs admin rights, and only owners can remove owners.
+    pub async fn remove_member(&self, organization_id: &str, actor_id: &str, user_id: &str) -> Result<(), AppError> {
+        let actor_role = self.get_role(organization_id, actor_id).await?;
+        let target_role = if actor_id == user_id {
+            actor_role
+        } else {
+            let target_role = self
+                .organization_repo
+                .get_role(organization_id, user_id)
+                .await?
+                .ok_or(AppError::UserNotFound)?;
+            if !actor_role.can_manage_members() || (target_role == OrganizationRole::Owner && actor_role != OrganizationRole::Owner) {
+                return Err(AppError::Forbidden);
+            }
+            target_role
+        };
+
+        if target_role == OrganizationRole::Owner {
+            self.ensure_other_owner(organization_id).await?;
+        }
+
+        self.organization_repo.delete_member(organization_id, user_id).await
+    }
+
+    // Issues a short-lived access token carrying the organization as its active context.
+    pub async fn issue_token(&self, organization_id: &str, user_id: &str) -> Result<AuthToken, AppError> {
+        self.get_role(organization_id, user_id).await?;
+
+        let now = self.system_clock.now();
+        let expires_in = Duration::hours(ACCESS_TOKEN_EXPIRES_IN_HOURS);
+        let access_token = jwt::sign_for_organization(&self.jwt_conf.secret.current, user_id, organization_id, expires_in, now)?;
+
+        Ok(AuthToken {
+            expires_in: expires_in.num_seconds() as i32,
+            access_token,
+            refresh_token: String::new(),
+        })
+    }
+
+    async fn ensure_other_owner(&self, organization_id: &str) -> Result<(), AppError> {
+        if self.organization_repo.count_owners(organization_id).await? < 2 {
+            return Err(AppError::InvalidRequest(anyhow::anyhow!("An organization needs at least one owner")));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+    use opxs_base::{EmailPolicyConfig, JwtSecretConfig, PasswordPolicyConfig, RegisterPolicyConfig};
+
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{
+            self,
+            model::{AuthAuditOutcome, UserAuthenticationType, UserRole},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+        let audit_repo = Arc::new(AuthAuditRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let organization_service = OrganizationService {
+            organization_repo: Arc::new(OrganizationRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider,
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+            jwt_conf: JwtConfig {
+                secret: JwtSecretConfig {
+                    current: "current".to_string(),
+                    previous: "previous".to_string(),
+                },
+            },
+            register_policy: Arc::new(RegisterPolicy::new(&RegisterPolicyConfig {
+                email: EmailPolicyConfig {
+                    allowed_domains: vec![],
+                    denied_domains: vec![],
+                    deny_disposable: true,
+                },
+                password: PasswordPolicyConfig {
+                    min_length: 8,
+                    max_length: 128,
+                    min_score: 2,
+                    deny_breached: true,
+                },
+            })),
+            audit_logger: Arc::new(AuthAuditLogger {
+                audit_repo: audit_repo.clone(),
+            }),
+        };
+        let client = ClientInfo::default();
+
+        // create users
+        let now = system_clock.now();
+        let mut users = vec![];
+        for id in ["owner_user_id", "member_user_id", "outsider_user_id"] {
+            let user = User {
+                id: id.to_string(),
+                name: id.to_string(),
+                role: UserRole::User,
+                created_at: now.naive_utc(),
+                updated_at: now.naive_utc(),
+            };
+            sqlx::query(
+                r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+            )
+            .bind(&user.id)
+            .bind(&user.name)
+            .bind(UserAuthenticationType::Email)
+            .bind(UserRole::User)
+            .bind(now)
+            .bind(now)
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+            sqlx::query(
+                r#"
+INSERT INTO user_auth_emails (email, normalized_email, user_id, password_hash, salt, email_verified, created_at, updated_at)
+    VALUES ($1, $2, $3, '', '', true, $4, $4)
+"#,
+            )
+            .bind(format!("{}@Example.com", id))
+            .bind(format!("{}@example.com", id))
+            .bind(id)
+            .bind(now)
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+            users.push(user);
+        }
+        let (owner, member, outsider) = (&users[0], &users[1], &users[2]);
+
+        // create
+        let organization = organization_service.create(owner, "organization_name").await.unwrap();
+        assert_eq!(
+            organization_service.get_role(&organization.id, &owner.id).await.unwrap(),
+            OrganizationRole::Owner
+        );
+
+        // invite and accept
+        assert!(matches!(
+            organization_service
+                .invite(&organization.id, outsider, "member@example.com", OrganizationRole::Member)
+                .await,
+            Err(AppError::Forbidden)
+        ));
+        let invitation = organization_service
+            .invite(&organization.id, owner, "Member_User_Id+team@example.com", OrganizationRole::Member)
+            .await
+            .unwrap();
+        // someone else holding the link can't use it
+        assert!(matches!(
+            organization_service.accept(&invitation.code, &outsider.id).await,
+            Err(AppError::InvalidInvitation)
+        ));
+        organization_service.accept(&invitation.code, &member.id).await.unwrap();
+        assert!(matches!(
+            organization_service.accept(&invitation.code, &outsider.id).await,
+            Err(AppError::InvalidInvitation)
+        ));
+        assert_eq!(organization_service.get_members(&organization.id, &member.id).await.unwrap().len(), 2);
+        assert!(matches!(
+            organization_service.get_members(&organization.id, &outsider.id).await,
+            Err(AppError::Forbidden)
+        ));
+
+        // roles
+        assert!(matches!(
+            organization_service.remove_member(&organization.id, &member.id, &owner.id).await,
+            Err(AppError::Forbidden)
+        ));
+        assert!(matches!(
+            organization_service
+                .update_role(&organization.id, &owner.id, &owner.id, OrganizationRole::Member, &client)
+                .await,
+            Err(AppError::InvalidRequest(_))
+        ));
+        organization_service
+            .update_role(&organization.id, &owner.id, &member.id, OrganizationRole::Admin, &client)
+            .await
+            .unwrap();
+        let logs = audit_repo
+            .get_logs(Some(&member.id), Some(&owner.id), Some(&AuthAuditEventType::RoleChange), None, 10)
+            .await
+            .unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].outcome, AuthAuditOutcome::Success);
+
+        // token
+        let token = organization_service.issue_token(&organization.id, &member.id).await.unwrap();
+        let claims = jwt::verify("current", &token.access_token, system_clock.now()).unwrap();
+        assert_eq!(claims.org, Some(organization.id.clone()));
+        assert!(matches!(
+            organization_service.issue_token(&organization.id, &outsider.id).await,
+            Err(AppError::Forbidden)
+        ));
+
+        // leave
+        assert!(matches!(
+            organization_service.remove_member(&organization.id, &owner.id, &owner.id).await,
+            Err(AppError::InvalidRequest(_))
+        ));
+        organization_service
+            .remove_member(&organization.id, &member.id, &member.id)
+            .await
+            .unwrap();
+        assert_eq!(organization_service.get_memberships(&member.id).await.unwrap().len(), 0);
+    }
+}
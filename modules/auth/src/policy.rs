@@ -0,0 +1,120 @@
+mod email;
+mod password;
+
+pub use email::*;
+pub use password::*;
+
+use std::collections::HashSet;
+
+use opxs_base::{AppError, FieldViolation, RegisterPolicyConfig};
+
+pub struct RegisterPolicy {
+    pub email: EmailPolicy,
+    pub password: PasswordPolicy,
+}
+
+impl RegisterPolicy {
+    pub fn new(conf: &RegisterPolicyConfig) -> Self {
+        Self {
+            email: EmailPolicy::new(&conf.email),
+            password: PasswordPolicy::new(&conf.password),
+        }
+    }
+
+    // Returns the normalized email, which is what uniqueness is checked against.
+    pub fn check(&self, name: &str, email: &str, password: &str) -> Result<String, AppError> {
+        let mut violations: Vec<FieldViolation> = vec![];
+
+        let email_reasons = self.email.check(email);
+        violations.extend(email_reasons.iter().map(|reason| FieldViolation {
+            field: "email".to_string(),
+            reason: reason.to_string(),
+        }));
+
+        let local_part = email.split('@').next().unwrap_or_default();
+        let password_reasons = self.password.check(password, &[name, local_part]);
+        violations.extend(password_reasons.iter().map(|reason| FieldViolation {
+            field: "password".to_string(),
+            reason: reason.to_string(),
+        }));
+
+        if !violations.is_empty() {
+            return Err(AppError::PolicyViolation(violations));
+        }
+
+        self.email
+            .normalize(email)
+            .ok_or_else(|| AppError::InvalidRequest(anyhow::anyhow!("Invalid email")))
+    }
+}
+
+fn parse_list(text: &str) -> HashSet<String> {
+    text.lines()
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty() && !n.starts_with('#'))
+        .map(|n| n.to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use opxs_base::{EmailPolicyConfig, PasswordPolicyConfig};
+
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        let policy = RegisterPolicy::new(&RegisterPolicyConfig {
+            email: EmailPolicyConfig {
+                allowed_domains: vec![],
+                denied_domains: vec!["example.org".to_string()],
+                deny_disposable: true,
+            },
+            password: PasswordPolicyConfig {
+                min_length: 8,
+                max_length: 128,
+                min_score: 2,
+                deny_breached: true,
+            },
+        });
+
+        // normalize
+        assert_eq!(
+            policy.check("user_name", "Foo.Bar+news@GoogleMail.com", "kq8zm3xp-Vw").unwrap(),
+            "foobar@gmail.com".to_string()
+        );
+        assert_eq!(
+            policy.check("user_name", "Foo.Bar+news@Example.com", "kq8zm3xp-Vw").unwrap(),
+            "foo.bar@example.com".to_string()
+        );
+
+        // domain rules
+        assert_eq!(policy.email.check("foo@mail.example.org"), vec![EMAIL_DOMAIN_DENIED]);
+        assert_eq!(policy.email.check("foo@sub.mailinator.com"), vec![EMAIL_DISPOSABLE_DOMAIN]);
+        assert_eq!(policy.email.check("foo"), vec![EMAIL_INVALID_FORMAT]);
+
+        // password
+        assert!(policy.password.check("Password", &[]).contains(&PASSWORD_BREACHED));
+        assert!(policy.password.check("abcdefgh", &[]).contains(&PASSWORD_TOO_WEAK));
+        assert!(policy.password.check("a1!", &[]).contains(&PASSWORD_TOO_SHORT));
+        assert!(policy
+            .password
+            .check("xxuser_namexx", &["user_name"])
+            .contains(&PASSWORD_CONTAINS_PERSONAL_INFO));
+
+        // field-level reasons
+        match policy.check("user_name", "foo@yopmail.com", "qwerty") {
+            Err(AppError::PolicyViolation(violations)) => {
+                assert!(violations.contains(&FieldViolation {
+                    field: "email".to_string(),
+                    reason: EMAIL_DISPOSABLE_DOMAIN.to_string(),
+                }));
+                assert!(violations.contains(&FieldViolation {
+                    field: "password".to_string(),
+                    reason: PASSWORD_BREACHED.to_string(),
+                }));
+            }
+            _ => panic!("policy violation expected"),
+        }
+    }
+}
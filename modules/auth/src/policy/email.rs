@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use opxs_base::EmailPolicyConfig;
+
+use super::parse_list;
+
+const DISPOSABLE_DOMAINS: &str = include_str!("disposable_domains.txt");
+const GMAIL_DOMAINS: [&str; 2] = ["gmail.com", "googlemail.com"];
+
+pub const EMAIL_INVALID_FORMAT: &str = "invalid_format";
+pub const EMAIL_DOMAIN_NOT_ALLOWED: &str = "domain_not_allowed";
+pub const EMAIL_DOMAIN_DENIED: &str = "domain_denied";
+pub const EMAIL_DISPOSABLE_DOMAIN: &str = "disposable_domain";
+
+pub struct EmailPolicy {
+    allowed_domains: HashSet<String>,
+    denied_domains: HashSet<String>,
+    disposable_domains: HashSet<String>,
+}
+
+impl EmailPolicy {
+    pub fn new(conf: &EmailPolicyConfig) -> Self {
+        let disposable_domains = if conf.deny_disposable {
+            parse_list(DISPOSABLE_DOMAINS)
+        } else {
+            HashSet::new()
+        };
+
+        Self {
+            allowed_domains: conf.allowed_domains.iter().map(|n| n.to_lowercase()).collect(),
+            denied_domains: conf.denied_domains.iter().map(|n| n.to_lowercase()).collect(),
+            disposable_domains,
+        }
+    }
+
+    // The rules are fixed rather than configured: the unique index on normalized_email and the backfill in migration 03 depend on them.
+    pub fn normalize(&self, email: &str) -> Option<String> {
+        let (local_part, domain) = split(email)?;
+        let mut local_part = local_part.to_lowercase();
+        let mut domain = domain.to_lowercase();
+
+        if let Some(pos) = local_part.find('+') {
+            local_part.truncate(pos);
+        }
+
+        if GMAIL_DOMAINS.contains(&domain.as_str()) {
+            local_part = local_part.replace('.', "");
+            domain = GMAIL_DOMAINS[0].to_string();
+        }
+
+        Some(format!("{}@{}", local_part, domain))
+    }
+
+    pub fn check(&self, email: &str) -> Vec<&'static str> {
+        let domain = match split(email) {
+            Some((_, domain)) => domain.to_lowercase(),
+            None => return vec![EMAIL_INVALID_FORMAT],
+        };
+
+        let mut reasons = vec![];
+        if !self.allowed_domains.is_empty() && !matches_domain(&self.allowed_domains, &domain) {
+            reasons.push(EMAIL_DOMAIN_NOT_ALLOWED);
+        }
+        if matches_domain(&self.denied_domains, &domain) {
+            reasons.push(EMAIL_DOMAIN_DENIED);
+        }
+        if matches_domain(&self.disposable_domains, &domain) {
+            reasons.push(EMAIL_DISPOSABLE_DOMAIN);
+        }
+        reasons
+    }
+}
+
+fn split(email: &str) -> Option<(&str, &str)> {
+    let (local_part, domain) = email.trim().rsplit_once('@')?;
+    if local_part.is_empty() || domain.is_empty() || !domain.contains('.') {
+        return None;
+    }
+    Some((local_part, domain))
+}
+
+// A rule for "example.com" also covers "mail.example.com".
+fn matches_domain(domains: &HashSet<String>, domain: &str) -> bool {
+    let mut current = domain;
+    loop {
+        if domains.contains(current) {
+            return true;
+        }
+        match current.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => current = parent,
+            _ => return false,
+        }
+    }
+}
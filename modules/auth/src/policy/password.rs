@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use opxs_base::PasswordPolicyConfig;
+
+use super::parse_list;
+
+const BREACHED_PASSWORDS: &str = include_str!("breached_passwords.txt");
+
+pub const PASSWORD_TOO_SHORT: &str = "too_short";
+pub const PASSWORD_TOO_LONG: &str = "too_long";
+pub const PASSWORD_TOO_WEAK: &str = "too_weak";
+pub const PASSWORD_BREACHED: &str = "breached";
+pub const PASSWORD_CONTAINS_PERSONAL_INFO: &str = "contains_personal_info";
+
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    min_score: u8,
+    breached_passwords: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn new(conf: &PasswordPolicyConfig) -> Self {
+        let breached_passwords = if conf.deny_breached {
+            parse_list(BREACHED_PASSWORDS)
+        } else {
+            HashSet::new()
+        };
+
+        Self {
+            min_length: conf.min_length,
+            max_length: conf.max_length,
+            min_score: conf.min_score,
+            breached_passwords,
+        }
+    }
+
+    pub fn check(&self, password: &str, personal_info: &[&str]) -> Vec<&'static str> {
+        let mut reasons = vec![];
+
+        let length = password.chars().count();
+        if length < self.min_length {
+            reasons.push(PASSWORD_TOO_SHORT);
+        }
+        if length > self.max_length {
+            reasons.push(PASSWORD_TOO_LONG);
+        }
+        if self.breached_passwords.contains(&password.to_lowercase()) {
+            reasons.push(PASSWORD_BREACHED);
+        }
+
+        let lower = password.to_lowercase();
+        if personal_info
+            .iter()
+            .map(|n| n.to_lowercase())
+            .any(|n| n.chars().count() >= 3 && lower.contains(&n))
+        {
+            reasons.push(PASSWORD_CONTAINS_PERSONAL_INFO);
+        }
+
+        if score(password) < self.min_score {
+            reasons.push(PASSWORD_TOO_WEAK);
+        }
+
+        reasons
+    }
+}
+
+// Rough 0-4 strength score from the estimated entropy. Repeated and sequential characters ("aaaa", "1234") count half.
+pub fn score(password: &str) -> u8 {
+    let chars: Vec<char> = password.chars().collect();
+    if chars.is_empty() {
+        return 0;
+    }
+
+    let mut charset = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        charset += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        charset += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        charset += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        charset += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        charset += 100;
+    }
+
+    let mut effective_length = 1.0;
+    for pair in chars.windows(2) {
+        let diff = (pair[1] as i64 - pair[0] as i64).abs();
+        effective_length += if diff <= 1 { 0.5 } else { 1.0 };
+    }
+
+    let bits = effective_length * (charset as f64).log2();
+    match bits {
+        n if n < 28.0 => 0,
+        n if n < 36.0 => 1,
+        n if n < 60.0 => 2,
+        n if n < 80.0 => 3,
+        _ => 4,
+    }
+}
@@ -1,5 +1,7 @@
 mod google;
 mod repo;
+mod saml;
 
 pub use google::*;
 pub use repo::*;
+pub use saml::*;
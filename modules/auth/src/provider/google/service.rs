@@ -1,8 +1,17 @@
 use std::sync::Arc;
 
+use serde_json::json;
+
 use opxs_base::{AppError, AuthConfig};
 
-use crate::provider::ProviderAuthRepo;
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    invitation::InvitationService,
+    legal::LegalService,
+    provider::ProviderAuthRepo,
+    shared::model::{AuthAuditAuthMethod, AuthAuditEventType, ClientInfo, LegalConsent},
+    user::UserRepo,
+};
 
 use super::GoogleOAuth2Provider;
 
@@ -10,11 +19,47 @@ use super::GoogleOAuth2Provider;
 pub struct GoogleAuthService {
     pub oauth2_provider: Arc<dyn GoogleOAuth2Provider + Send + Sync>,
     pub auth_repo: Arc<ProviderAuthRepo>,
+    pub user_repo: Arc<UserRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub invitation_service: Arc<InvitationService>,
+    pub legal_service: Arc<LegalService>,
     pub auth_conf: AuthConfig,
 }
 
 impl GoogleAuthService {
-    pub async fn register(&self, auth_code: &str, auth_redirect_uri: &str, auth_nonce: &str) -> Result<String, AppError> {
+    pub async fn register(
+        &self,
+        auth_code: &str,
+        auth_redirect_uri: &str,
+        auth_nonce: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<String, AppError> {
+        let res = self
+            .register_sub(auth_code, auth_redirect_uri, auth_nonce, invitation_code, legal_document_ids, client)
+            .await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|user_id| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Register,
+            auth_method: Some(AuthAuditAuthMethod::Google),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn register_sub(
+        &self,
+        auth_code: &str,
+        auth_redirect_uri: &str,
+        auth_nonce: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<String, AppError> {
         let oauth2_token_result = self
             .oauth2_provider
             .get_oauth2_token(
@@ -32,22 +77,126 @@ impl GoogleAuthService {
         }
 
         if let Ok(user) = self.auth_repo.get_user("google", &id_token_claims.sub).await {
+            self.user_repo.cancel_deletion(&user.id).await?;
             return Ok(user.id);
         }
+        self.legal_service.check(legal_document_ids).await?;
 
         let user_info = self.oauth2_provider.get_user_info(&access_token).await?;
 
-        let user_id = self.auth_repo.create_user(&user_info.name, "google", &id_token_claims.sub).await?;
+        let invitation = self.invitation_service.reserve(invitation_code, Some(&user_info.email)).await?;
+        let consent = LegalConsent {
+            document_ids: legal_document_ids,
+            client,
+        };
+        let res = self
+            .auth_repo
+            .create_user(&user_info.name, "google", &id_token_claims.sub, &consent)
+            .await;
+        self.invitation_service.complete(invitation.as_ref(), &res).await?;
+        let user_id = res?;
 
         Ok(user_id)
     }
 
-    pub async fn unregister(&self, id: &str) -> Result<(), AppError> {
-        self.auth_repo.delete_user(id).await?;
+    pub async fn upgrade_guest(
+        &self,
+        guest_user_id: &str,
+        auth_code: &str,
+        auth_redirect_uri: &str,
+        auth_nonce: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        let res = self
+            .upgrade_guest_sub(
+                guest_user_id,
+                auth_code,
+                auth_redirect_uri,
+                auth_nonce,
+                invitation_code,
+                legal_document_ids,
+                client,
+            )
+            .await;
+        let event = AuthAuditEvent {
+            user_id: Some(guest_user_id),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Register,
+            auth_method: Some(AuthAuditAuthMethod::Google),
+            client,
+            detail: Some(json!({ "guest": true })),
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn upgrade_guest_sub(
+        &self,
+        guest_user_id: &str,
+        auth_code: &str,
+        auth_redirect_uri: &str,
+        auth_nonce: &str,
+        invitation_code: Option<&str>,
+        legal_document_ids: &[String],
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        let oauth2_token_result = self
+            .oauth2_provider
+            .get_oauth2_token(
+                auth_code,
+                auth_redirect_uri,
+                &self.auth_conf.google.client_id,
+                &self.auth_conf.google.client_secret,
+            )
+            .await?;
+        let access_token = oauth2_token_result.access_token;
+        let id_token_claims = oauth2_token_result.id_token_claims;
+
+        if auth_nonce != id_token_claims.nonce {
+            return Err(AppError::RegisterRejection(anyhow::anyhow!("Nonce mismatch error")));
+        }
+
+        // Merging two accounts isn't supported, so a Google account that already signs in elsewhere can't take over the guest.
+        if self.auth_repo.exist_user("google", &id_token_claims.sub).await? {
+            return Err(AppError::Conflict(anyhow::anyhow!("Google account is already registered")));
+        }
+        self.legal_service.check(legal_document_ids).await?;
+
+        let user_info = self.oauth2_provider.get_user_info(&access_token).await?;
+
+        let invitation = self.invitation_service.reserve(invitation_code, Some(&user_info.email)).await?;
+        let consent = LegalConsent {
+            document_ids: legal_document_ids,
+            client,
+        };
+        let res = self
+            .auth_repo
+            .upgrade_guest(guest_user_id, &user_info.name, "google", &id_token_claims.sub, &consent)
+            .await
+            .map(|_| guest_user_id.to_string());
+        self.invitation_service.complete(invitation.as_ref(), &res).await?;
+        res?;
+
         Ok(())
     }
 
-    pub async fn login(&self, auth_code: &str, auth_redirect_uri: &str, auth_nonce: &str) -> Result<String, AppError> {
+    pub async fn login(&self, auth_code: &str, auth_redirect_uri: &str, auth_nonce: &str, client: &ClientInfo) -> Result<String, AppError> {
+        let res = self.login_sub(auth_code, auth_redirect_uri, auth_nonce).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|user_id| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Login,
+            auth_method: Some(AuthAuditAuthMethod::Google),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn login_sub(&self, auth_code: &str, auth_redirect_uri: &str, auth_nonce: &str) -> Result<String, AppError> {
         let oauth2_token_result = self
             .oauth2_provider
             .get_oauth2_token(
@@ -64,9 +213,40 @@ impl GoogleAuthService {
         }
 
         let user = self.auth_repo.get_user("google", &id_token_claims.sub).await?;
+        self.user_repo.cancel_deletion(&user.id).await?;
 
         Ok(user.id)
     }
+
+    pub async fn reauthenticate(
+        &self,
+        user_id: &str,
+        auth_code: &str,
+        auth_redirect_uri: &str,
+        auth_nonce: &str,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        let res = self.reauthenticate_sub(user_id, auth_code, auth_redirect_uri, auth_nonce).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Reauthenticate,
+            auth_method: Some(AuthAuditAuthMethod::Google),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    // The Google account that just signed in must be the one behind the current session.
+    async fn reauthenticate_sub(&self, user_id: &str, auth_code: &str, auth_redirect_uri: &str, auth_nonce: &str) -> Result<(), AppError> {
+        let authenticated_user_id = self.login_sub(auth_code, auth_redirect_uri, auth_nonce).await?;
+        if authenticated_user_id != user_id {
+            return Err(AppError::Forbidden);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -74,16 +254,19 @@ mod tests {
     use std::sync::Mutex;
 
     use async_trait::async_trait;
-    use chrono::Duration;
     use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
-    use core_migration::postgres::PostgresMigrator;
-    use core_testkit::containers::postgres::PostgresContainer;
-    use opxs_base::{GoogleAuthConfig, JwtConfig, JwtSecretConfig};
-    use sqlx::postgres::PgPoolOptions;
+    use opxs_base::{
+        EmailPolicyConfig, GoogleAuthConfig, GuestConfig, InvitationConfig, JwtConfig, JwtSecretConfig, PasswordPolicyConfig, RegisterPolicyConfig,
+        UnregisterConfig,
+    };
 
     use crate::{
+        audit::AuthAuditRepo,
+        invitation::InvitationRepo,
+        legal::LegalRepo,
         provider::{IdTokenClaims, OAuth2TokenResult, UserInfo},
         shared,
+        user::UserService,
     };
 
     use super::*;
@@ -91,22 +274,8 @@ mod tests {
     #[tokio::test]
     async fn simple_test() {
         let docker = testcontainers::clients::Cli::default();
-        let container = PostgresContainer::new(&docker, shared::POSTGRES_VERSION);
-
-        let db = Arc::new(
-            PgPoolOptions::new()
-                .max_connections(100)
-                .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
-                .connect(&container.connection_string)
-                .await
-                .unwrap(),
-        );
-
-        let migrations_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../conf/migrations");
-        let migrator = PostgresMigrator::new(&container.connection_string, migrations_path, "opxs-api", "")
-            .await
-            .unwrap();
-        migrator.migrate().await.unwrap();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
 
         let access_token = "access_token";
         let provider_user_id = "provider_user_id";
@@ -137,10 +306,22 @@ mod tests {
             },
         ));
         let auth_repo = Arc::new(ProviderAuthRepo {
-            db,
+            db: db.clone(),
             system_clock: system_clock.clone(),
-            tsid_provider,
+            tsid_provider: tsid_provider.clone(),
         });
+        let user_repo = Arc::new(UserRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: Arc::new(AuthAuditRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+        });
+        let client = ClientInfo::default();
         let auth_conf = AuthConfig {
             jwt: JwtConfig {
                 secret: JwtSecretConfig {
@@ -152,16 +333,63 @@ mod tests {
                 client_id: client_id.to_string(),
                 client_secret: client_secret.to_string(),
             },
+            unregister: UnregisterConfig { grace_period_days: 0 },
+            register_policy: RegisterPolicyConfig {
+                email: EmailPolicyConfig {
+                    allowed_domains: vec![],
+                    denied_domains: vec![],
+                    deny_disposable: true,
+                },
+                password: PasswordPolicyConfig {
+                    min_length: 8,
+                    max_length: 128,
+                    min_score: 2,
+                    deny_breached: true,
+                },
+            },
+            invitation: InvitationConfig {
+                required: false,
+                expires_in_days: 7,
+                max_uses: 1,
+            },
+            guest: GuestConfig {
+                convert_quota: 10,
+                retention_days: 30,
+                login_limit_per_hour: 20,
+                anonymous_convert_limit_per_hour: 10,
+            },
         };
+        let invitation_service = Arc::new(InvitationService {
+            invitation_repo: Arc::new(InvitationRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+            invitation_conf: auth_conf.invitation.clone(),
+        });
+        let legal_service = Arc::new(LegalService {
+            legal_repo: Arc::new(LegalRepo {
+                db,
+                system_clock: system_clock.clone(),
+                tsid_provider,
+            }),
+            system_clock: system_clock.clone(),
+        });
 
         let auth_service = GoogleAuthService {
             oauth2_provider: oauth2_provider.clone(),
             auth_repo: auth_repo.clone(),
-            auth_conf,
+            user_repo: user_repo.clone(),
+            audit_logger: audit_logger.clone(),
+            invitation_service,
+            legal_service,
+            auth_conf: auth_conf.clone(),
         };
 
         // register
-        let user_id = auth_service.register(code, redirect_uri, nonce).await.unwrap();
+        let user_id = auth_service.register(code, redirect_uri, nonce, None, &[], &client).await.unwrap();
         println!("{}", user_id);
         assert_eq!(*oauth2_provider.clone().get_oauth2_token_param.lock().unwrap().code, code.to_string());
         assert_eq!(
@@ -178,14 +406,28 @@ mod tests {
         );
 
         // login
-        assert_eq!(auth_service.login(code, redirect_uri, nonce).await.unwrap(), user_id);
+        assert_eq!(auth_service.login(code, redirect_uri, nonce, &client).await.unwrap(), user_id);
+
+        // reauthenticate
+        assert!(auth_service.reauthenticate(&user_id, code, redirect_uri, nonce, &client).await.is_ok());
+        assert!(matches!(
+            auth_service.reauthenticate("other_user_id", code, redirect_uri, nonce, &client).await,
+            Err(AppError::Forbidden)
+        ));
 
         // get user
         let user = auth_repo.get_user("google", provider_user_id).await.unwrap();
         assert_eq!(user.name, user_name.to_string());
 
         // unregister
-        assert!(auth_service.unregister(&user_id).await.is_ok());
+        let user_service = UserService {
+            user_repo,
+            audit_logger,
+            system_clock,
+            unregister_conf: auth_conf.unregister,
+        };
+        assert!(user_service.unregister(&user_id, &client).await.is_ok());
+        assert!(user_service.purge(&user_id).await.is_ok());
 
         // get user
         assert!(auth_repo.get_user("google", provider_user_id).await.is_err());
@@ -7,7 +7,10 @@ use core_base::{clock::SystemClock, tsid::TsidProvider};
 
 use opxs_base::AppError;
 
-use crate::shared::model::{User, UserAuthenticationType, UserRole};
+use crate::{
+    legal::LegalRepo,
+    shared::model::{LegalConsent, User, UserAuthenticationType, UserRole},
+};
 
 pub struct ProviderAuthRepo {
     pub db: Arc<PgPool>,
@@ -16,7 +19,7 @@ pub struct ProviderAuthRepo {
 }
 
 impl ProviderAuthRepo {
-    pub async fn create_user(&self, name: &str, provider_type: &str, provider_user_id: &str) -> Result<String, AppError> {
+    pub async fn create_user(&self, name: &str, provider_type: &str, provider_user_id: &str, consent: &LegalConsent<'_>) -> Result<String, AppError> {
         let user_id = self.tsid_provider.gen().to_string();
         let now = self.system_clock.now();
 
@@ -52,24 +55,68 @@ INSERT INTO user_auth_providers (user_id, provider_type, provider_user_id, creat
         .await
         .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
+        LegalRepo::insert_acceptances(&mut tx, &user_id, consent, &now).await?;
+
         tx.commit().await?;
 
         Ok(user_id)
     }
 
-    pub async fn delete_user(&self, id: &str) -> Result<(), AppError> {
+    pub async fn upgrade_guest(
+        &self,
+        user_id: &str,
+        name: &str,
+        provider_type: &str,
+        provider_user_id: &str,
+        consent: &LegalConsent<'_>,
+    ) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
         let mut tx = self.db.begin().await?;
 
-        let queries = vec![
-            sqlx::query("DELETE FROM users WHERE id = $1").bind(id),
-            sqlx::query("DELETE FROM user_auth_providers WHERE user_id = $1").bind(id),
-            sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1").bind(id),
-        ];
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET name = $2, authentication_type = $3, role = $4, updated_at = $6
+    WHERE id = $1 AND role = $5;
+"#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(UserAuthenticationType::Provider)
+        .bind(UserRole::User)
+        .bind(UserRole::Guest)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
-        for query in queries {
-            query.execute(&mut tx).await.map_err(|e| AppError::UnexpectedError(e.into()))?;
+        if res.rows_affected() < 1 {
+            return Err(AppError::Forbidden);
         }
 
+        sqlx::query(
+            r#"
+INSERT INTO user_auth_providers (user_id, provider_type, provider_user_id, created_at)
+    VALUES ($1, $2, $3, $4)
+"#,
+        )
+        .bind(user_id)
+        .bind(provider_type)
+        .bind(provider_user_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query("DELETE FROM user_guests WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        LegalRepo::insert_acceptances(&mut tx, user_id, consent, &now).await?;
+
         tx.commit().await?;
 
         Ok(())
@@ -0,0 +1,7 @@
+mod metadata;
+mod repo;
+mod service;
+
+pub use metadata::*;
+pub use repo::*;
+pub use service::*;
@@ -0,0 +1,81 @@
+use base64::Engine as _;
+
+use opxs_base::AppError;
+
+// Builds the IdP EntityDescriptor from the three values an organization admin configures, so they don't have to paste metadata XML.
+pub fn idp_metadata_xml(entity_id: &str, sso_url: &str, certificate: &str) -> String {
+    format!(
+        r#"<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" xmlns:ds="http://www.w3.org/2000/09/xmldsig#" entityID="{entity_id}">
+    <md:IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <md:KeyDescriptor use="signing">
+            <ds:KeyInfo>
+                <ds:X509Data>
+                    <ds:X509Certificate>{certificate}</ds:X509Certificate>
+                </ds:X509Data>
+            </ds:KeyInfo>
+        </md:KeyDescriptor>
+        <md:SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="{sso_url}"/>
+    </md:IDPSSODescriptor>
+</md:EntityDescriptor>"#,
+        entity_id = escape_xml(entity_id),
+        sso_url = escape_xml(sso_url),
+        certificate = certificate,
+    )
+}
+
+pub fn sp_metadata_xml(entity_id: &str, acs_url: &str) -> String {
+    format!(
+        r#"<md:EntityDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" entityID="{entity_id}">
+    <md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">
+        <md:NameIDFormat>urn:oasis:names:tc:SAML:1.1:nameid-format:unspecified</md:NameIDFormat>
+        <md:AssertionConsumerService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="{acs_url}" index="0" isDefault="true"/>
+    </md:SPSSODescriptor>
+</md:EntityDescriptor>"#,
+        entity_id = escape_xml(entity_id),
+        acs_url = escape_xml(acs_url),
+    )
+}
+
+// Accepts either PEM or bare base64 DER and returns the base64 body that goes into X509Certificate.
+pub fn normalize_certificate(certificate: &str) -> Result<String, AppError> {
+    let body: String = certificate
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .flat_map(|line| line.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    match base64::engine::general_purpose::STANDARD.decode(&body) {
+        Ok(der) if !der.is_empty() => Ok(body),
+        _ => Err(AppError::InvalidRequest(anyhow::anyhow!("Invalid certificate"))),
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_certificate_test() {
+        let pem = "-----BEGIN CERTIFICATE-----\nTUlJ\nQ2Q=\n-----END CERTIFICATE-----\n";
+        assert_eq!(normalize_certificate(pem).unwrap(), "TUlJQ2Q=");
+        assert_eq!(normalize_certificate("TUlJQ2Q=").unwrap(), "TUlJQ2Q=");
+        assert!(normalize_certificate("not a certificate").is_err());
+        assert!(normalize_certificate("").is_err());
+    }
+
+    #[test]
+    fn escape_test() {
+        let xml = sp_metadata_xml("https://example.com/?a=1&b=\"2\"", "https://example.com/acs");
+        assert!(xml.contains(r#"entityID="https://example.com/?a=1&amp;b=&quot;2&quot;""#));
+    }
+}
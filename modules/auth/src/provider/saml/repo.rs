@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::shared::model::{OrganizationRole, SamlProvider, UserAuthenticationType, UserRole};
+
+pub struct SamlRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl SamlRepo {
+    pub async fn upsert_provider(
+        &self,
+        organization_id: &str,
+        idp_entity_id: &str,
+        idp_sso_url: &str,
+        idp_certificate: &str,
+    ) -> Result<SamlProvider, AppError> {
+        let now = self.system_clock.now();
+
+        let provider: SamlProvider = sqlx::query_as(
+            r#"
+INSERT INTO organization_saml_providers (organization_id, idp_entity_id, idp_sso_url, idp_certificate, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $5)
+    ON CONFLICT (organization_id) DO UPDATE
+        SET idp_entity_id = $2, idp_sso_url = $3, idp_certificate = $4, updated_at = $5
+    RETURNING *;
+"#,
+        )
+        .bind(organization_id)
+        .bind(idp_entity_id)
+        .bind(idp_sso_url)
+        .bind(idp_certificate)
+        .bind(now)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(provider)
+    }
+
+    pub async fn get_provider(&self, organization_id: &str) -> Result<Option<SamlProvider>, AppError> {
+        let provider: Option<SamlProvider> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM organization_saml_providers
+    WHERE organization_id = $1;
+"#,
+        )
+        .bind(organization_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(provider)
+    }
+
+    pub async fn delete_provider(&self, organization_id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+DELETE FROM organization_saml_providers
+    WHERE organization_id = $1;
+"#,
+        )
+        .bind(organization_id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    pub async fn create_request(&self, id: &str, organization_id: &str, expires_at: &DateTime<Utc>) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+DELETE FROM saml_authn_requests
+    WHERE expires_at <= $1;
+"#,
+        )
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO saml_authn_requests (id, organization_id, expires_at, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $4);
+"#,
+        )
+        .bind(id)
+        .bind(organization_id)
+        .bind(expires_at)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    // A request id is only valid until an assertion has been accepted for it, so responses can't be replayed.
+    pub async fn exist_pending_request(&self, id: &str, organization_id: &str) -> Result<bool, AppError> {
+        let now = self.system_clock.now();
+
+        let (existed,): (bool,) = sqlx::query_as(
+            r#"
+SELECT EXISTS (
+    SELECT id
+        FROM saml_authn_requests
+        WHERE id = $1 AND organization_id = $2 AND user_id IS NULL AND expires_at > $3
+);
+"#,
+        )
+        .bind(id)
+        .bind(organization_id)
+        .bind(now)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(existed)
+    }
+
+    pub async fn complete_request(&self, id: &str, user_id: &str, login_code_hash: &str) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE saml_authn_requests
+    SET user_id = $2, login_code_hash = $3, updated_at = $4
+    WHERE id = $1 AND user_id IS NULL AND expires_at > $4;
+"#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(login_code_hash)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::LoginRejection(anyhow::anyhow!("SAML request is already used")));
+        }
+
+        Ok(())
+    }
+
+    pub async fn consume_login_code(&self, login_code_hash: &str) -> Result<Option<String>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<(String,)> = sqlx::query_as(
+            r#"
+DELETE FROM saml_authn_requests
+    WHERE login_code_hash = $1 AND expires_at > $2
+    RETURNING user_id;
+"#,
+        )
+        .bind(login_code_hash)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.map(|(user_id,)| user_id))
+    }
+
+    // Just-in-time provisioning: the user joins the organization whose IdP vouched for them.
+    pub async fn create_user(&self, name: &str, organization_id: &str, provider_user_id: &str) -> Result<String, AppError> {
+        let user_id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(&user_id)
+        .bind(name)
+        .bind(UserAuthenticationType::Provider)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO user_auth_providers (user_id, provider_type, provider_user_id, created_at)
+    VALUES ($1, $2, $3, $4)
+"#,
+        )
+        .bind(&user_id)
+        .bind(super::SAML_PROVIDER_TYPE)
+        .bind(provider_user_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO organization_members (organization_id, user_id, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5);
+"#,
+        )
+        .bind(organization_id)
+        .bind(&user_id)
+        .bind(OrganizationRole::Member)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+}
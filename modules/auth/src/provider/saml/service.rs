@@ -0,0 +1,385 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use samael::{
+    metadata::EntityDescriptor,
+    schema::Assertion,
+    service_provider::{ServiceProvider, ServiceProviderBuilder},
+};
+use serde_json::json;
+use url::Url;
+
+use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
+
+use opxs_base::AppError;
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    organization::OrganizationRepo,
+    provider::ProviderAuthRepo,
+    shared::{
+        model::{AuthAuditAuthMethod, AuthAuditEventType, ClientInfo, SamlProvider},
+        sha256_hex,
+    },
+    user::UserRepo,
+};
+
+use super::{idp_metadata_xml, normalize_certificate, sp_metadata_xml, SamlRepo};
+
+pub const SAML_PROVIDER_TYPE: &str = "saml";
+
+const REQUEST_EXPIRES_IN_MINUTES: i64 = 10;
+const DISPLAY_NAME_ATTRIBUTES: &[&str] = &["displayName", "http://schemas.xmlsoap.org/ws/2005/05/identity/claims/name"];
+
+pub struct SamlAuthService {
+    pub saml_repo: Arc<SamlRepo>,
+    pub provider_repo: Arc<ProviderAuthRepo>,
+    pub organization_repo: Arc<OrganizationRepo>,
+    pub user_repo: Arc<UserRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
+    // Where the per-organization SP endpoints are mounted, e.g. "https://example.com/api/v1/auth/saml/".
+    pub sp_base_url: String,
+}
+
+impl SamlAuthService {
+    pub async fn get_provider(&self, organization_id: &str, user_id: &str) -> Result<SamlProvider, AppError> {
+        self.ensure_manager(organization_id, user_id).await?;
+        self.get_provider_sub(organization_id).await
+    }
+
+    pub async fn update_provider(
+        &self,
+        organization_id: &str,
+        user_id: &str,
+        idp_entity_id: &str,
+        idp_sso_url: &str,
+        idp_certificate: &str,
+    ) -> Result<SamlProvider, AppError> {
+        self.ensure_manager(organization_id, user_id).await?;
+
+        Url::parse(idp_sso_url).map_err(|e| AppError::InvalidRequest(e.into()))?;
+        let idp_certificate = normalize_certificate(idp_certificate)?;
+        parse_idp_metadata(idp_entity_id, idp_sso_url, &idp_certificate).map_err(|e| AppError::InvalidRequest(e.into()))?;
+
+        self.saml_repo
+            .upsert_provider(organization_id, idp_entity_id, idp_sso_url, &idp_certificate)
+            .await
+    }
+
+    pub async fn delete_provider(&self, organization_id: &str, user_id: &str) -> Result<(), AppError> {
+        self.ensure_manager(organization_id, user_id).await?;
+        self.saml_repo.delete_provider(organization_id).await
+    }
+
+    pub fn sp_metadata(&self, organization_id: &str) -> String {
+        sp_metadata_xml(&self.sp_entity_id(organization_id), &self.acs_url(organization_id))
+    }
+
+    // SP-initiated login: returns the IdP URL to redirect the browser to. The request id doubles as RelayState.
+    pub async fn start(&self, organization_id: &str) -> Result<String, AppError> {
+        let provider = self.get_provider_sub(organization_id).await?;
+        let (request_id, redirect_url) = {
+            let sp = self.service_provider(&provider)?;
+            let request = sp
+                .make_authentication_request(&provider.idp_sso_url)
+                .map_err(|e| AppError::UnexpectedError(anyhow::anyhow!("{}", e)))?;
+            let redirect_url = request
+                .redirect(&request.id)
+                .map_err(|e| AppError::UnexpectedError(anyhow::anyhow!("{}", e)))?
+                .ok_or(AppError::UnexpectedError(anyhow::anyhow!("Failed to build SAML redirect URL")))?;
+            (request.id.clone(), redirect_url.to_string())
+        };
+
+        let expires_at = self.system_clock.now() + Duration::minutes(REQUEST_EXPIRES_IN_MINUTES);
+        self.saml_repo.create_request(&request_id, organization_id, &expires_at).await?;
+
+        Ok(redirect_url)
+    }
+
+    // Validates the IdP's signed response and returns a one-time code the web app exchanges for a session.
+    pub async fn acs(&self, organization_id: &str, saml_response: &str, relay_state: &str, client: &ClientInfo) -> Result<String, AppError> {
+        let res = self.acs_sub(organization_id, saml_response, relay_state).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|(user_id, _)| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Login,
+            auth_method: Some(AuthAuditAuthMethod::Saml),
+            client,
+            detail: Some(json!({ "organization_id": organization_id })),
+        };
+        self.audit_logger.write(event, &res).await?;
+        res.map(|(_, login_code)| login_code)
+    }
+
+    async fn acs_sub(&self, organization_id: &str, saml_response: &str, relay_state: &str) -> Result<(String, String), AppError> {
+        if !self.saml_repo.exist_pending_request(relay_state, organization_id).await? {
+            return Err(AppError::LoginRejection(anyhow::anyhow!("Unknown or expired SAML request")));
+        }
+
+        let provider = self.get_provider_sub(organization_id).await?;
+        let assertion = self
+            .service_provider(&provider)?
+            .parse_base64_response(saml_response, Some(&[relay_state]))
+            .map_err(|e| AppError::LoginRejection(anyhow::anyhow!("{}", e)))?;
+
+        let name_id = assertion
+            .subject
+            .as_ref()
+            .and_then(|n| n.name_id.as_ref())
+            .map(|n| n.value.clone())
+            .filter(|n| !n.is_empty())
+            .ok_or(AppError::LoginRejection(anyhow::anyhow!("NameID not found")))?;
+
+        // NameIDs are only unique within one IdP, so the organization is part of the identity.
+        let provider_user_id = format!("{}:{}", organization_id, name_id);
+        let user_id = match self.provider_repo.get_user(SAML_PROVIDER_TYPE, &provider_user_id).await {
+            Ok(user) => {
+                self.user_repo.cancel_deletion(&user.id).await?;
+                user.id
+            }
+            Err(AppError::UserNotFound) => {
+                let name = display_name(&assertion).unwrap_or(name_id);
+                self.saml_repo.create_user(&name, organization_id, &provider_user_id).await?
+            }
+            Err(e) => return Err(e),
+        };
+
+        let login_code = hex::encode(self.random_bytes_provider.get_bytes(32));
+        self.saml_repo.complete_request(relay_state, &user_id, &sha256_hex(&login_code)).await?;
+
+        Ok((user_id, login_code))
+    }
+
+    pub async fn exchange(&self, login_code: &str) -> Result<String, AppError> {
+        self.saml_repo
+            .consume_login_code(&sha256_hex(login_code))
+            .await?
+            .ok_or(AppError::LoginRejection(anyhow::anyhow!("Invalid or expired login code")))
+    }
+
+    async fn ensure_manager(&self, organization_id: &str, user_id: &str) -> Result<(), AppError> {
+        match self.organization_repo.get_role(organization_id, user_id).await? {
+            Some(role) if role.can_manage_members() => Ok(()),
+            _ => Err(AppError::Forbidden),
+        }
+    }
+
+    async fn get_provider_sub(&self, organization_id: &str) -> Result<SamlProvider, AppError> {
+        self.saml_repo
+            .get_provider(organization_id)
+            .await?
+            .ok_or(AppError::InvalidRequest(anyhow::anyhow!("SAML is not configured for this organization")))
+    }
+
+    fn service_provider(&self, provider: &SamlProvider) -> Result<ServiceProvider, AppError> {
+        let idp_metadata = parse_idp_metadata(&provider.idp_entity_id, &provider.idp_sso_url, &provider.idp_certificate)?;
+
+        ServiceProviderBuilder::default()
+            .entity_id(self.sp_entity_id(&provider.organization_id))
+            .acs_url(self.acs_url(&provider.organization_id))
+            .idp_metadata(idp_metadata)
+            .allow_idp_initiated(false)
+            .build()
+            .map_err(|e| AppError::UnexpectedError(anyhow::anyhow!("{}", e)))
+    }
+
+    fn sp_entity_id(&self, organization_id: &str) -> String {
+        format!("{}{}/metadata", self.sp_base_url, organization_id)
+    }
+
+    fn acs_url(&self, organization_id: &str) -> String {
+        format!("{}{}/acs", self.sp_base_url, organization_id)
+    }
+}
+
+fn parse_idp_metadata(entity_id: &str, sso_url: &str, certificate: &str) -> anyhow::Result<EntityDescriptor> {
+    samael::metadata::de::from_str(&idp_metadata_xml(entity_id, sso_url, certificate)).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+fn display_name(assertion: &Assertion) -> Option<String> {
+    assertion
+        .attribute_statements
+        .iter()
+        .flatten()
+        .flat_map(|s| s.attributes.iter())
+        .filter(|a| {
+            let name = a.name.as_deref().or(a.friendly_name.as_deref()).unwrap_or_default();
+            DISPLAY_NAME_ATTRIBUTES.contains(&name)
+        })
+        .flat_map(|a| a.values.iter())
+        .find_map(|v| v.value.clone())
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine as _;
+    use chrono::{DateTime, NaiveDateTime, TimeZone};
+    use samael::{
+        idp::{CertificateParams, IdentityProvider, KeyType},
+        traits::ToXml,
+    };
+
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{
+            self,
+            model::{OrganizationRole, UserAuthenticationType, UserRole},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+        let organization_repo = Arc::new(OrganizationRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let saml_service = SamlAuthService {
+            saml_repo: Arc::new(SamlRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            provider_repo: Arc::new(ProviderAuthRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            organization_repo: organization_repo.clone(),
+            user_repo: Arc::new(UserRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            audit_logger: Arc::new(AuthAuditLogger {
+                audit_repo: Arc::new(AuthAuditRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: tsid_provider.clone(),
+                }),
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+            sp_base_url: "https://opxs.example.com/api/v1/auth/saml/".to_string(),
+        };
+        let client = ClientInfo::default();
+
+        let now = NaiveDateTime::from_timestamp_opt(0, 0).unwrap_or(NaiveDateTime::MIN);
+        let now: DateTime<Utc> = Utc.from_utc_datetime(&now);
+        let owner_id = "owner_id";
+
+        // create owner and organization
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(owner_id)
+        .bind("owner")
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        let organization = organization_repo.create_organization("org", owner_id).await.unwrap();
+
+        // a locally generated IdP signs the fixture responses
+        let idp = IdentityProvider::generate_new(KeyType::Rsa2048).unwrap();
+        let idp_certificate = idp
+            .create_certificate(&CertificateParams {
+                common_name: "opxs-test-idp",
+                issuer_name: "opxs-test-idp",
+                days_until_expiration: 1,
+            })
+            .unwrap();
+        let idp_entity_id = "https://idp.example.com/metadata";
+
+        assert!(matches!(
+            saml_service
+                .update_provider(&organization.id, "stranger", idp_entity_id, "https://idp.example.com/sso", "")
+                .await,
+            Err(AppError::Forbidden)
+        ));
+        saml_service
+            .update_provider(
+                &organization.id,
+                owner_id,
+                idp_entity_id,
+                "https://idp.example.com/sso",
+                &base64::engine::general_purpose::STANDARD.encode(&idp_certificate),
+            )
+            .await
+            .unwrap();
+
+        let redirect_url = Url::parse(&saml_service.start(&organization.id).await.unwrap()).unwrap();
+        assert_eq!(redirect_url.host_str(), Some("idp.example.com"));
+        let request_id = redirect_url
+            .query_pairs()
+            .find(|(k, _)| k == "RelayState")
+            .map(|(_, v)| v.to_string())
+            .unwrap();
+
+        let sign = |idp: &IdentityProvider, in_response_to: &str| {
+            let response = idp
+                .sign_authn_response(
+                    &idp_certificate,
+                    "alice@example.com",
+                    &saml_service.sp_entity_id(&organization.id),
+                    &saml_service.acs_url(&organization.id),
+                    idp_entity_id,
+                    in_response_to,
+                    &[],
+                )
+                .unwrap();
+            base64::engine::general_purpose::STANDARD.encode(response.to_xml().unwrap())
+        };
+
+        // a response signed by another key is rejected
+        let other_idp = IdentityProvider::generate_new(KeyType::Rsa2048).unwrap();
+        assert!(saml_service
+            .acs(&organization.id, &sign(&other_idp, &request_id), &request_id, &client)
+            .await
+            .is_err());
+
+        // a valid response provisions the user into the organization
+        let response = sign(&idp, &request_id);
+        let login_code = saml_service.acs(&organization.id, &response, &request_id, &client).await.unwrap();
+        let user_id = saml_service.exchange(&login_code).await.unwrap();
+        assert_eq!(
+            organization_repo.get_role(&organization.id, &user_id).await.unwrap(),
+            Some(OrganizationRole::Member)
+        );
+
+        // neither the response nor the login code can be replayed
+        assert!(saml_service.acs(&organization.id, &response, &request_id, &client).await.is_err());
+        assert!(saml_service.exchange(&login_code).await.is_err());
+
+        // the next login finds the same user
+        let redirect_url = Url::parse(&saml_service.start(&organization.id).await.unwrap()).unwrap();
+        let request_id = redirect_url
+            .query_pairs()
+            .find(|(k, _)| k == "RelayState")
+            .map(|(_, v)| v.to_string())
+            .unwrap();
+        let login_code = saml_service
+            .acs(&organization.id, &sign(&idp, &request_id), &request_id, &client)
+            .await
+            .unwrap();
+        assert_eq!(saml_service.exchange(&login_code).await.unwrap(), user_id);
+    }
+}
@@ -0,0 +1,3 @@
+mod repo;
+
+pub use repo::*;
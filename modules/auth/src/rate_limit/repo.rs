@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use core_base::clock::SystemClock;
+
+use opxs_base::AppError;
+
+pub struct RateLimitRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl RateLimitRepo {
+    // Counts the hit with a single upsert so concurrent requests can't both slip under the limit.
+    pub async fn hit(&self, key: &str, window: Duration, limit: i32) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let (count,): (i32,) = sqlx::query_as(
+            r#"
+INSERT INTO auth_rate_limits (key, count, window_started_at)
+    VALUES ($1, 1, $2)
+    ON CONFLICT (key) DO UPDATE SET
+        count = CASE WHEN auth_rate_limits.window_started_at <= $3 THEN 1 ELSE auth_rate_limits.count + 1 END,
+        window_started_at = CASE WHEN auth_rate_limits.window_started_at <= $3 THEN $2 ELSE auth_rate_limits.window_started_at END
+    RETURNING count;
+"#,
+        )
+        .bind(key)
+        .bind(now)
+        .bind(now - window)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if count > limit {
+            return Err(AppError::TooManyRequests);
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(&self, before: &DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+DELETE FROM auth_rate_limits
+    WHERE window_started_at < $1;
+"#,
+        )
+        .bind(before)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
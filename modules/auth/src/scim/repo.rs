@@ -0,0 +1,489 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use opxs_base::AppError;
+
+use crate::{
+    provider::SAML_PROVIDER_TYPE,
+    shared::model::{OrganizationRole, ScimGroup, ScimGroupMember, ScimToken, ScimUser, UserAuthenticationType, UserRole},
+};
+
+use super::{ScimGroupInput, ScimUserFilter, ScimUserInput};
+
+// SCIM users are the organization's SAML identities: provider_user_id is "<organization_id>:<userName>".
+const SCIM_USER_SELECT: &str = r#"
+SELECT u.id, SUBSTRING(p.provider_user_id FROM CHAR_LENGTH($1) + 2) AS user_name, p.external_id, u.name, u.suspended_at IS NULL AS active, u.created_at, u.updated_at
+    FROM users u
+    JOIN user_auth_providers p on u.id = p.user_id
+    WHERE p.provider_type = $2 AND STARTS_WITH(p.provider_user_id, $1 || ':')
+"#;
+
+pub struct ScimRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+impl ScimRepo {
+    pub async fn create_token(&self, organization_id: &str, token_hash: &str, created_by: &str) -> Result<ScimToken, AppError> {
+        let id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let token: ScimToken = sqlx::query_as(
+            r#"
+INSERT INTO organization_scim_tokens (id, organization_id, token_hash, created_by, created_at)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING id, organization_id, created_by, last_used_at, created_at;
+"#,
+        )
+        .bind(&id)
+        .bind(organization_id)
+        .bind(token_hash)
+        .bind(created_by)
+        .bind(now)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(token)
+    }
+
+    pub async fn get_tokens(&self, organization_id: &str) -> Result<Vec<ScimToken>, AppError> {
+        let res: Vec<ScimToken> = sqlx::query_as(
+            r#"
+SELECT id, organization_id, created_by, last_used_at, created_at
+    FROM organization_scim_tokens
+    WHERE organization_id = $1
+    ORDER BY created_at DESC;
+"#,
+        )
+        .bind(organization_id)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn delete_token(&self, organization_id: &str, id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+DELETE FROM organization_scim_tokens
+    WHERE organization_id = $1 AND id = $2;
+"#,
+        )
+        .bind(organization_id)
+        .bind(id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    pub async fn use_token(&self, token_hash: &str) -> Result<Option<String>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<(String,)> = sqlx::query_as(
+            r#"
+UPDATE organization_scim_tokens
+    SET last_used_at = $2
+    WHERE token_hash = $1
+    RETURNING organization_id;
+"#,
+        )
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.map(|(organization_id,)| organization_id))
+    }
+
+    pub async fn count_users(&self, organization_id: &str, filter: Option<&ScimUserFilter>) -> Result<i64, AppError> {
+        let (user_name, external_id) = filter_params(filter);
+        let (count,): (i64,) = sqlx::query_as(&format!(
+            "SELECT COUNT(*) FROM ({} AND ($3::VARCHAR IS NULL OR p.provider_user_id = $1 || ':' || $3) AND ($4::VARCHAR IS NULL OR p.external_id = $4)) s;",
+            SCIM_USER_SELECT
+        ))
+        .bind(organization_id)
+        .bind(SAML_PROVIDER_TYPE)
+        .bind(user_name)
+        .bind(external_id)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(count)
+    }
+
+    pub async fn get_users(
+        &self,
+        organization_id: &str,
+        filter: Option<&ScimUserFilter>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<ScimUser>, AppError> {
+        let (user_name, external_id) = filter_params(filter);
+        let res: Vec<ScimUser> = sqlx::query_as(&format!(
+            "{} AND ($3::VARCHAR IS NULL OR p.provider_user_id = $1 || ':' || $3) AND ($4::VARCHAR IS NULL OR p.external_id = $4) ORDER BY u.id OFFSET $5 LIMIT $6;",
+            SCIM_USER_SELECT
+        ))
+        .bind(organization_id)
+        .bind(SAML_PROVIDER_TYPE)
+        .bind(user_name)
+        .bind(external_id)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_user(&self, organization_id: &str, user_id: &str) -> Result<Option<ScimUser>, AppError> {
+        let res: Option<ScimUser> = sqlx::query_as(&format!("{} AND u.id = $3;", SCIM_USER_SELECT))
+            .bind(organization_id)
+            .bind(SAML_PROVIDER_TYPE)
+            .bind(user_id)
+            .fetch_optional(self.db.as_ref())
+            .await
+            .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn exist_user_name(&self, organization_id: &str, user_name: &str, exclude_user_id: Option<&str>) -> Result<bool, AppError> {
+        let (existed,): (bool,) = sqlx::query_as(
+            r#"
+SELECT EXISTS (
+    SELECT user_id
+        FROM user_auth_providers
+        WHERE provider_type = $1 AND provider_user_id = $2 || ':' || $3 AND ($4::VARCHAR IS NULL OR user_id <> $4)
+);
+"#,
+        )
+        .bind(SAML_PROVIDER_TYPE)
+        .bind(organization_id)
+        .bind(user_name)
+        .bind(exclude_user_id)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(existed)
+    }
+
+    pub async fn create_user(&self, organization_id: &str, input: &ScimUserInput) -> Result<String, AppError> {
+        let user_id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, suspended_at, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+"#,
+        )
+        .bind(&user_id)
+        .bind(&input.name)
+        .bind(UserAuthenticationType::Provider)
+        .bind(UserRole::User)
+        .bind(if input.active { None } else { Some(now) })
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO user_auth_providers (user_id, provider_type, provider_user_id, external_id, created_at)
+    VALUES ($1, $2, $3 || ':' || $4, $5, $6)
+"#,
+        )
+        .bind(&user_id)
+        .bind(SAML_PROVIDER_TYPE)
+        .bind(organization_id)
+        .bind(&input.user_name)
+        .bind(&input.external_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+INSERT INTO organization_members (organization_id, user_id, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5);
+"#,
+        )
+        .bind(organization_id)
+        .bind(&user_id)
+        .bind(OrganizationRole::Member)
+        .bind(now)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+
+    // Deactivation suspends the account and ends its sessions; reactivation only lifts the suspension.
+    pub async fn update_user(&self, organization_id: &str, user_id: &str, input: &ScimUserInput) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+UPDATE users
+    SET name = $2, suspended_at = CASE WHEN $3 THEN NULL ELSE COALESCE(suspended_at, $4) END, updated_at = $4
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .bind(&input.name)
+        .bind(input.active)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+UPDATE user_auth_providers
+    SET provider_user_id = $3 || ':' || $4, external_id = $5
+    WHERE user_id = $1 AND provider_type = $2;
+"#,
+        )
+        .bind(user_id)
+        .bind(SAML_PROVIDER_TYPE)
+        .bind(organization_id)
+        .bind(&input.user_name)
+        .bind(&input.external_id)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if !input.active {
+            sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut tx)
+                .await
+                .map_err(|e| AppError::UnexpectedError(e.into()))?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn count_groups(&self, organization_id: &str, display_name: Option<&str>) -> Result<i64, AppError> {
+        let (count,): (i64,) = sqlx::query_as(
+            r#"
+SELECT COUNT(*)
+    FROM scim_groups
+    WHERE organization_id = $1 AND ($2::VARCHAR IS NULL OR display_name = $2);
+"#,
+        )
+        .bind(organization_id)
+        .bind(display_name)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(count)
+    }
+
+    pub async fn get_groups(&self, organization_id: &str, display_name: Option<&str>, offset: i64, limit: i64) -> Result<Vec<ScimGroup>, AppError> {
+        let res: Vec<ScimGroup> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM scim_groups
+    WHERE organization_id = $1 AND ($2::VARCHAR IS NULL OR display_name = $2)
+    ORDER BY id
+    OFFSET $3
+    LIMIT $4;
+"#,
+        )
+        .bind(organization_id)
+        .bind(display_name)
+        .bind(offset)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_group(&self, organization_id: &str, group_id: &str) -> Result<Option<ScimGroup>, AppError> {
+        let res: Option<ScimGroup> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM scim_groups
+    WHERE organization_id = $1 AND id = $2;
+"#,
+        )
+        .bind(organization_id)
+        .bind(group_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_group_members(&self, group_ids: &[String]) -> Result<Vec<ScimGroupMember>, AppError> {
+        let res: Vec<ScimGroupMember> = sqlx::query_as(
+            r#"
+SELECT m.group_id, m.user_id, u.name
+    FROM scim_group_members m
+    JOIN users u on m.user_id = u.id
+    WHERE m.group_id = ANY($1)
+    ORDER BY m.group_id, m.user_id;
+"#,
+        )
+        .bind(group_ids)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn exist_display_name(&self, organization_id: &str, display_name: &str, exclude_group_id: Option<&str>) -> Result<bool, AppError> {
+        let (existed,): (bool,) = sqlx::query_as(
+            r#"
+SELECT EXISTS (
+    SELECT id
+        FROM scim_groups
+        WHERE organization_id = $1 AND display_name = $2 AND ($3::VARCHAR IS NULL OR id <> $3)
+);
+"#,
+        )
+        .bind(organization_id)
+        .bind(display_name)
+        .bind(exclude_group_id)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(existed)
+    }
+
+    pub async fn create_group(&self, organization_id: &str, input: &ScimGroupInput) -> Result<String, AppError> {
+        let group_id = self.tsid_provider.gen().to_string();
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO scim_groups (id, organization_id, display_name, external_id, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $5);
+"#,
+        )
+        .bind(&group_id)
+        .bind(organization_id)
+        .bind(&input.display_name)
+        .bind(&input.external_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Self::insert_members(&mut tx, &group_id, &input.member_ids, now).await?;
+
+        tx.commit().await?;
+
+        Ok(group_id)
+    }
+
+    pub async fn update_group(&self, group_id: &str, input: &ScimGroupInput) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+UPDATE scim_groups
+    SET display_name = $2, external_id = $3, updated_at = $4
+    WHERE id = $1;
+"#,
+        )
+        .bind(group_id)
+        .bind(&input.display_name)
+        .bind(&input.external_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        sqlx::query("DELETE FROM scim_group_members WHERE group_id = $1")
+            .bind(group_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Self::insert_members(&mut tx, group_id, &input.member_ids, now).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_group(&self, organization_id: &str, group_id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+DELETE FROM scim_groups
+    WHERE organization_id = $1 AND id = $2;
+"#,
+        )
+        .bind(organization_id)
+        .bind(group_id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    async fn insert_members(tx: &mut Transaction<'_, Postgres>, group_id: &str, user_ids: &[String], now: DateTime<Utc>) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+INSERT INTO scim_group_members (group_id, user_id, created_at)
+    SELECT $1, user_id, $3
+        FROM UNNEST($2::VARCHAR[]) AS user_id
+    ON CONFLICT DO NOTHING;
+"#,
+        )
+        .bind(group_id)
+        .bind(user_ids)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+fn filter_params(filter: Option<&ScimUserFilter>) -> (Option<&str>, Option<&str>) {
+    match filter {
+        Some(ScimUserFilter::UserName(v)) => (Some(v.as_str()), None),
+        Some(ScimUserFilter::ExternalId(v)) => (None, Some(v.as_str())),
+        None => (None, None),
+    }
+}
@@ -0,0 +1,365 @@
+use std::sync::Arc;
+
+use core_base::random_bytes::RandomBytesProvider;
+
+use opxs_base::AppError;
+
+use crate::{
+    organization::OrganizationRepo,
+    shared::{
+        model::{ScimGroup, ScimGroupMember, ScimToken, ScimUser},
+        sha256_hex,
+    },
+};
+
+use super::ScimRepo;
+
+pub enum ScimUserFilter {
+    UserName(String),
+    ExternalId(String),
+}
+
+pub struct ScimUserInput {
+    pub user_name: String,
+    pub external_id: Option<String>,
+    pub name: String,
+    pub active: bool,
+}
+
+pub struct ScimGroupInput {
+    pub display_name: String,
+    pub external_id: Option<String>,
+    pub member_ids: Vec<String>,
+}
+
+pub struct ScimService {
+    pub scim_repo: Arc<ScimRepo>,
+    pub organization_repo: Arc<OrganizationRepo>,
+    pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
+}
+
+impl ScimService {
+    // The raw token is only returned here; the database keeps its digest.
+    pub async fn create_token(&self, organization_id: &str, user_id: &str) -> Result<(ScimToken, String), AppError> {
+        self.ensure_manager(organization_id, user_id).await?;
+
+        let token = hex::encode(self.random_bytes_provider.get_bytes(32));
+        let scim_token = self.scim_repo.create_token(organization_id, &sha256_hex(&token), user_id).await?;
+
+        Ok((scim_token, token))
+    }
+
+    pub async fn get_tokens(&self, organization_id: &str, user_id: &str) -> Result<Vec<ScimToken>, AppError> {
+        self.ensure_manager(organization_id, user_id).await?;
+        self.scim_repo.get_tokens(organization_id).await
+    }
+
+    pub async fn delete_token(&self, organization_id: &str, user_id: &str, token_id: &str) -> Result<(), AppError> {
+        self.ensure_manager(organization_id, user_id).await?;
+        self.scim_repo.delete_token(organization_id, token_id).await
+    }
+
+    // Resolves a SCIM bearer token to the organization it provisions.
+    pub async fn authenticate(&self, token: &str) -> Result<String, AppError> {
+        self.scim_repo.use_token(&sha256_hex(token)).await?.ok_or(AppError::AccessTokenExpired)
+    }
+
+    pub async fn get_users(
+        &self,
+        organization_id: &str,
+        filter: Option<&ScimUserFilter>,
+        start_index: i64,
+        count: i64,
+    ) -> Result<(i64, Vec<ScimUser>), AppError> {
+        let total = self.scim_repo.count_users(organization_id, filter).await?;
+        let users = self.scim_repo.get_users(organization_id, filter, start_index - 1, count).await?;
+        Ok((total, users))
+    }
+
+    pub async fn get_user(&self, organization_id: &str, user_id: &str) -> Result<ScimUser, AppError> {
+        self.scim_repo.get_user(organization_id, user_id).await?.ok_or(AppError::UserNotFound)
+    }
+
+    pub async fn create_user(&self, organization_id: &str, input: &ScimUserInput) -> Result<ScimUser, AppError> {
+        if self.scim_repo.exist_user_name(organization_id, &input.user_name, None).await? {
+            return Err(AppError::Conflict(anyhow::anyhow!("userName is already taken")));
+        }
+
+        let user_id = self.scim_repo.create_user(organization_id, input).await?;
+        self.get_user(organization_id, &user_id).await
+    }
+
+    pub async fn replace_user(&self, organization_id: &str, user_id: &str, input: &ScimUserInput) -> Result<ScimUser, AppError> {
+        self.get_user(organization_id, user_id).await?;
+        if self.scim_repo.exist_user_name(organization_id, &input.user_name, Some(user_id)).await? {
+            return Err(AppError::Conflict(anyhow::anyhow!("userName is already taken")));
+        }
+
+        self.scim_repo.update_user(organization_id, user_id, input).await?;
+        self.get_user(organization_id, user_id).await
+    }
+
+    pub async fn deactivate_user(&self, organization_id: &str, user_id: &str) -> Result<(), AppError> {
+        let user = self.get_user(organization_id, user_id).await?;
+        let input = ScimUserInput {
+            user_name: user.user_name,
+            external_id: user.external_id,
+            name: user.name,
+            active: false,
+        };
+        self.scim_repo.update_user(organization_id, user_id, &input).await
+    }
+
+    pub async fn get_groups(
+        &self,
+        organization_id: &str,
+        display_name: Option<&str>,
+        start_index: i64,
+        count: i64,
+    ) -> Result<(i64, Vec<(ScimGroup, Vec<ScimGroupMember>)>), AppError> {
+        let total = self.scim_repo.count_groups(organization_id, display_name).await?;
+        let groups = self.scim_repo.get_groups(organization_id, display_name, start_index - 1, count).await?;
+        let groups = self.with_members(groups).await?;
+        Ok((total, groups))
+    }
+
+    pub async fn get_group(&self, organization_id: &str, group_id: &str) -> Result<(ScimGroup, Vec<ScimGroupMember>), AppError> {
+        let group = self.scim_repo.get_group(organization_id, group_id).await?.ok_or(AppError::NotFound)?;
+        let mut groups = self.with_members(vec![group]).await?;
+        Ok(groups.remove(0))
+    }
+
+    pub async fn create_group(&self, organization_id: &str, input: &ScimGroupInput) -> Result<(ScimGroup, Vec<ScimGroupMember>), AppError> {
+        if self.scim_repo.exist_display_name(organization_id, &input.display_name, None).await? {
+            return Err(AppError::Conflict(anyhow::anyhow!("displayName is already taken")));
+        }
+        self.ensure_members(organization_id, &input.member_ids).await?;
+
+        let group_id = self.scim_repo.create_group(organization_id, input).await?;
+        self.get_group(organization_id, &group_id).await
+    }
+
+    pub async fn replace_group(
+        &self,
+        organization_id: &str,
+        group_id: &str,
+        input: &ScimGroupInput,
+    ) -> Result<(ScimGroup, Vec<ScimGroupMember>), AppError> {
+        self.get_group(organization_id, group_id).await?;
+        if self
+            .scim_repo
+            .exist_display_name(organization_id, &input.display_name, Some(group_id))
+            .await?
+        {
+            return Err(AppError::Conflict(anyhow::anyhow!("displayName is already taken")));
+        }
+        self.ensure_members(organization_id, &input.member_ids).await?;
+
+        self.scim_repo.update_group(group_id, input).await?;
+        self.get_group(organization_id, group_id).await
+    }
+
+    pub async fn delete_group(&self, organization_id: &str, group_id: &str) -> Result<(), AppError> {
+        self.get_group(organization_id, group_id).await?;
+        self.scim_repo.delete_group(organization_id, group_id).await
+    }
+
+    async fn with_members(&self, groups: Vec<ScimGroup>) -> Result<Vec<(ScimGroup, Vec<ScimGroupMember>)>, AppError> {
+        let group_ids: Vec<String> = groups.iter().map(|g| g.id.clone()).collect();
+        let members = self.scim_repo.get_group_members(&group_ids).await?;
+
+        Ok(groups
+            .into_iter()
+            .map(|g| {
+                let group_members = members.iter().filter(|m| m.group_id == g.id).cloned().collect();
+                (g, group_members)
+            })
+            .collect())
+    }
+
+    // Groups may only contain users this organization provisions.
+    async fn ensure_members(&self, organization_id: &str, user_ids: &[String]) -> Result<(), AppError> {
+        for user_id in user_ids {
+            if self.scim_repo.get_user(organization_id, user_id).await?.is_none() {
+                return Err(AppError::InvalidRequest(anyhow::anyhow!("Unknown member: {}", user_id)));
+            }
+        }
+        Ok(())
+    }
+
+    async fn ensure_manager(&self, organization_id: &str, user_id: &str) -> Result<(), AppError> {
+        match self.organization_repo.get_role(organization_id, user_id).await? {
+            Some(role) if role.can_manage_members() => Ok(()),
+            _ => Err(AppError::Forbidden),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::{
+        shared::{
+            self,
+            model::{UserAuthenticationType, UserRole},
+        },
+        token::TokenRepo,
+        user::UserRepo,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
+        let organization_repo = Arc::new(OrganizationRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        });
+        let scim_service = ScimService {
+            scim_repo: Arc::new(ScimRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: tsid_provider.clone(),
+            }),
+            organization_repo: organization_repo.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+        };
+        let user_repo = UserRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        };
+        let token_repo = TokenRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: tsid_provider.clone(),
+        };
+
+        let now = NaiveDateTime::from_timestamp_opt(0, 0).unwrap_or(NaiveDateTime::MIN);
+        let now: DateTime<Utc> = Utc.from_utc_datetime(&now);
+        let owner_id = "owner_id";
+
+        // create owner and organization
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(owner_id)
+        .bind("owner")
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        let organization = organization_repo.create_organization("org", owner_id).await.unwrap();
+        let other = organization_repo.create_organization("other", owner_id).await.unwrap();
+
+        // tokens
+        assert!(matches!(
+            scim_service.create_token(&organization.id, "stranger").await,
+            Err(AppError::Forbidden)
+        ));
+        let (_, token) = scim_service.create_token(&organization.id, owner_id).await.unwrap();
+        assert_eq!(scim_service.authenticate(&token).await.unwrap(), organization.id);
+        assert!(scim_service.authenticate("unknown").await.is_err());
+
+        // users
+        let input = ScimUserInput {
+            user_name: "alice@example.com".to_string(),
+            external_id: Some("ext-alice".to_string()),
+            name: "Alice".to_string(),
+            active: true,
+        };
+        let alice = scim_service.create_user(&organization.id, &input).await.unwrap();
+        assert_eq!(alice.user_name, "alice@example.com");
+        assert!(alice.active);
+        assert!(matches!(
+            scim_service.create_user(&organization.id, &input).await,
+            Err(AppError::Conflict(_))
+        ));
+
+        let filter = ScimUserFilter::UserName("alice@example.com".to_string());
+        let (total, users) = scim_service.get_users(&organization.id, Some(&filter), 1, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(users[0].id, alice.id);
+        let filter = ScimUserFilter::ExternalId("ext-alice".to_string());
+        assert_eq!(scim_service.get_users(&organization.id, Some(&filter), 1, 10).await.unwrap().0, 1);
+        assert_eq!(scim_service.get_users(&other.id, None, 1, 10).await.unwrap().0, 0);
+        assert!(scim_service.get_user(&other.id, &alice.id).await.is_err());
+
+        // groups
+        let group_input = ScimGroupInput {
+            display_name: "Engineering".to_string(),
+            external_id: None,
+            member_ids: vec![alice.id.clone()],
+        };
+        let (group, members) = scim_service.create_group(&organization.id, &group_input).await.unwrap();
+        assert_eq!(members.len(), 1);
+        let unknown_member = ScimGroupInput {
+            display_name: "Other".to_string(),
+            external_id: None,
+            member_ids: vec![owner_id.to_string()],
+        };
+        assert!(scim_service.create_group(&organization.id, &unknown_member).await.is_err());
+        let (_, members) = scim_service
+            .replace_group(
+                &organization.id,
+                &group.id,
+                &ScimGroupInput {
+                    display_name: "Engineering".to_string(),
+                    external_id: None,
+                    member_ids: vec![],
+                },
+            )
+            .await
+            .unwrap();
+        assert!(members.is_empty());
+        scim_service.delete_group(&organization.id, &group.id).await.unwrap();
+        assert_eq!(scim_service.get_groups(&organization.id, None, 1, 10).await.unwrap().0, 0);
+
+        // deactivation suspends the account and its sessions
+        token_repo
+            .create_token(&alice.id, "hash", &(Utc::now() + Duration::days(1)))
+            .await
+            .unwrap();
+        scim_service.deactivate_user(&organization.id, &alice.id).await.unwrap();
+        assert!(!scim_service.get_user(&organization.id, &alice.id).await.unwrap().active);
+        assert!(user_repo.get_user(&alice.id).await.is_err());
+        assert!(token_repo.get_session("hash").await.is_err());
+        assert!(matches!(
+            token_repo.create_token(&alice.id, "hash", &(Utc::now() + Duration::days(1))).await,
+            Err(AppError::UserSuspended)
+        ));
+
+        // and replacing with active restores it
+        let alice = scim_service
+            .replace_user(
+                &organization.id,
+                &alice.id,
+                &ScimUserInput {
+                    user_name: "alice@example.com".to_string(),
+                    external_id: None,
+                    name: "Alice Liddell".to_string(),
+                    active: true,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(alice.active);
+        assert_eq!(alice.name, "Alice Liddell");
+        assert!(user_repo.get_user(&alice.id).await.is_ok());
+    }
+}
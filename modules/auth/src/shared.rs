@@ -1,5 +1,12 @@
+use ring::digest;
+
 pub mod jwt;
 pub mod kdf;
 pub mod model;
 
 pub const POSTGRES_VERSION: &str = "15.1";
+
+// Hex-encoded SHA-256, for values that are stored and looked up by digest rather than as given.
+pub fn sha256_hex(value: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, value.as_bytes()))
+}
@@ -9,6 +9,19 @@ pub struct Claims {
     pub sub: String,
     pub exp: i64,
     pub iat: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+    // When the user last authenticated interactively; absent on refreshed and delegated tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+}
+
+// RFC 8693 actor claim, naming the admin acting on behalf of `sub`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Actor {
+    pub sub: String,
 }
 
 impl Claims {
@@ -17,15 +30,49 @@ impl Claims {
             sub: sub.to_string(),
             iat: iat.timestamp(),
             exp: exp.timestamp(),
+            org: None,
+            act: None,
+            auth_time: None,
         }
     }
 }
 
 pub fn sign(secret: &str, sub: &str, expires_in: Duration, iat: DateTime<Utc>) -> Result<String, AppError> {
     let exp = iat + expires_in;
+    encode(secret, &Claims::new(sub, iat, exp))
+}
+
+pub fn sign_with_auth_time(secret: &str, sub: &str, auth_time: DateTime<Utc>, expires_in: Duration, iat: DateTime<Utc>) -> Result<String, AppError> {
+    let exp = iat + expires_in;
+    let claims = Claims {
+        auth_time: Some(auth_time.timestamp()),
+        ..Claims::new(sub, iat, exp)
+    };
+    encode(secret, &claims)
+}
+
+pub fn sign_for_organization(secret: &str, sub: &str, organization_id: &str, expires_in: Duration, iat: DateTime<Utc>) -> Result<String, AppError> {
+    let exp = iat + expires_in;
+    let claims = Claims {
+        org: Some(organization_id.to_string()),
+        ..Claims::new(sub, iat, exp)
+    };
+    encode(secret, &claims)
+}
+
+pub fn sign_for_impersonation(secret: &str, sub: &str, actor_sub: &str, expires_in: Duration, iat: DateTime<Utc>) -> Result<String, AppError> {
+    let exp = iat + expires_in;
+    let claims = Claims {
+        act: Some(Actor { sub: actor_sub.to_string() }),
+        ..Claims::new(sub, iat, exp)
+    };
+    encode(secret, &claims)
+}
+
+fn encode(secret: &str, claims: &Claims) -> Result<String, AppError> {
     Ok(jsonwebtoken::encode(
         &Header::default(),
-        &Claims::new(sub, iat, exp),
+        claims,
         &EncodingKey::from_secret(secret.as_bytes()),
     )?)
 }
@@ -7,6 +7,7 @@ use validator::Validate;
 pub struct AuthToken {
     pub expires_in: i32,
     pub access_token: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub refresh_token: String,
 }
 
@@ -15,13 +16,15 @@ pub struct AuthToken {
 pub enum UserAuthenticationType {
     Email,
     Provider,
+    Guest,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
 #[sqlx(type_name = "user_role")]
 pub enum UserRole {
     Admin,
     User,
+    Guest,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Validate, ToSchema)]
@@ -46,3 +49,239 @@ pub struct EmailUser {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "auth_audit_event_type")]
+pub enum AuthAuditEventType {
+    Register,
+    Confirm,
+    Login,
+    Refresh,
+    TokenDelete,
+    Unregister,
+    RoleChange,
+    ImpersonationStart,
+    ImpersonationStop,
+    ImpersonatedRequest,
+    Reauthenticate,
+    SessionRevoke,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "auth_audit_auth_method")]
+pub enum AuthAuditAuthMethod {
+    Email,
+    Google,
+    RefreshToken,
+    AccessToken,
+    DeviceCode,
+    Saml,
+    Guest,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "auth_audit_outcome")]
+pub enum AuthAuditOutcome {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct AuthAuditLog {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub actor_user_id: Option<String>,
+    pub event_type: AuthAuditEventType,
+    pub auth_method: Option<AuthAuditAuthMethod>,
+    pub outcome: AuthAuditOutcome,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub detail: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Invitation {
+    pub id: String,
+    pub code: String,
+    pub inviter_user_id: String,
+    pub email: Option<String>,
+    pub max_uses: i32,
+    pub use_count: i32,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct InvitationRedemption {
+    pub invitation_id: String,
+    pub inviter_user_id: String,
+    pub invitee_user_id: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "organization_role")]
+pub enum OrganizationRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl OrganizationRole {
+    pub fn can_manage_members(&self) -> bool {
+        matches!(self, OrganizationRole::Owner | OrganizationRole::Admin)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct OrganizationMembership {
+    pub organization_id: String,
+    pub organization_name: String,
+    pub role: OrganizationRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct OrganizationMember {
+    pub organization_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub role: OrganizationRole,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct OrganizationInvitation {
+    pub id: String,
+    pub organization_id: String,
+    pub code: String,
+    pub email: String,
+    pub role: OrganizationRole,
+    pub invited_by: String,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "device_authorization_status")]
+pub enum DeviceAuthorizationStatus {
+    Pending,
+    Approved,
+    Denied,
+    Consumed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct DeviceAuthorization {
+    #[serde(skip_serializing)]
+    pub device_code: String,
+    pub user_code: String,
+    pub client_id: Option<String>,
+    pub status: DeviceAuthorizationStatus,
+    #[serde(skip_serializing)]
+    pub user_id: Option<String>,
+    pub interval_seconds: i32,
+    #[serde(skip_serializing)]
+    pub last_polled_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    #[serde(skip_serializing)]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct SamlProvider {
+    pub organization_id: String,
+    pub idp_entity_id: String,
+    pub idp_sso_url: String,
+    pub idp_certificate: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ScimToken {
+    pub id: String,
+    pub organization_id: String,
+    pub created_by: String,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ScimUser {
+    pub id: String,
+    pub user_name: String,
+    pub external_id: Option<String>,
+    pub name: String,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ScimGroup {
+    pub id: String,
+    pub organization_id: String,
+    pub display_name: String,
+    pub external_id: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct ScimGroupMember {
+    pub group_id: String,
+    pub user_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "legal_document_type")]
+pub enum LegalDocumentType {
+    TermsOfService,
+    PrivacyPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct LegalDocument {
+    pub id: String,
+    pub document_type: LegalDocumentType,
+    pub version: String,
+    pub url: String,
+    pub published_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct LegalAcceptance {
+    pub user_id: String,
+    pub document_id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub accepted_at: NaiveDateTime,
+}
+
+// Documents accepted while signing up, written in the same transaction as the new account.
+#[derive(Debug, Clone, Copy)]
+pub struct LegalConsent<'a> {
+    pub document_ids: &'a [String],
+    pub client: &'a ClientInfo,
+}
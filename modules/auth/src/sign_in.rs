@@ -0,0 +1,5 @@
+mod repo;
+mod service;
+
+pub use repo::*;
+pub use service::*;
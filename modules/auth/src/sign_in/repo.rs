@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use core_base::clock::SystemClock;
+
+use opxs_base::AppError;
+
+pub struct SignInRepo {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl SignInRepo {
+    pub async fn exist_device(&self, user_id: &str) -> Result<bool, AppError> {
+        let (existed,): (bool,) = sqlx::query_as(
+            r#"
+SELECT EXISTS (
+    SELECT user_id
+        FROM user_sign_in_devices
+        WHERE user_id = $1
+        LIMIT 1
+);
+"#,
+        )
+        .bind(user_id)
+        .fetch_one(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(existed)
+    }
+
+    // Returns false when the device was already known; its last_seen_at is bumped instead.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_device(
+        &self,
+        user_id: &str,
+        fingerprint: &str,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+        session_id: &str,
+        revoke_code_hash: Option<&str>,
+        revoke_expires_at: &DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+INSERT INTO user_sign_in_devices (user_id, fingerprint, ip_address, user_agent, session_id, revoke_code_hash, revoke_expires_at, first_seen_at, last_seen_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (user_id, fingerprint) DO NOTHING;
+"#,
+        )
+        .bind(user_id)
+        .bind(fingerprint)
+        .bind(ip_address)
+        .bind(user_agent)
+        .bind(session_id)
+        .bind(revoke_code_hash)
+        .bind(revoke_code_hash.map(|_| revoke_expires_at))
+        .bind(now)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() > 0 {
+            return Ok(true);
+        }
+
+        sqlx::query(
+            r#"
+UPDATE user_sign_in_devices
+    SET last_seen_at = $3
+    WHERE user_id = $1 AND fingerprint = $2;
+"#,
+        )
+        .bind(user_id)
+        .bind(fingerprint)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(false)
+    }
+
+    // Forgets the device as well, so signing in from it again is reported as new.
+    pub async fn consume_revoke_code(&self, revoke_code_hash: &str) -> Result<Option<(String, String)>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Option<(String, String)> = sqlx::query_as(
+            r#"
+DELETE FROM user_sign_in_devices
+    WHERE revoke_code_hash = $1 AND revoke_expires_at > $2
+    RETURNING user_id, session_id;
+"#,
+        )
+        .bind(revoke_code_hash)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    // Returns the name, verified email and locale of a user who still wants new sign-in notifications.
+    pub async fn get_notification_target(&self, user_id: &str) -> Result<Option<(String, String, String)>, AppError> {
+        let res: Option<(String, String, String)> = sqlx::query_as(
+            r#"
+SELECT u.name, e.email, u.locale
+    FROM users u
+    JOIN user_auth_emails e on u.id = e.user_id
+    WHERE u.id = $1 AND u.sign_in_notification_enabled = true AND e.email_verified = true;
+"#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res)
+    }
+
+    pub async fn get_notification_enabled(&self, user_id: &str) -> Result<bool, AppError> {
+        let res: Option<(bool,)> = sqlx::query_as(
+            r#"
+SELECT sign_in_notification_enabled
+    FROM users
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        res.map(|(enabled,)| enabled).ok_or(AppError::UserNotFound)
+    }
+
+    pub async fn update_notification_enabled(&self, user_id: &str, enabled: bool) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET sign_in_notification_enabled = $2, updated_at = $3
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .bind(enabled)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
+
+use opxs_base::AppError;
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    shared::{
+        model::{AuthAuditEventType, ClientInfo},
+        sha256_hex,
+    },
+    token::TokenRepo,
+};
+
+use super::SignInRepo;
+
+// Matches the refresh token lifetime, after which the session is gone anyway.
+const REVOKE_EXPIRES_IN_DAYS: i64 = 14;
+
+pub struct NewSignInNotice {
+    pub user_name: String,
+    pub email: String,
+    pub locale: String,
+    pub signed_in_at: DateTime<Utc>,
+    pub revoke_code: String,
+}
+
+pub struct SignInService {
+    pub sign_in_repo: Arc<SignInRepo>,
+    pub token_repo: Arc<TokenRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
+}
+
+impl SignInService {
+    // Remembers the client for this user and returns a notice to mail when it hasn't been seen before.
+    // The very first sign-in of an account has nothing to compare against, so it is never reported.
+    pub async fn record(&self, user_id: &str, session_id: &str, client: &ClientInfo) -> Result<Option<NewSignInNotice>, AppError> {
+        let now = self.system_clock.now();
+
+        let target = match self.sign_in_repo.exist_device(user_id).await? {
+            true => self.sign_in_repo.get_notification_target(user_id).await?,
+            false => None,
+        };
+        let revoke_code = target.as_ref().map(|_| hex::encode(self.random_bytes_provider.get_bytes(32)));
+
+        let created = self
+            .sign_in_repo
+            .create_device(
+                user_id,
+                &fingerprint(client),
+                client.ip_address.as_deref(),
+                client.user_agent.as_deref(),
+                session_id,
+                revoke_code.as_deref().map(sha256_hex).as_deref(),
+                &(now + Duration::days(REVOKE_EXPIRES_IN_DAYS)),
+            )
+            .await?;
+        if !created {
+            return Ok(None);
+        }
+
+        Ok(target.zip(revoke_code).map(|((user_name, email, locale), revoke_code)| NewSignInNotice {
+            user_name,
+            email,
+            locale,
+            signed_in_at: now,
+            revoke_code,
+        }))
+    }
+
+    // Backs the "this wasn't me" link: ends the session that signed in from the unknown device.
+    pub async fn revoke(&self, revoke_code: &str, client: &ClientInfo) -> Result<(), AppError> {
+        let res = self.revoke_sub(revoke_code).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|(user_id, _)| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::SessionRevoke,
+            auth_method: None,
+            client,
+            detail: res.as_ref().ok().map(|(_, session_id)| json!({ "session_id": session_id })),
+        };
+        self.audit_logger.write(event, &res).await?;
+        res.map(|_| ())
+    }
+
+    async fn revoke_sub(&self, revoke_code: &str) -> Result<(String, String), AppError> {
+        let (user_id, session_id) = self
+            .sign_in_repo
+            .consume_revoke_code(&sha256_hex(revoke_code))
+            .await?
+            .ok_or(AppError::InvalidRequest(anyhow::anyhow!("Invalid or expired revoke code")))?;
+
+        self.token_repo.delete_session(&user_id, &session_id).await?;
+
+        Ok((user_id, session_id))
+    }
+
+    pub async fn get_notification_enabled(&self, user_id: &str) -> Result<bool, AppError> {
+        self.sign_in_repo.get_notification_enabled(user_id).await
+    }
+
+    pub async fn update_notification_enabled(&self, user_id: &str, enabled: bool) -> Result<(), AppError> {
+        self.sign_in_repo.update_notification_enabled(user_id, enabled).await
+    }
+}
+
+fn fingerprint(client: &ClientInfo) -> String {
+    let source = format!(
+        "{}\n{}",
+        client.ip_address.as_deref().unwrap_or_default(),
+        client.user_agent.as_deref().unwrap_or_default()
+    );
+    sha256_hex(&source)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, TimeZone};
+
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{
+            self,
+            model::{UserAuthenticationType, UserRole},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let token_repo = Arc::new(TokenRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+            tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+        });
+        let sign_in_service = SignInService {
+            sign_in_repo: Arc::new(SignInRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            token_repo: token_repo.clone(),
+            audit_logger: Arc::new(AuthAuditLogger {
+                audit_repo: Arc::new(AuthAuditRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+                }),
+            }),
+            system_clock: system_clock.clone(),
+            random_bytes_provider: Arc::new(RandomBytesProviderImpl {}),
+        };
+
+        let now = NaiveDateTime::from_timestamp_opt(0, 0).unwrap_or(NaiveDateTime::MIN);
+        let now: DateTime<Utc> = Utc.from_utc_datetime(&now);
+        let user_id = "test_user_id";
+        let user_email = "user@example.com";
+
+        // create user
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, locale, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+"#,
+        )
+        .bind(user_id)
+        .bind("test_user_name")
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind("en")
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+INSERT INTO user_auth_emails (email, normalized_email, user_id, password_hash, salt, email_verified, created_at, updated_at)
+    VALUES ($1, $1, $2, '', '', true, $3, $3)
+"#,
+        )
+        .bind(user_email)
+        .bind(user_id)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+
+        let home = ClientInfo {
+            ip_address: Some("192.0.2.1".to_string()),
+            user_agent: Some("home".to_string()),
+        };
+        let other = ClientInfo {
+            ip_address: Some("198.51.100.1".to_string()),
+            user_agent: Some("other".to_string()),
+        };
+
+        // the first device and repeat sign-ins are not reported
+        assert!(sign_in_service.record(user_id, "session_1", &home).await.unwrap().is_none());
+        assert!(sign_in_service.record(user_id, "session_2", &home).await.unwrap().is_none());
+
+        // a new device is reported, and the link revokes only its session
+        let session_id = token_repo.create_token(user_id, "hash", &(Utc::now() + Duration::days(1))).await.unwrap();
+        let notice = sign_in_service.record(user_id, &session_id, &other).await.unwrap().unwrap();
+        assert_eq!(notice.email, user_email);
+        assert_eq!(notice.locale, "en");
+
+        sign_in_service.revoke(&notice.revoke_code, &other).await.unwrap();
+        assert!(token_repo.get_session("hash").await.is_err());
+        assert!(sign_in_service.revoke(&notice.revoke_code, &other).await.is_err());
+
+        // opted out users are not reported
+        sign_in_service.update_notification_enabled(user_id, false).await.unwrap();
+        assert!(!sign_in_service.get_notification_enabled(user_id).await.unwrap());
+        assert!(sign_in_service.record(user_id, "session_3", &other).await.unwrap().is_none());
+    }
+}
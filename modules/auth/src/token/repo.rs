@@ -3,27 +3,31 @@ use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
-use core_base::clock::SystemClock;
+use core_base::{clock::SystemClock, tsid::TsidProvider};
 
 use opxs_base::AppError;
 
-use crate::shared::model::User;
-
 pub struct TokenRepo {
     pub db: Arc<PgPool>,
     pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
 }
 
 impl TokenRepo {
-    pub async fn create_token(&self, user_id: &str, refresh_token: &str, expires_at: &DateTime<Utc>) -> Result<(), AppError> {
+    pub async fn create_token(&self, user_id: &str, token_hash: &str, expires_at: &DateTime<Utc>) -> Result<String, AppError> {
         let now = self.system_clock.now();
-        sqlx::query(
+        let session_id = self.tsid_provider.gen().to_string();
+        // Suspended users are refused here, which covers every sign-in path at once.
+        let res = sqlx::query(
             r#"
-INSERT INTO refresh_tokens (refresh_token, user_id, expires_at, created_at, updated_at)
-    VALUES ($1, $2, $3, $4, $5);
+INSERT INTO refresh_tokens (id, token_hash, user_id, expires_at, created_at, updated_at)
+    SELECT $1, $2, u.id, $4, $5, $6
+        FROM users u
+        WHERE u.id = $3 AND u.suspended_at IS NULL;
 "#,
         )
-        .bind(refresh_token)
+        .bind(&session_id)
+        .bind(token_hash)
         .bind(user_id)
         .bind(expires_at)
         .bind(now)
@@ -32,7 +36,19 @@ INSERT INTO refresh_tokens (refresh_token, user_id, expires_at, created_at, upda
         .await
         .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
-        Ok(())
+        if res.rows_affected() < 1 {
+            let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(self.db.as_ref())
+                .await
+                .map_err(|e| AppError::UnexpectedError(e.into()))?;
+            return Err(match exists {
+                Some(_) => AppError::UserSuspended,
+                None => AppError::UserNotFound,
+            });
+        }
+
+        Ok(session_id)
     }
 
     pub async fn delete_token(&self, user_id: &str) -> Result<(), AppError> {
@@ -50,45 +66,71 @@ DELETE FROM refresh_tokens
         Ok(())
     }
 
-    pub async fn update_token(&self, refresh_token: &str, expires_at: &DateTime<Utc>) -> Result<(), AppError> {
-        let now = self.system_clock.now();
+    pub async fn delete_session(&self, user_id: &str, session_id: &str) -> Result<(), AppError> {
         sqlx::query(
             r#"
+DELETE FROM refresh_tokens
+    WHERE user_id = $1 AND id = $2;
+"#,
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    // Swaps in the new hash only if the old one is still current, so a refresh token can be redeemed once.
+    pub async fn rotate_token(
+        &self,
+        session_id: &str,
+        current_token_hash: &str,
+        new_token_hash: &str,
+        expires_at: &DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+        let res = sqlx::query(
+            r#"
 UPDATE refresh_tokens
-    SET expires_at = $2, updated_at = $3
-    WHERE refresh_token = $1;
+    SET token_hash = $3, expires_at = $4, updated_at = $5
+    WHERE id = $1 AND token_hash = $2;
 "#,
         )
-        .bind(refresh_token)
+        .bind(session_id)
+        .bind(current_token_hash)
+        .bind(new_token_hash)
         .bind(expires_at)
         .bind(now)
         .execute(self.db.as_ref())
         .await
         .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
+        if res.rows_affected() == 0 {
+            return Err(AppError::RefreshTokenNotFound);
+        }
+
         Ok(())
     }
 
-    pub async fn get_user_id(&self, refresh_token: &str) -> Result<String, AppError> {
+    // Returns the session id and user id for a live refresh token.
+    pub async fn get_session(&self, token_hash: &str) -> Result<(String, String), AppError> {
         let now = self.system_clock.now();
-        let user: Option<User> = sqlx::query_as(
+        let session: Option<(String, String)> = sqlx::query_as(
             r#"
-SELECT u.*
-    FROM users u
-    JOIN refresh_tokens t on t.user_id = u.id
-    WHERE t.refresh_token = $1 AND expires_at > $2;
+SELECT t.id, t.user_id
+    FROM refresh_tokens t
+    JOIN users u on t.user_id = u.id
+    WHERE t.token_hash = $1 AND t.expires_at > $2 AND u.suspended_at IS NULL;
 "#,
         )
-        .bind(refresh_token)
+        .bind(token_hash)
         .bind(now)
         .fetch_optional(self.db.as_ref())
         .await
         .map_err(|e| AppError::UnexpectedError(e.into()))?;
 
-        if user.is_none() {
-            return Err(AppError::RefreshTokenNotFound);
-        }
-
-        Ok(user.unwrap().id)
+        session.ok_or(AppError::RefreshTokenNotFound)
     }
 }
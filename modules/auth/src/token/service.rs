@@ -1,48 +1,115 @@
 use std::sync::Arc;
 
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 use core_base::{clock::SystemClock, random_bytes::RandomBytesProvider};
 
 use opxs_base::{AppError, JwtConfig};
 
-use crate::shared::{jwt, model::AuthToken};
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    shared::{
+        jwt,
+        model::{AuthAuditAuthMethod, AuthAuditEventType, AuthToken, ClientInfo},
+        sha256_hex,
+    },
+};
 
 use super::TokenRepo;
 
+const ELEVATED_TOKEN_EXPIRES_IN_MINUTES: i64 = 10;
+
 pub struct TokenService {
     pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
     pub random_bytes_provider: Arc<dyn RandomBytesProvider + Send + Sync>,
     pub jwt_conf: JwtConfig,
     pub token_repo: Arc<TokenRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
 }
 
 impl TokenService {
-    pub async fn create(&self, user_id: &str) -> Result<AuthToken, AppError> {
+    // For interactive logins: the access token records the login as its auth_time. Also returns the session id.
+    pub async fn create(&self, user_id: &str) -> Result<(String, AuthToken), AppError> {
+        let now = self.system_clock.now();
+        self.create_sub(user_id, Some(now)).await
+    }
+
+    // Issued without the user authenticating on this client, so it can't satisfy step-up checks.
+    pub async fn create_delegated(&self, user_id: &str) -> Result<AuthToken, AppError> {
+        self.create_sub(user_id, None).await.map(|(_, token)| token)
+    }
+
+    async fn create_sub(&self, user_id: &str, auth_time: Option<DateTime<Utc>>) -> Result<(String, AuthToken), AppError> {
         let now = self.system_clock.now();
 
         let sub = user_id.to_string();
         let expires_in = Duration::days(14);
-        let access_token = jwt::sign(&self.jwt_conf.secret.current, &sub, expires_in, now)?;
+        let access_token = match auth_time {
+            Some(auth_time) => jwt::sign_with_auth_time(&self.jwt_conf.secret.current, &sub, auth_time, expires_in, now)?,
+            None => jwt::sign(&self.jwt_conf.secret.current, &sub, expires_in, now)?,
+        };
         let refresh_token = hex::encode(self.random_bytes_provider.get_bytes(32));
         let expires_at = now + expires_in;
 
-        self.token_repo.create_token(user_id, &refresh_token, &expires_at).await?;
+        // Only the digest is stored, so a leaked table can't be replayed as live sessions.
+        let session_id = self.token_repo.create_token(user_id, &sha256_hex(&refresh_token), &expires_at).await?;
+
+        Ok((
+            session_id,
+            AuthToken {
+                expires_in: expires_in.num_seconds() as i32,
+                access_token,
+                refresh_token,
+            },
+        ))
+    }
+
+    // A short-lived access token minted right after re-authentication, for sensitive operations.
+    pub fn create_elevated(&self, user_id: &str) -> Result<AuthToken, AppError> {
+        let now = self.system_clock.now();
+
+        let expires_in = Duration::minutes(ELEVATED_TOKEN_EXPIRES_IN_MINUTES);
+        let access_token = jwt::sign_with_auth_time(&self.jwt_conf.secret.current, user_id, now, expires_in, now)?;
 
         Ok(AuthToken {
             expires_in: expires_in.num_seconds() as i32,
             access_token,
-            refresh_token,
+            refresh_token: String::new(),
         })
     }
 
-    pub async fn delete(&self, user_id: &str) -> Result<(), AppError> {
-        self.token_repo.delete_token(user_id).await
+    pub async fn delete(&self, user_id: &str, client: &ClientInfo) -> Result<(), AppError> {
+        let res = self.token_repo.delete_token(user_id).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::TokenDelete,
+            auth_method: Some(AuthAuditAuthMethod::AccessToken),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
     }
 
-    pub async fn refresh(&self, refresh_token: &str) -> Result<AuthToken, AppError> {
+    pub async fn refresh(&self, refresh_token: &str, client: &ClientInfo) -> Result<AuthToken, AppError> {
+        let res = self.refresh_sub(refresh_token).await;
+        let event = AuthAuditEvent {
+            user_id: res.as_ref().ok().map(|(user_id, _)| user_id.as_str()),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Refresh,
+            auth_method: Some(AuthAuditAuthMethod::RefreshToken),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res.map(|(_, token)| token)
+    }
+
+    async fn refresh_sub(&self, refresh_token: &str) -> Result<(String, AuthToken), AppError> {
         let now = self.system_clock.now();
-        let user_id = self.token_repo.get_user_id(refresh_token).await?;
+        let current_token_hash = sha256_hex(refresh_token);
+        let (session_id, user_id) = self.token_repo.get_session(&current_token_hash).await?;
 
         let sub = user_id.to_string();
         let expires_in = Duration::days(14);
@@ -50,30 +117,35 @@ impl TokenService {
         let refresh_token = hex::encode(self.random_bytes_provider.get_bytes(32));
         let expires_at = now + expires_in;
 
-        self.token_repo.update_token(&refresh_token, &expires_at).await?;
+        self.token_repo
+            .rotate_token(&session_id, &current_token_hash, &sha256_hex(&refresh_token), &expires_at)
+            .await?;
 
-        Ok(AuthToken {
-            expires_in: expires_in.num_seconds() as i32,
-            access_token,
-            refresh_token,
-        })
+        Ok((
+            user_id,
+            AuthToken {
+                expires_in: expires_in.num_seconds() as i32,
+                access_token,
+                refresh_token,
+            },
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::{DateTime, Duration, NaiveDateTime, TimeZone};
-    use sqlx::postgres::PgPoolOptions;
+    use chrono::{DateTime, NaiveDateTime, TimeZone};
 
-    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl};
-    use core_migration::postgres::PostgresMigrator;
-    use core_testkit::containers::postgres::PostgresContainer;
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
 
     use opxs_base::JwtSecretConfig;
 
-    use crate::shared::{
-        self,
-        model::{UserAuthenticationType, UserRole},
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{
+            self,
+            model::{UserAuthenticationType, UserRole},
+        },
     };
 
     use super::*;
@@ -81,22 +153,8 @@ mod tests {
     #[tokio::test]
     async fn simple_test() {
         let docker = testcontainers::clients::Cli::default();
-        let container = PostgresContainer::new(&docker, shared::POSTGRES_VERSION);
-
-        let db = Arc::new(
-            PgPoolOptions::new()
-                .max_connections(100)
-                .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
-                .connect(&container.connection_string)
-                .await
-                .unwrap(),
-        );
-
-        let migrations_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../conf/migrations");
-        let migrator = PostgresMigrator::new(&container.connection_string, migrations_path, "opxs-api", "")
-            .await
-            .unwrap();
-        migrator.migrate().await.unwrap();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
 
         let system_clock = Arc::new(SystemClockUtc {});
         let token_service = TokenService {
@@ -111,8 +169,17 @@ mod tests {
             token_repo: Arc::new(TokenRepo {
                 db: db.clone(),
                 system_clock: system_clock.clone(),
+                tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+            }),
+            audit_logger: Arc::new(AuthAuditLogger {
+                audit_repo: Arc::new(AuthAuditRepo {
+                    db: db.clone(),
+                    system_clock: system_clock.clone(),
+                    tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+                }),
             }),
         };
+        let client = ClientInfo::default();
 
         let now = NaiveDateTime::from_timestamp_opt(0, 0).unwrap_or(NaiveDateTime::MIN);
         let now: DateTime<Utc> = Utc.from_utc_datetime(&now);
@@ -136,14 +203,38 @@ INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
         .await
         .unwrap();
 
-        let token = token_service.create(user_id).await.unwrap();
+        let (_, token) = token_service.create(user_id).await.unwrap();
+        let claims = jwt::verify("current", &token.access_token, Utc::now()).unwrap();
+        assert!(claims.auth_time.is_some());
+
+        let stored: (String,) = sqlx::query_as("SELECT token_hash FROM refresh_tokens WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(db.as_ref())
+            .await
+            .unwrap();
+        assert_ne!(stored.0, token.refresh_token);
+
+        let old_refresh_token = token.refresh_token;
+        let token = token_service.refresh(&old_refresh_token, &client).await.unwrap();
+        assert!(token_service.refresh(&old_refresh_token, &client).await.is_err());
+
+        let claims = jwt::verify("current", &token.access_token, Utc::now()).unwrap();
+        assert!(claims.auth_time.is_none());
 
-        let token = token_service.refresh(&token.refresh_token).await.unwrap();
+        token_service.delete(user_id, &client).await.unwrap();
 
-        token_service.delete(user_id).await.unwrap();
+        assert!(token_service.refresh(&token.refresh_token, &client).await.is_err());
 
-        assert!(token_service.refresh(&token.refresh_token).await.is_err());
+        token_service.delete(user_id, &client).await.unwrap();
 
-        token_service.delete(user_id).await.unwrap();
+        // unknown and suspended users get no session
+        assert!(matches!(token_service.create("unknown_user_id").await, Err(AppError::UserNotFound)));
+        sqlx::query("UPDATE users SET suspended_at = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(now)
+            .execute(db.as_ref())
+            .await
+            .unwrap();
+        assert!(matches!(token_service.create(user_id).await, Err(AppError::UserSuspended)));
     }
 }
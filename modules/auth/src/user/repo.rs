@@ -1,13 +1,17 @@
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
+use core_base::clock::SystemClock;
+
 use opxs_base::AppError;
 
 use crate::shared::model::User;
 
 pub struct UserRepo {
     pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
 }
 
 impl UserRepo {
@@ -16,7 +20,7 @@ impl UserRepo {
             r#"
 SELECT *
     FROM users
-    WHERE id = $1;
+    WHERE id = $1 AND deletion_scheduled_at IS NULL AND suspended_at IS NULL;
 "#,
         )
         .bind(user_id)
@@ -30,4 +34,231 @@ SELECT *
 
         Ok(user.unwrap())
     }
+
+    pub async fn schedule_deletion(&self, user_id: &str, scheduled_at: &DateTime<Utc>) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET deletion_requested_at = $2, deletion_scheduled_at = $3, updated_at = $2
+    WHERE id = $1 AND deletion_scheduled_at IS NULL;
+"#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .bind(scheduled_at)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::UserNotFound);
+        }
+
+        sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn cancel_deletion(&self, user_id: &str) -> Result<bool, AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET deletion_requested_at = NULL, deletion_scheduled_at = NULL, updated_at = $2
+    WHERE id = $1 AND deletion_scheduled_at IS NOT NULL AND deletion_scheduled_at > $2;
+"#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    pub async fn get_deletion_expired_user_ids(&self, limit: i64) -> Result<Vec<String>, AppError> {
+        let now = self.system_clock.now();
+
+        let res: Vec<(String,)> = sqlx::query_as(
+            r#"
+SELECT id
+    FROM users
+    WHERE deletion_scheduled_at <= $1
+    ORDER BY deletion_scheduled_at
+    LIMIT $2;
+"#,
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub async fn get_announcement_enabled(&self, user_id: &str) -> Result<bool, AppError> {
+        let res: Option<(bool,)> = sqlx::query_as(
+            r#"
+SELECT announcement_email_enabled
+    FROM users
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        res.map(|(enabled,)| enabled).ok_or(AppError::UserNotFound)
+    }
+
+    pub async fn update_announcement_enabled(&self, user_id: &str, enabled: bool) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE users
+    SET announcement_email_enabled = $2, updated_at = $3
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .bind(enabled)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_locale(&self, user_id: &str) -> Result<String, AppError> {
+        let res: Option<(String,)> = sqlx::query_as(
+            r#"
+SELECT locale
+    FROM users
+    WHERE id = $1;
+"#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        res.map(|(locale,)| locale).ok_or(AppError::UserNotFound)
+    }
+
+    // With keep_existing, a locale the user already has is left as it is.
+    pub async fn update_locale(&self, user_id: &str, locale: &str, keep_existing: bool) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+UPDATE users
+    SET locale = $2, updated_at = $3
+    WHERE id = $1 AND (NOT $4 OR locale = '');
+"#,
+        )
+        .bind(user_id)
+        .bind(locale)
+        .bind(now)
+        .bind(keep_existing)
+        .execute(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    // The locale of whoever owns the verified address, or None when it isn't a user yet.
+    pub async fn get_locale_by_email(&self, email: &str) -> Result<Option<String>, AppError> {
+        let res: Option<(String,)> = sqlx::query_as(
+            r#"
+SELECT u.locale
+    FROM users u
+    JOIN user_auth_emails e on u.id = e.user_id
+    WHERE LOWER(e.email) = LOWER($1) AND e.email_verified = true;
+"#,
+        )
+        .bind(email)
+        .fetch_optional(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.map(|(locale,)| locale))
+    }
+
+    // Verified addresses of active users who opted in to announcements.
+    pub async fn get_announcement_email_addresses(&self) -> Result<Vec<String>, AppError> {
+        let res: Vec<(String,)> = sqlx::query_as(
+            r#"
+SELECT e.email
+    FROM users u
+    JOIN user_auth_emails e on u.id = e.user_id
+    WHERE u.announcement_email_enabled = true AND u.deletion_scheduled_at IS NULL AND u.suspended_at IS NULL
+        AND e.email_verified = true
+    ORDER BY u.id;
+"#,
+        )
+        .fetch_all(self.db.as_ref())
+        .await
+        .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        Ok(res.into_iter().map(|(email,)| email).collect())
+    }
+
+    // The schedule is checked again by the final DELETE, so a deletion cancelled after the targets were listed rolls back
+    // and returns false.
+    pub async fn delete_user(&self, user_id: &str) -> Result<bool, AppError> {
+        let now = self.system_clock.now();
+
+        let mut tx = self.db.begin().await?;
+
+        let queries = vec![
+            sqlx::query("DELETE FROM user_auth_emails WHERE user_id = $1").bind(user_id),
+            sqlx::query("DELETE FROM user_auth_providers WHERE user_id = $1").bind(user_id),
+            sqlx::query("DELETE FROM refresh_tokens WHERE user_id = $1").bind(user_id),
+            sqlx::query("DELETE FROM user_sign_in_devices WHERE user_id = $1").bind(user_id),
+            sqlx::query("DELETE FROM organization_members WHERE user_id = $1").bind(user_id),
+            sqlx::query("DELETE FROM user_guests WHERE user_id = $1").bind(user_id),
+            sqlx::query("DELETE FROM legal_acceptances WHERE user_id = $1").bind(user_id),
+        ];
+
+        for query in queries {
+            query.execute(&mut tx).await.map_err(|e| AppError::UnexpectedError(e.into()))?;
+        }
+
+        let res = sqlx::query("DELETE FROM users WHERE id = $1 AND deletion_scheduled_at IS NOT NULL AND deletion_scheduled_at <= $2")
+            .bind(user_id)
+            .bind(now)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| AppError::UnexpectedError(e.into()))?;
+
+        if res.rows_affected() < 1 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
 }
@@ -1,13 +1,23 @@
 use std::sync::Arc;
 
-use opxs_base::AppError;
+use chrono::{Duration, Utc};
 
-use crate::shared::model::User;
+use core_base::clock::SystemClock;
+
+use opxs_base::{AppError, UnregisterConfig};
+
+use crate::{
+    audit::{AuthAuditEvent, AuthAuditLogger},
+    shared::model::{AuthAuditAuthMethod, AuthAuditEventType, ClientInfo, User},
+};
 
 use super::UserRepo;
 
 pub struct UserService {
     pub user_repo: Arc<UserRepo>,
+    pub audit_logger: Arc<AuthAuditLogger>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub unregister_conf: UnregisterConfig,
 }
 
 impl UserService {
@@ -15,4 +25,189 @@ impl UserService {
         let user = self.user_repo.get_user(user_id).await?;
         Ok(user)
     }
+
+    pub async fn unregister(&self, user_id: &str, client: &ClientInfo) -> Result<(), AppError> {
+        let res = self.unregister_sub(user_id).await;
+        let event = AuthAuditEvent {
+            user_id: Some(user_id),
+            actor_user_id: None,
+            event_type: AuthAuditEventType::Unregister,
+            auth_method: Some(AuthAuditAuthMethod::AccessToken),
+            client,
+            detail: None,
+        };
+        self.audit_logger.write(event, &res).await?;
+        res
+    }
+
+    async fn unregister_sub(&self, user_id: &str) -> Result<(), AppError> {
+        let now = self.system_clock.now();
+        let scheduled_at = now + Duration::days(self.unregister_conf.grace_period_days);
+        self.user_repo.schedule_deletion(user_id, &scheduled_at).await?;
+        Ok(())
+    }
+
+    pub async fn get_announcement_enabled(&self, user_id: &str) -> Result<bool, AppError> {
+        self.user_repo.get_announcement_enabled(user_id).await
+    }
+
+    pub async fn update_announcement_enabled(&self, user_id: &str, enabled: bool) -> Result<(), AppError> {
+        self.user_repo.update_announcement_enabled(user_id, enabled).await
+    }
+
+    pub async fn get_locale(&self, user_id: &str) -> Result<String, AppError> {
+        self.user_repo.get_locale(user_id).await
+    }
+
+    pub async fn update_locale(&self, user_id: &str, locale: &str) -> Result<(), AppError> {
+        self.user_repo.update_locale(user_id, locale, false).await
+    }
+
+    // Seeds the locale from the client that completed sign-up, without overriding one the user chose.
+    pub async fn init_locale(&self, user_id: &str, locale: &str) -> Result<(), AppError> {
+        if locale.is_empty() {
+            return Ok(());
+        }
+        self.user_repo.update_locale(user_id, locale, true).await
+    }
+
+    // Empty when the address has no account, so the email goes out in the default locale.
+    pub async fn get_locale_by_email(&self, email: &str) -> Result<String, AppError> {
+        Ok(self.user_repo.get_locale_by_email(email).await?.unwrap_or_default())
+    }
+
+    pub async fn get_announcement_email_addresses(&self) -> Result<Vec<String>, AppError> {
+        self.user_repo.get_announcement_email_addresses().await
+    }
+
+    pub async fn get_purge_target_user_ids(&self, limit: i64) -> Result<Vec<String>, AppError> {
+        self.user_repo.get_deletion_expired_user_ids(limit).await
+    }
+
+    pub async fn purge(&self, user_id: &str) -> Result<bool, AppError> {
+        self.user_repo.delete_user(user_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use crate::{
+        audit::AuthAuditRepo,
+        shared::{
+            self,
+            model::{UserAuthenticationType, UserRole},
+        },
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, shared::POSTGRES_VERSION).await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let user_repo = Arc::new(UserRepo {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let audit_logger = Arc::new(AuthAuditLogger {
+            audit_repo: Arc::new(AuthAuditRepo {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+                tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+            }),
+        });
+        let client = ClientInfo::default();
+
+        let user_id = "test_user_id";
+        let now = system_clock.now();
+
+        // create user
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6)
+"#,
+        )
+        .bind(user_id)
+        .bind("test_user_name")
+        .bind(UserAuthenticationType::Email)
+        .bind(UserRole::User)
+        .bind(now)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+
+        // unregister with grace period
+        let user_service = UserService {
+            user_repo: user_repo.clone(),
+            audit_logger: audit_logger.clone(),
+            system_clock: system_clock.clone(),
+            unregister_conf: UnregisterConfig { grace_period_days: 30 },
+        };
+        user_service.unregister(user_id, &client).await.unwrap();
+        assert!(matches!(user_service.get_user(user_id).await, Err(AppError::UserNotFound)));
+        assert!(user_service.get_purge_target_user_ids(100).await.unwrap().is_empty());
+
+        // restore
+        assert!(user_repo.cancel_deletion(user_id).await.unwrap());
+        assert!(user_service.get_user(user_id).await.is_ok());
+
+        // a restored user is left alone even if it was listed for purge before
+        assert!(!user_service.purge(user_id).await.unwrap());
+        assert!(user_service.get_user(user_id).await.is_ok());
+
+        // announcements are opt-in and go to verified addresses only
+        sqlx::query(
+            r#"
+INSERT INTO user_auth_emails (email, normalized_email, user_id, password_hash, salt, email_verified, created_at, updated_at)
+    VALUES ($1, $1, $2, '', '', true, $3, $3)
+"#,
+        )
+        .bind("test@example.com")
+        .bind(user_id)
+        .bind(now)
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        assert!(!user_service.get_announcement_enabled(user_id).await.unwrap());
+        assert!(user_service.get_announcement_email_addresses().await.unwrap().is_empty());
+        user_service.update_announcement_enabled(user_id, true).await.unwrap();
+        assert_eq!(
+            user_service.get_announcement_email_addresses().await.unwrap(),
+            vec!["test@example.com".to_string()]
+        );
+
+        // the sign-up locale is only a default, and an explicit choice wins
+        assert_eq!(user_service.get_locale_by_email("other@example.com").await.unwrap(), "");
+        user_service.init_locale(user_id, "en-US").await.unwrap();
+        user_service.init_locale(user_id, "fr").await.unwrap();
+        assert_eq!(user_service.get_locale_by_email("Test@example.com").await.unwrap(), "en-US");
+        user_service.update_locale(user_id, "ja").await.unwrap();
+        assert_eq!(user_service.get_locale(user_id).await.unwrap(), "ja");
+
+        // unregister without grace period
+        let user_service = UserService {
+            user_repo: user_repo.clone(),
+            audit_logger,
+            system_clock,
+            unregister_conf: UnregisterConfig { grace_period_days: 0 },
+        };
+        user_service.unregister(user_id, &client).await.unwrap();
+        let user_ids = user_service.get_purge_target_user_ids(100).await.unwrap();
+        assert_eq!(user_ids, vec![user_id.to_string()]);
+
+        // an expired schedule can no longer be cancelled
+        assert!(!user_repo.cancel_deletion(user_id).await.unwrap());
+
+        // purge
+        assert!(user_service.purge(user_id).await.unwrap());
+        assert!(user_service.get_purge_target_user_ids(100).await.unwrap().is_empty());
+        assert!(!user_repo.cancel_deletion(user_id).await.unwrap());
+    }
 }
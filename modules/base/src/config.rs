@@ -9,6 +9,7 @@ pub struct AppConfig {
     pub auth: AuthConfig,
     pub email: EmailConfig,
     pub image_convert: ImageConvertConfig,
+    pub outbox: OutboxConfig,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,6 +20,19 @@ pub struct PostgresConfig {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WebConfig {
     pub origin: String,
+    pub cookie: CookieConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieConfig {
+    pub secret: CookieSecretConfig,
+}
+
+// Hex-encoded keys of at least 64 bytes. `current` signs new cookies, `previous` is still accepted while rotating.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CookieSecretConfig {
+    pub current: Option<String>,
+    pub previous: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,6 +45,10 @@ pub struct JwtSecretConfig {
 pub struct AuthConfig {
     pub jwt: JwtConfig,
     pub google: GoogleAuthConfig,
+    pub unregister: UnregisterConfig,
+    pub register_policy: RegisterPolicyConfig,
+    pub invitation: InvitationConfig,
+    pub guest: GuestConfig,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -44,10 +62,69 @@ pub struct GoogleAuthConfig {
     pub client_secret: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnregisterConfig {
+    pub grace_period_days: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvitationConfig {
+    pub required: bool,
+    pub expires_in_days: i64,
+    pub max_uses: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestConfig {
+    pub convert_quota: i32,
+    pub retention_days: i64,
+    // Guest sign-ins per client IP and hour; each one can create a user.
+    pub login_limit_per_hour: i32,
+    // Uploads without any session per client IP and hour; they aren't counted against a quota otherwise.
+    pub anonymous_convert_limit_per_hour: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterPolicyConfig {
+    pub email: EmailPolicyConfig,
+    pub password: PasswordPolicyConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailPolicyConfig {
+    pub allowed_domains: Vec<String>,
+    pub denied_domains: Vec<String>,
+    pub deny_disposable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub min_score: u8,
+    pub deny_breached: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EmailConfig {
     pub from_email_address: String,
     pub ses: SesConfig,
+    pub retry: EmailRetryConfig,
+    pub bulk: EmailBulkConfig,
+    pub transport: EmailTransportConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmailTransportConfig {
+    Ses,
+    Smtp(SmtpConfig),
+    // Stores messages in the captured_emails table instead of delivering them.
+    Local,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpConfig {
+    pub url: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -55,6 +132,17 @@ pub struct SesConfig {
     pub configuration_set_name: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailRetryConfig {
+    pub max_retry_count: i32,
+    pub base_delay_seconds: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailBulkConfig {
+    pub batch_size: i32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ImageConvertConfig {
     pub s3: S3Config,
@@ -65,6 +153,13 @@ pub struct S3Config {
     pub bucket: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxConfig {
+    pub relay_interval_seconds: i64,
+    pub relay_batch_size: i64,
+    pub base_delay_seconds: i64,
+}
+
 impl AppConfig {
     pub async fn load(application_name: &str, mode: &RunMode) -> anyhow::Result<Self> {
         let secret_reader = Box::new(SecretsReaderImpl {
@@ -78,6 +173,10 @@ impl AppConfig {
         let jwt_secret_retired = secret_value.get_str("jwt_secret_retired")?;
         let auth_google_client_id = secret_value.get_str("auth_google_client_id")?;
         let auth_google_client_secret = secret_value.get_str("auth_google_client_secret")?;
+        let cookie_secret_current = secret_value.get_opt_str("cookie_secret_current");
+        let cookie_secret_previous = secret_value.get_opt_str("cookie_secret_previous");
+        // Only read in local mode, e.g. to deliver to a MailHog instance instead of capturing.
+        let local_smtp_url = secret_value.get_opt_str("local_smtp_url");
 
         match mode {
             RunMode::Local => {
@@ -94,6 +193,12 @@ impl AppConfig {
                     postgres: PostgresConfig { url: postgres_url },
                     web: WebConfig {
                         origin: "https://localhost.omnius-labs.com/".to_string(),
+                        cookie: CookieConfig {
+                            secret: CookieSecretConfig {
+                                current: cookie_secret_current,
+                                previous: cookie_secret_previous,
+                            },
+                        },
                     },
                     auth: AuthConfig {
                         jwt: JwtConfig {
@@ -106,18 +211,54 @@ impl AppConfig {
                             client_id: auth_google_client_id,
                             client_secret: auth_google_client_secret,
                         },
+                        unregister: UnregisterConfig { grace_period_days: 30 },
+                        register_policy: RegisterPolicyConfig {
+                            email: EmailPolicyConfig {
+                                allowed_domains: vec![],
+                                denied_domains: vec![],
+                                deny_disposable: true,
+                            },
+                            password: PasswordPolicyConfig {
+                                min_length: 8,
+                                max_length: 128,
+                                min_score: 2,
+                                deny_breached: true,
+                            },
+                        },
+                        invitation: InvitationConfig {
+                            required: false,
+                            expires_in_days: 7,
+                            max_uses: 1,
+                        },
+                        guest: GuestConfig {
+                            convert_quota: 10,
+                            retention_days: 30,
+                            login_limit_per_hour: 20,
+                            anonymous_convert_limit_per_hour: 10,
+                        },
                     },
                     email: EmailConfig {
                         from_email_address: "Opxs <no-reply@opxs-dev.omnius-labs.com>".to_string(),
                         ses: SesConfig {
                             configuration_set_name: "opxs-dev".to_string(),
                         },
+                        retry: EmailRetryConfig {
+                            max_retry_count: 5,
+                            base_delay_seconds: 30,
+                        },
+                        bulk: EmailBulkConfig { batch_size: 50 },
+                        transport: local_smtp_url.map_or(EmailTransportConfig::Local, |url| EmailTransportConfig::Smtp(SmtpConfig { url })),
                     },
                     image_convert: ImageConvertConfig {
                         s3: S3Config {
                             bucket: "opxs.v1.dev.image-convert".to_string(),
                         },
                     },
+                    outbox: OutboxConfig {
+                        relay_interval_seconds: 10,
+                        relay_batch_size: 100,
+                        base_delay_seconds: 10,
+                    },
                 })
             }
             RunMode::Dev => {
@@ -134,6 +275,12 @@ impl AppConfig {
                     postgres: PostgresConfig { url: postgres_url },
                     web: WebConfig {
                         origin: "https://opxs-dev.omnius-labs.com/".to_string(),
+                        cookie: CookieConfig {
+                            secret: CookieSecretConfig {
+                                current: cookie_secret_current,
+                                previous: cookie_secret_previous,
+                            },
+                        },
                     },
                     auth: AuthConfig {
                         jwt: JwtConfig {
@@ -146,18 +293,54 @@ impl AppConfig {
                             client_id: auth_google_client_id,
                             client_secret: auth_google_client_secret,
                         },
+                        unregister: UnregisterConfig { grace_period_days: 30 },
+                        register_policy: RegisterPolicyConfig {
+                            email: EmailPolicyConfig {
+                                allowed_domains: vec![],
+                                denied_domains: vec![],
+                                deny_disposable: true,
+                            },
+                            password: PasswordPolicyConfig {
+                                min_length: 8,
+                                max_length: 128,
+                                min_score: 2,
+                                deny_breached: true,
+                            },
+                        },
+                        invitation: InvitationConfig {
+                            required: false,
+                            expires_in_days: 7,
+                            max_uses: 1,
+                        },
+                        guest: GuestConfig {
+                            convert_quota: 10,
+                            retention_days: 30,
+                            login_limit_per_hour: 20,
+                            anonymous_convert_limit_per_hour: 10,
+                        },
                     },
                     email: EmailConfig {
                         from_email_address: "Opxs <no-reply@opxs-dev.omnius-labs.com>".to_string(),
                         ses: SesConfig {
                             configuration_set_name: "opxs-dev".to_string(),
                         },
+                        retry: EmailRetryConfig {
+                            max_retry_count: 5,
+                            base_delay_seconds: 30,
+                        },
+                        bulk: EmailBulkConfig { batch_size: 50 },
+                        transport: EmailTransportConfig::Ses,
                     },
                     image_convert: ImageConvertConfig {
                         s3: S3Config {
                             bucket: "opxs.v1.dev.image-convert".to_string(),
                         },
                     },
+                    outbox: OutboxConfig {
+                        relay_interval_seconds: 10,
+                        relay_batch_size: 100,
+                        base_delay_seconds: 10,
+                    },
                 })
             }
         }
@@ -166,6 +349,7 @@ impl AppConfig {
 
 trait ValueExt {
     fn get_str(&self, name: &str) -> anyhow::Result<String>;
+    fn get_opt_str(&self, name: &str) -> Option<String>;
 }
 
 impl ValueExt for serde_json::Value {
@@ -176,6 +360,10 @@ impl ValueExt for serde_json::Value {
             .ok_or(anyhow::anyhow!("{name} is not found"))?;
         Ok(res)
     }
+
+    fn get_opt_str(&self, name: &str) -> Option<String> {
+        self.get(name).and_then(|n| n.as_str()).filter(|n| !n.is_empty()).map(|n| n.to_string())
+    }
 }
 
 #[cfg(test)]
@@ -35,6 +35,8 @@ pub enum AppError {
     LoginRejection(anyhow::Error),
     #[error("access token expired")]
     AccessTokenExpired,
+    #[error("reauthentication required")]
+    ReauthenticationRequired,
     #[error("refresh token not found")]
     RefreshTokenNotFound,
     #[error("user not found")]
@@ -45,6 +47,30 @@ pub enum AppError {
     DuplicateEmail,
     #[error("email verify token expired")]
     EmailVerifyTokenExpired,
+    #[error("forbidden")]
+    Forbidden,
+    #[error("invalid invitation")]
+    InvalidInvitation,
+    #[error("policy violation")]
+    PolicyViolation(Vec<FieldViolation>),
+    #[error("authorization pending")]
+    AuthorizationPending,
+    #[error("slow down")]
+    SlowDown,
+    #[error("access denied")]
+    AccessDenied,
+    #[error("device code expired")]
+    DeviceCodeExpired,
+    #[error("user suspended")]
+    UserSuspended,
+    #[error("conflict")]
+    Conflict(anyhow::Error),
+    #[error("not found")]
+    NotFound,
+    #[error("legal acceptance required")]
+    LegalAcceptanceRequired,
+    #[error("too many requests")]
+    TooManyRequests,
 
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
@@ -67,29 +93,64 @@ impl IntoResponse for AppError {
             AppError::RegisterRejection(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::InternalServerError),
             AppError::LoginRejection(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::InternalServerError),
             AppError::AccessTokenExpired => (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized),
+            AppError::ReauthenticationRequired => (StatusCode::UNAUTHORIZED, ErrorCode::ReauthenticationRequired),
             AppError::RefreshTokenNotFound => (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized),
             AppError::UserNotFound => (StatusCode::NOT_FOUND, ErrorCode::UserNotFound),
             AppError::WrongPassword => (StatusCode::NOT_FOUND, ErrorCode::UserNotFound),
             AppError::DuplicateEmail => (StatusCode::CONFLICT, ErrorCode::DuplicateEmail),
             AppError::EmailVerifyTokenExpired => (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, ErrorCode::Forbidden),
+            AppError::InvalidInvitation => (StatusCode::BAD_REQUEST, ErrorCode::InvalidInvitation),
+            AppError::PolicyViolation(_) => (StatusCode::BAD_REQUEST, ErrorCode::PolicyViolation),
+            AppError::AuthorizationPending => (StatusCode::BAD_REQUEST, ErrorCode::AuthorizationPending),
+            AppError::SlowDown => (StatusCode::BAD_REQUEST, ErrorCode::SlowDown),
+            AppError::AccessDenied => (StatusCode::BAD_REQUEST, ErrorCode::AccessDenied),
+            AppError::DeviceCodeExpired => (StatusCode::BAD_REQUEST, ErrorCode::ExpiredToken),
+            AppError::UserSuspended => (StatusCode::FORBIDDEN, ErrorCode::UserSuspended),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, ErrorCode::Conflict),
+            AppError::NotFound => (StatusCode::NOT_FOUND, ErrorCode::NotFound),
+            AppError::LegalAcceptanceRequired => (StatusCode::FORBIDDEN, ErrorCode::LegalAcceptanceRequired),
+            AppError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, ErrorCode::TooManyRequests),
 
             AppError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::InternalServerError),
         };
 
         error!("{:?}", self);
 
-        let payload = json!({"error_code": error_code.to_string()});
+        let payload = match self {
+            AppError::PolicyViolation(violations) => json!({"error_code": error_code.to_string(), "violations": violations}),
+            _ => json!({"error_code": error_code.to_string()}),
+        };
         (status_code, Json(payload)).into_response()
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FieldViolation {
+    pub field: String,
+    pub reason: String,
+}
+
 #[derive(Debug, Serialize)]
 enum ErrorCode {
     InternalServerError,
     BadRequest,
     Unauthorized,
+    ReauthenticationRequired,
+    Forbidden,
     UserNotFound,
     DuplicateEmail,
+    InvalidInvitation,
+    PolicyViolation,
+    AuthorizationPending,
+    SlowDown,
+    AccessDenied,
+    ExpiredToken,
+    UserSuspended,
+    Conflict,
+    NotFound,
+    LegalAcceptanceRequired,
+    TooManyRequests,
 }
 
 impl fmt::Display for ErrorCode {
@@ -98,8 +159,21 @@ impl fmt::Display for ErrorCode {
             ErrorCode::InternalServerError => write!(f, "InternalServerError"),
             ErrorCode::BadRequest => write!(f, "BadRequest"),
             ErrorCode::Unauthorized => write!(f, "Unauthorized"),
+            ErrorCode::ReauthenticationRequired => write!(f, "ReauthenticationRequired"),
+            ErrorCode::Forbidden => write!(f, "Forbidden"),
             ErrorCode::UserNotFound => write!(f, "UserNotFound"),
             ErrorCode::DuplicateEmail => write!(f, "DuplicateEmail"),
+            ErrorCode::InvalidInvitation => write!(f, "InvalidInvitation"),
+            ErrorCode::PolicyViolation => write!(f, "PolicyViolation"),
+            ErrorCode::AuthorizationPending => write!(f, "AuthorizationPending"),
+            ErrorCode::SlowDown => write!(f, "SlowDown"),
+            ErrorCode::AccessDenied => write!(f, "AccessDenied"),
+            ErrorCode::ExpiredToken => write!(f, "ExpiredToken"),
+            ErrorCode::UserSuspended => write!(f, "UserSuspended"),
+            ErrorCode::Conflict => write!(f, "Conflict"),
+            ErrorCode::NotFound => write!(f, "NotFound"),
+            ErrorCode::LegalAcceptanceRequired => write!(f, "LegalAcceptanceRequired"),
+            ErrorCode::TooManyRequests => write!(f, "TooManyRequests"),
         }
     }
 }
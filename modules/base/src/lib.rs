@@ -1,9 +1,15 @@
 mod config;
 mod error;
 mod info;
+mod outbox;
+#[cfg(any(test, feature = "testkit"))]
+mod testkit;
 mod world;
 
 pub use config::*;
 pub use error::*;
 pub use info::*;
+pub use outbox::*;
+#[cfg(any(test, feature = "testkit"))]
+pub use testkit::*;
 pub use world::*;
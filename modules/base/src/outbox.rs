@@ -0,0 +1,290 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use core_base::clock::SystemClock;
+use core_cloud::aws::sqs::SqsSender;
+
+const OUTBOX_LEASE_SECONDS: i64 = 60;
+const OUTBOX_MAX_DELAY_SECONDS: i64 = 3600;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OutboxMessage {
+    pub id: String,
+    pub queue_url: String,
+    pub message_body: String,
+    pub retry_count: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct OutboxRepository {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl OutboxRepository {
+    // Takes the caller's transaction so the messages only exist if the rows they point at were committed.
+    pub async fn enqueue(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        queue_url: &str,
+        message_bodies: &[String],
+        now: &DateTime<Utc>,
+    ) -> anyhow::Result<Vec<String>> {
+        Self::enqueue_at(tx, queue_url, message_bodies, now, now).await
+    }
+
+    // Held back by the relay until next_attempt_at, for messages that must not be handled right after commit.
+    pub async fn enqueue_at(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        queue_url: &str,
+        message_bodies: &[String],
+        now: &DateTime<Utc>,
+        next_attempt_at: &DateTime<Utc>,
+    ) -> anyhow::Result<Vec<String>> {
+        let ids: Vec<String> = message_bodies.iter().map(|_| uuid::Uuid::new_v4().simple().to_string()).collect();
+
+        sqlx::query(
+            r#"
+INSERT INTO outbox_messages (id, queue_url, message_body, retry_count, next_attempt_at, created_at)
+    SELECT t.id, $3, t.message_body, 0, $5, $4
+        FROM UNNEST($1::VARCHAR[], $2::TEXT[]) AS t(id, message_body);
+"#,
+        )
+        .bind(&ids)
+        .bind(message_bodies)
+        .bind(queue_url)
+        .bind(now)
+        .bind(next_attempt_at)
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(ids)
+    }
+
+    // Claimed messages are leased by pushing next_attempt_at forward, so a relay that dies mid-send gives them back.
+    pub async fn claim(&self, ids: Option<&[String]>, limit: i64) -> anyhow::Result<Vec<OutboxMessage>> {
+        let now = self.system_clock.now();
+        let lease_until = now + Duration::seconds(OUTBOX_LEASE_SECONDS);
+
+        let res: Vec<OutboxMessage> = sqlx::query_as(
+            r#"
+UPDATE outbox_messages
+    SET next_attempt_at = $3
+    WHERE id IN (
+        SELECT id
+            FROM outbox_messages
+            WHERE sent_at IS NULL AND next_attempt_at <= $2 AND ($1::VARCHAR[] IS NULL OR id = ANY($1))
+            ORDER BY next_attempt_at
+            LIMIT $4
+            FOR UPDATE SKIP LOCKED
+    )
+    RETURNING *
+"#,
+        )
+        .bind(ids.map(|n| n.to_vec()))
+        .bind(now)
+        .bind(lease_until)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn get_message(&self, id: &str) -> anyhow::Result<OutboxMessage> {
+        let res: OutboxMessage = sqlx::query_as(
+            r#"
+SELECT *
+    FROM outbox_messages
+    WHERE id = $1
+"#,
+        )
+        .bind(id)
+        .fetch_one(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn update_status_to_sent(&self, id: &str) -> anyhow::Result<()> {
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+UPDATE outbox_messages
+    SET sent_at = $2
+    WHERE id = $1 AND sent_at IS NULL
+"#,
+        )
+        .bind(id)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_status_to_failed(&self, id: &str, error: &str, next_attempt_at: &DateTime<Utc>) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+UPDATE outbox_messages
+    SET retry_count = retry_count + 1, last_error = $2, next_attempt_at = $3
+    WHERE id = $1 AND sent_at IS NULL
+"#,
+        )
+        .bind(id)
+        .bind(error)
+        .bind(next_attempt_at)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+}
+
+pub struct OutboxRelay {
+    pub outbox_repository: Arc<OutboxRepository>,
+    // Keyed by the queue_url the messages were written with.
+    pub sqs_senders: HashMap<String, Arc<dyn SqsSender + Send + Sync>>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+    pub base_delay_seconds: i64,
+}
+
+impl OutboxRelay {
+    // Delivers the given messages right away; anything left pending is picked up by relay_pending later.
+    pub async fn relay(&self, ids: &[String]) -> anyhow::Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let messages = self.outbox_repository.claim(Some(ids), ids.len() as i64).await?;
+        self.deliver(&messages).await
+    }
+
+    pub async fn relay_pending(&self, limit: i64) -> anyhow::Result<usize> {
+        let messages = self.outbox_repository.claim(None, limit).await?;
+        self.deliver(&messages).await
+    }
+
+    pub async fn run(&self, interval: std::time::Duration, limit: i64) {
+        loop {
+            match self.relay_pending(limit).await {
+                Ok(0) => {}
+                Ok(count) => info!("relayed outbox messages: {}", count),
+                Err(e) => warn!("failed to relay outbox messages: {:?}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn deliver(&self, messages: &[OutboxMessage]) -> anyhow::Result<usize> {
+        let mut sent_count = 0;
+
+        for m in messages.iter() {
+            let res = match self.sqs_senders.get(&m.queue_url) {
+                Some(sender) => sender.send_message(&m.message_body).await.map(|_| ()),
+                None => Err(anyhow::anyhow!("no sender for queue: {}", m.queue_url)),
+            };
+
+            match res {
+                Ok(_) => {
+                    self.outbox_repository.update_status_to_sent(&m.id).await?;
+                    sent_count += 1;
+                }
+                Err(e) => {
+                    warn!("failed to send outbox message {}: {:?}", m.id, e);
+                    let next_attempt_at = self.system_clock.now() + Duration::seconds(self.retry_delay_seconds(m.retry_count + 1));
+                    self.outbox_repository
+                        .update_status_to_failed(&m.id, &format!("{:?}", e), &next_attempt_at)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(sent_count)
+    }
+
+    fn retry_delay_seconds(&self, retry_count: i32) -> i64 {
+        let exponent = (retry_count - 1).clamp(0, 20) as u32;
+        (self.base_delay_seconds * 2_i64.pow(exponent)).min(OUTBOX_MAX_DELAY_SECONDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::clock::SystemClockUtc;
+    use core_cloud::aws::sqs::SqsSenderMock;
+
+    use crate::setup_postgres;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = setup_postgres(&docker, "15.1").await;
+        let db = fixture.db.clone();
+
+        let system_clock = Arc::new(SystemClockUtc {});
+        let outbox_repository = Arc::new(OutboxRepository {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
+        let sqs_sender = Arc::new(SqsSenderMock::new());
+        let mut sqs_senders: HashMap<String, Arc<dyn SqsSender + Send + Sync>> = HashMap::new();
+        sqs_senders.insert("test-queue".to_string(), sqs_sender.clone());
+        let relay = OutboxRelay {
+            outbox_repository: outbox_repository.clone(),
+            sqs_senders,
+            system_clock: system_clock.clone(),
+            base_delay_seconds: 10,
+        };
+
+        let now = system_clock.now();
+        let mut tx = db.begin().await.unwrap();
+        let ids = OutboxRepository::enqueue(&mut tx, "test-queue", &["a".to_string(), "b".to_string()], &now)
+            .await
+            .unwrap();
+        let unknown_ids = OutboxRepository::enqueue(&mut tx, "unknown-queue", &["c".to_string()], &now)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        // Delayed messages wait for their time.
+        let mut tx = db.begin().await.unwrap();
+        let delayed_ids = OutboxRepository::enqueue_at(&mut tx, "test-queue", &["e".to_string()], &now, &(now + Duration::hours(1)))
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        // Rolled back messages are never relayed.
+        let mut tx = db.begin().await.unwrap();
+        OutboxRepository::enqueue(&mut tx, "test-queue", &["d".to_string()], &now).await.unwrap();
+        tx.rollback().await.unwrap();
+
+        assert_eq!(relay.relay(&ids[..1]).await.unwrap(), 1);
+        assert_eq!(relay.relay_pending(10).await.unwrap(), 1);
+        assert_eq!(relay.relay_pending(10).await.unwrap(), 0);
+
+        let bodies: Vec<String> = sqs_sender
+            .send_message_inputs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|n| n.message_body.clone())
+            .collect();
+        assert_eq!(bodies, vec!["a".to_string(), "b".to_string()]);
+        assert!(outbox_repository.get_message(&ids[1]).await.unwrap().sent_at.is_some());
+        assert!(outbox_repository.get_message(&delayed_ids[0]).await.unwrap().sent_at.is_none());
+
+        let failed = outbox_repository.get_message(&unknown_ids[0]).await.unwrap();
+        assert!(failed.sent_at.is_none());
+        assert_eq!(failed.retry_count, 1);
+        assert!(failed.last_error.unwrap().contains("unknown-queue"));
+        assert!(failed.next_attempt_at > now.naive_utc());
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use chrono::Duration;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+use core_migration::postgres::PostgresMigrator;
+use core_testkit::containers::postgres::PostgresContainer;
+
+const MIGRATIONS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../conf/migrations");
+
+// Keeps the container alive for as long as the test holds the pool.
+pub struct PostgresFixture<'a> {
+    pub container: PostgresContainer<'a>,
+    pub db: Arc<PgPool>,
+}
+
+// Starts a Postgres container for a test and applies the api migrations to it.
+pub async fn setup_postgres<'a>(docker: &'a testcontainers::clients::Cli, version: &str) -> PostgresFixture<'a> {
+    let container = PostgresContainer::new(docker, version);
+
+    let db = Arc::new(
+        PgPoolOptions::new()
+            .max_connections(100)
+            .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
+            .connect(&container.connection_string)
+            .await
+            .unwrap(),
+    );
+
+    let migrator = PostgresMigrator::new(&container.connection_string, MIGRATIONS_PATH, "opxs-api", "")
+        .await
+        .unwrap();
+    migrator.migrate().await.unwrap();
+
+    PostgresFixture { container, db }
+}
@@ -1,67 +1,183 @@
 use std::sync::Arc;
 
-use core_cloud::aws::ses::SesSender;
+use core_cloud::aws::sqs::SqsSender;
+use tracing::{error, warn};
 
-use super::{EmailConfirmRequestParam, EmailSendJobBatchSqsMessage, EmailSendJobRepository, EmailSendJobType};
+use super::{
+    is_transient_error, render_email, AccountExistsRequestParam, AnnouncementRequestParam, EmailConfirmRequestParam, EmailMessage,
+    EmailSendJobBatchDetailStatus, EmailSendJobBatchSqsMessage, EmailSendJobBatchStatus, EmailSendJobRepository, EmailSendJobType,
+    EmailTemplateParam, EmailTransport, InvitationRequestParam, NewSignInRequestParam,
+};
 
 pub struct Executor {
     pub email_send_job_repository: Arc<EmailSendJobRepository>,
-    pub ses_sender: Arc<dyn SesSender + Send + Sync>,
+    pub email_transport: Arc<dyn EmailTransport + Send + Sync>,
+    // The n-th retry is re-enqueued through the n-th sender, each configured with its own delay.
+    pub retry_sqs_senders: Vec<Arc<dyn SqsSender + Send + Sync>>,
 }
 
 impl Executor {
+    // A message that can't be processed fails its own batch rather than the whole invocation,
+    // which would make SQS redeliver the batches that already went out.
     pub async fn execute(&self, ms: &[EmailSendJobBatchSqsMessage]) -> anyhow::Result<()> {
         for m in ms.iter() {
-            self.execute_one(m).await?;
+            if let Err(e) = self.execute_one(m).await {
+                error!("failed to execute job: {} {} {:?}", m.job_id, m.batch_id, e);
+                self.email_send_job_repository
+                    .update_unfinished_status_to_failed(&m.job_id, m.batch_id, &e.to_string())
+                    .await?;
+            }
         }
         Ok(())
     }
 
     async fn execute_one(&self, m: &EmailSendJobBatchSqsMessage) -> anyhow::Result<()> {
         let job = self.email_send_job_repository.get_job(&m.job_id).await?;
+        let param = job.param.ok_or(anyhow::anyhow!("param is not found"))?;
 
         match job.typ {
             EmailSendJobType::EmailConfirm => {
-                let param = job.param.ok_or(anyhow::anyhow!("param is not found"))?;
                 let param = serde_json::from_str::<EmailConfirmRequestParam>(&param)?;
-                self.execute_email_confirm(&m.job_id, m.batch_id, &param).await
+                self.execute_template(&m.job_id, m.batch_id, &param.to_email_address, &param.from_email_address, &param)
+                    .await
+            }
+            EmailSendJobType::Invitation => {
+                let param = serde_json::from_str::<InvitationRequestParam>(&param)?;
+                self.execute_template(&m.job_id, m.batch_id, &param.to_email_address, &param.from_email_address, &param)
+                    .await
+            }
+            EmailSendJobType::AccountExists => {
+                let param = serde_json::from_str::<AccountExistsRequestParam>(&param)?;
+                self.execute_template(&m.job_id, m.batch_id, &param.to_email_address, &param.from_email_address, &param)
+                    .await
+            }
+            EmailSendJobType::NewSignIn => {
+                let param = serde_json::from_str::<NewSignInRequestParam>(&param)?;
+                self.execute_template(&m.job_id, m.batch_id, &param.to_email_address, &param.from_email_address, &param)
+                    .await
+            }
+            EmailSendJobType::Announcement => {
+                let param = serde_json::from_str::<AnnouncementRequestParam>(&param)?;
+                self.execute_bulk(&m.job_id, m.batch_id, &param.from_email_address, &param).await
             }
             _ => anyhow::bail!("invalid job type"),
         }
     }
 
-    async fn execute_email_confirm(&self, job_id: &str, batch_id: i32, param: &EmailConfirmRequestParam) -> anyhow::Result<()> {
-        self.email_send_job_repository
-            .update_status_to_processing(job_id, batch_id, &param.to_email_address)
-            .await?;
+    // Every recipient of the batch gets the same rendered content. Recipients are only claimed while the batch is
+    // Processing, so pausing or cancelling takes effect mid-batch; resume enqueues the batch again.
+    async fn execute_bulk<P: EmailTemplateParam>(&self, job_id: &str, batch_id: i32, from_email_address: &str, param: &P) -> anyhow::Result<()> {
+        if !self.email_send_job_repository.update_batch_status_to_processing(job_id, batch_id).await? {
+            return Ok(());
+        }
 
-        let subject = "Opxs: メールアドレスの確認をお願いします";
-        let body = &format!(
-            "\
-こんにちは、{user_name}様。
+        let email = render_email(param)?;
+        let details = self.email_send_job_repository.get_job_batch_details(job_id, batch_id).await?;
 
-Opxs へのご登録ありがとうございます。
+        for detail in details.iter().filter(|n| matches!(n.status, EmailSendJobBatchDetailStatus::Waiting)) {
+            if !self
+                .email_send_job_repository
+                .try_update_detail_status_to_processing(job_id, batch_id, &detail.email_address)
+                .await?
+            {
+                let batch = self.email_send_job_repository.get_job_batch(job_id, batch_id).await?;
+                if matches!(batch.status, EmailSendJobBatchStatus::Processing) {
+                    continue;
+                }
+                break;
+            }
+            if self.reject_if_blocked(job_id, batch_id, &detail.email_address).await? {
+                continue;
+            }
 
-以下のリンクをクリックして、メールアドレスの確認を完了してください。
+            let message = EmailMessage {
+                to_address: detail.email_address.clone(),
+                from_address: from_email_address.to_string(),
+                subject: email.subject.clone(),
+                text_body: email.text_body.clone(),
+                html_body: email.html_body.clone(),
+            };
+            let res = self.email_transport.send(&message).await;
+            self.finish(job_id, batch_id, &detail.email_address, res).await?;
+        }
 
-{email_confirm_url}
+        Ok(())
+    }
 
-このメールに心当たりがない場合、またはご自身で opxs に登録を行っていない場合は、このメールを無視してください。
+    async fn execute_template<P: EmailTemplateParam>(
+        &self,
+        job_id: &str,
+        batch_id: i32,
+        to_email_address: &str,
+        from_email_address: &str,
+        param: &P,
+    ) -> anyhow::Result<()> {
+        self.email_send_job_repository
+            .update_status_to_processing(job_id, batch_id, to_email_address)
+            .await?;
+        if self.reject_if_blocked(job_id, batch_id, to_email_address).await? {
+            return Ok(());
+        }
 
-ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。
+        let email = render_email(param)?;
+        let message = EmailMessage {
+            to_address: to_email_address.to_string(),
+            from_address: from_email_address.to_string(),
+            subject: email.subject,
+            text_body: email.text_body,
+            html_body: email.html_body,
+        };
+        let res = self.email_transport.send(&message).await;
+        self.finish(job_id, batch_id, to_email_address, res).await
+    }
 
-ありがとうございます。
+    async fn finish(&self, job_id: &str, batch_id: i32, email_address: &str, res: anyhow::Result<String>) -> anyhow::Result<()> {
+        match res {
+            Ok(message_id) => {
+                self.email_send_job_repository
+                    .update_status_to_completed(job_id, batch_id, email_address, &message_id)
+                    .await
+            }
+            Err(e) => {
+                let transient = is_transient_error(&e);
+                if transient {
+                    let max_retry_count = self.retry_sqs_senders.len() as i32;
+                    let retry_count = self
+                        .email_send_job_repository
+                        .update_status_to_retrying(job_id, batch_id, email_address, &e.to_string(), max_retry_count)
+                        .await?;
+                    if let Some(retry_count) = retry_count {
+                        warn!("retrying email send: {} {} {} {:?}", job_id, batch_id, retry_count, e);
+                        let message = EmailSendJobBatchSqsMessage {
+                            job_id: job_id.to_string(),
+                            batch_id,
+                        };
+                        self.retry_sqs_senders[(retry_count - 1) as usize]
+                            .send_message(&serde_json::to_string(&message)?)
+                            .await?;
+                        return Ok(());
+                    }
+                }
 
-Opxs サポートチーム",
-            user_name = param.user_name,
-            email_confirm_url = param.email_confirm_url,
-        );
+                self.email_send_job_repository
+                    .update_status_to_failed(job_id, batch_id, email_address, &e.to_string(), transient)
+                    .await
+            }
+        }
+    }
 
-        self.ses_sender
-            .send_mail_simple_text(&param.to_email_address, &param.from_email_address, subject, body)
+    // Suppressed recipients never reach SES, so a hard bounce or complaint isn't repeated.
+    async fn reject_if_blocked(&self, job_id: &str, batch_id: i32, email_address: &str) -> anyhow::Result<bool> {
+        let Some(blocked) = self.email_send_job_repository.get_active_blocked_address(email_address).await? else {
+            return Ok(false);
+        };
+
+        let reason = format!("Suppressed: {}", blocked.reason.as_deref().unwrap_or("unknown"));
+        self.email_send_job_repository
+            .update_status_to_rejected(job_id, batch_id, email_address, &reason)
             .await?;
 
-        Ok(())
+        Ok(true)
     }
 }
 
@@ -69,50 +185,51 @@ Opxs サポートチーム",
 mod tests {
     use chrono::Duration;
     use core_base::{
-        clock::SystemClockUtc,
+        clock::{SystemClock, SystemClockUtc},
         random_bytes::RandomBytesProviderImpl,
         tsid::{TsidProvider, TsidProviderImpl},
     };
-    use core_migration::postgres::PostgresMigrator;
-    use core_testkit::containers::postgres::PostgresContainer;
-    use sqlx::postgres::PgPoolOptions;
 
-    use core_cloud::aws::{ses::SesSenderMock, sqs::SqsSenderMock};
+    use core_cloud::aws::sqs::SqsSenderMock;
+    use opxs_base::{OutboxRelay, OutboxRepository};
 
-    use crate::EmailSendJobCreator;
+    use crate::{EmailSendJobController, EmailSendJobCreator, EmailTransportMock, EMAIL_SEND_QUEUE_URL};
 
     use super::*;
 
     #[tokio::test]
     async fn simple_test() {
         let docker = testcontainers::clients::Cli::default();
-        let container = PostgresContainer::new(&docker, "15.1");
-
-        let db = Arc::new(
-            PgPoolOptions::new()
-                .max_connections(100)
-                .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
-                .connect(&container.connection_string)
-                .await
-                .unwrap(),
-        );
+        let fixture = opxs_base::setup_postgres(&docker, "15.1").await;
+        let db = fixture.db.clone();
+
         let system_clock = Arc::new(SystemClockUtc {});
         let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
 
-        let migrations_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../conf/migrations");
-        let migrator = PostgresMigrator::new(&container.connection_string, migrations_path, "opxs-api", "")
-            .await
-            .unwrap();
-        migrator.migrate().await.unwrap();
-
-        let email_send_job_repository = Arc::new(EmailSendJobRepository { db, system_clock });
+        let email_send_job_repository = Arc::new(EmailSendJobRepository {
+            db: db.clone(),
+            system_clock: system_clock.clone(),
+        });
 
         let send_email_sqs_sender = Arc::new(SqsSenderMock::new());
+        let outbox_relay = Arc::new(OutboxRelay {
+            outbox_repository: Arc::new(OutboxRepository {
+                db: db.clone(),
+                system_clock: system_clock.clone(),
+            }),
+            sqs_senders: [(
+                EMAIL_SEND_QUEUE_URL.to_string(),
+                send_email_sqs_sender.clone() as Arc<dyn SqsSender + Send + Sync>,
+            )]
+            .into(),
+            system_clock: system_clock.clone(),
+            base_delay_seconds: 10,
+        });
 
         let job_id = tsid_provider.gen().to_string();
         let job_creator = EmailSendJobCreator {
             email_send_job_repository: email_send_job_repository.clone(),
-            send_email_sqs_sender: send_email_sqs_sender.clone(),
+            outbox_relay: outbox_relay.clone(),
         };
         job_creator
             .create_email_confirm_job(
@@ -121,28 +238,212 @@ mod tests {
                 "lyrise1984@gmail.com",
                 "no-reply@opxs-dev.omnius-labs.com",
                 "https://example.com",
+                "en",
             )
             .await
             .unwrap();
 
-        let ses_sender = Arc::new(SesSenderMock::new());
+        let email_transport = Arc::new(EmailTransportMock::new());
         let sqs_send_message_input = send_email_sqs_sender.send_message_inputs.lock().unwrap().first().cloned().unwrap();
         let sqs_message = serde_json::from_str::<EmailSendJobBatchSqsMessage>(sqs_send_message_input.message_body.as_str()).unwrap();
 
         let executor = Executor {
             email_send_job_repository,
-            ses_sender: ses_sender.clone(),
+            email_transport: email_transport.clone(),
+            retry_sqs_senders: vec![],
         };
+        executor.execute(&[sqs_message.clone()]).await.unwrap();
+
+        let send_input = email_transport.send_inputs.lock().unwrap().first().cloned().unwrap();
+
+        assert_eq!(send_input.to_address, "lyrise1984@gmail.com".to_string());
+        assert_eq!(send_input.from_address, "no-reply@opxs-dev.omnius-labs.com".to_string());
+        assert_eq!(send_input.subject, "Opxs: Please confirm your email address");
+        assert!(send_input.text_body.contains("https://example.com"));
+        assert!(send_input.html_body.contains("https://example.com"));
+
+        let details = executor.email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+        assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Completed));
+        assert!(details[0].message_id.is_some());
+        let batches = executor.email_send_job_repository.get_job_batches(&job_id).await.unwrap();
+        assert!(matches!(batches[0].status, EmailSendJobBatchStatus::Completed));
+
+        // a redelivered message neither sends again nor fails the finished batch
         executor.execute(&[sqs_message]).await.unwrap();
+        assert_eq!(email_transport.send_inputs.lock().unwrap().len(), 1);
+        let details = executor.email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+        assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Completed));
 
-        let ses_send_mail_simple_text_input = ses_sender.send_mail_simple_text_inputs.lock().unwrap().first().cloned().unwrap();
+        // an unknown job fails on its own
+        let sqs_message = EmailSendJobBatchSqsMessage {
+            job_id: "unknown".to_string(),
+            batch_id: 0,
+        };
+        assert!(executor.execute(&[sqs_message]).await.is_ok());
+
+        // suppressed recipient
+        let now = system_clock.now();
+        executor
+            .email_send_job_repository
+            .create_blocked_address("Blocked@example.com", "Bounce: Permanent/General", None, &now)
+            .await
+            .unwrap();
+        let job_id = tsid_provider.gen().to_string();
+        job_creator
+            .create_email_confirm_job(
+                &job_id,
+                "test_name",
+                "blocked@example.com",
+                "no-reply@opxs-dev.omnius-labs.com",
+                "https://example.com",
+                "en",
+            )
+            .await
+            .unwrap();
+        let sqs_send_message_input = send_email_sqs_sender.send_message_inputs.lock().unwrap().last().cloned().unwrap();
+        let sqs_message = serde_json::from_str::<EmailSendJobBatchSqsMessage>(sqs_send_message_input.message_body.as_str()).unwrap();
+        executor.execute(&[sqs_message]).await.unwrap();
+
+        assert_eq!(email_transport.send_inputs.lock().unwrap().len(), 1);
+        let details = executor.email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+        assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Rejected));
+        assert_eq!(details[0].failed_reason.as_deref(), Some("Suppressed: Bounce: Permanent/General"));
+
+        let single_job_id = job_id.clone();
+
+        // an expired soft entry no longer suppresses
+        let expires_at = now - Duration::days(1);
+        executor
+            .email_send_job_repository
+            .create_blocked_address("soft@example.com", "Manual", Some(&expires_at), &now)
+            .await
+            .unwrap();
+        assert!(executor
+            .email_send_job_repository
+            .get_active_blocked_address("soft@example.com")
+            .await
+            .unwrap()
+            .is_none());
+
+        // bulk job: 5 unique recipients in batches of 2, paused before running and then resumed
+        let job_controller = EmailSendJobController {
+            email_send_job_repository: executor.email_send_job_repository.clone(),
+            outbox_relay: outbox_relay.clone(),
+        };
+        let to_email_addresses: Vec<String> = ["a", "b", "c", "d", "e", "A"].iter().map(|n| format!("{}@example.com", n)).collect();
+        let param = AnnouncementRequestParam {
+            from_email_address: "no-reply@opxs-dev.omnius-labs.com".to_string(),
+            subject: "test".to_string(),
+            body: "test body".to_string(),
+            settings_url: "https://example.com/settings".to_string(),
+            locale: "en".to_string(),
+        };
+        let job_id = tsid_provider.gen().to_string();
+        let sent_message_count = send_email_sqs_sender.send_message_inputs.lock().unwrap().len();
+        job_creator
+            .create_announcement_job(&job_id, &param, &to_email_addresses, 2)
+            .await
+            .unwrap();
+        let sqs_messages: Vec<EmailSendJobBatchSqsMessage> = send_email_sqs_sender.send_message_inputs.lock().unwrap()[sent_message_count..]
+            .iter()
+            .map(|n| serde_json::from_str(n.message_body.as_str()).unwrap())
+            .collect();
+        assert_eq!(sqs_messages.len(), 3);
+
+        let sent_mail_count = email_transport.send_inputs.lock().unwrap().len();
+        assert!(job_controller.pause(&job_id).await.unwrap());
+        executor.execute(&sqs_messages).await.unwrap();
+        assert_eq!(email_transport.send_inputs.lock().unwrap().len(), sent_mail_count);
+
+        // a recipient left Processing by a crashed invocation is sent again on resume
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batch_details
+    SET status = 'Processing', updated_at = $3
+    WHERE job_id = $1 AND email_address = $2
+"#,
+        )
+        .bind(&job_id)
+        .bind("a@example.com")
+        .bind(now - Duration::hours(1))
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+
+        let sent_message_count = send_email_sqs_sender.send_message_inputs.lock().unwrap().len();
+        assert!(job_controller.resume(&job_id).await.unwrap());
+        let sqs_messages: Vec<EmailSendJobBatchSqsMessage> = send_email_sqs_sender.send_message_inputs.lock().unwrap()[sent_message_count..]
+            .iter()
+            .map(|n| serde_json::from_str(n.message_body.as_str()).unwrap())
+            .collect();
+        assert_eq!(sqs_messages.len(), 3);
+        executor.execute(&sqs_messages).await.unwrap();
+        assert_eq!(email_transport.send_inputs.lock().unwrap().len(), sent_mail_count + 5);
+
+        let progress = job_controller.get_progress(&job_id).await.unwrap().unwrap();
+        assert_eq!(progress.batch_count, 3);
+        assert_eq!(progress.email_address_count, 5);
+        assert_eq!(progress.batches.iter().map(|n| n.completed_count).sum::<i64>(), 5);
+        assert!(progress.batches.iter().all(|n| matches!(n.status, EmailSendJobBatchStatus::Completed)));
+
+        // a cancelled bulk job sends nothing; single jobs can't be controlled
+        let job_id = tsid_provider.gen().to_string();
+        job_creator
+            .create_announcement_job(&job_id, &param, &to_email_addresses, 2)
+            .await
+            .unwrap();
+        assert!(job_controller.cancel(&job_id).await.unwrap());
+        let progress = job_controller.get_progress(&job_id).await.unwrap().unwrap();
+        assert_eq!(progress.batches.iter().map(|n| n.cancelled_count).sum::<i64>(), 5);
+        assert!(!job_controller.pause(&single_job_id).await.unwrap());
+
+        // transient failures go back out through the sender for their retry, then fail once the retries are used up
+        let retry_sqs_senders = [Arc::new(SqsSenderMock::new()), Arc::new(SqsSenderMock::new())];
+        let executor = Executor {
+            email_send_job_repository: executor.email_send_job_repository.clone(),
+            email_transport: email_transport.clone(),
+            retry_sqs_senders: retry_sqs_senders.iter().map(|n| n.clone() as Arc<dyn SqsSender + Send + Sync>).collect(),
+        };
+        let job_id = tsid_provider.gen().to_string();
+        job_creator
+            .create_email_confirm_job(
+                &job_id,
+                "test_name",
+                "retry@example.com",
+                "no-reply@opxs-dev.omnius-labs.com",
+                "https://example.com",
+                "en",
+            )
+            .await
+            .unwrap();
+        let sqs_send_message_input = send_email_sqs_sender.send_message_inputs.lock().unwrap().last().cloned().unwrap();
+        let mut sqs_message = serde_json::from_str::<EmailSendJobBatchSqsMessage>(sqs_send_message_input.message_body.as_str()).unwrap();
+        email_transport
+            .send_errors
+            .lock()
+            .unwrap()
+            .extend(vec!["Throttling: Maximum sending rate exceeded".to_string(); 3]);
+        let sent_mail_count = email_transport.send_inputs.lock().unwrap().len();
+
+        for (i, retry_sqs_sender) in retry_sqs_senders.iter().enumerate() {
+            executor.execute(&[sqs_message]).await.unwrap();
+
+            let details = executor.email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+            assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Waiting));
+            assert_eq!(details[0].retry_count, i as i32 + 1);
+            let send_message_inputs = retry_sqs_sender.send_message_inputs.lock().unwrap().clone();
+            assert_eq!(send_message_inputs.len(), 1);
+            sqs_message = serde_json::from_str(send_message_inputs[0].message_body.as_str()).unwrap();
+            assert_eq!(sqs_message.job_id, job_id);
+        }
+        executor.execute(&[sqs_message]).await.unwrap();
 
-        assert_eq!(ses_send_mail_simple_text_input.to_address, "lyrise1984@gmail.com".to_string());
-        assert_eq!(
-            ses_send_mail_simple_text_input.from_address,
-            "no-reply@opxs-dev.omnius-labs.com".to_string()
-        );
-        println!("{}", ses_send_mail_simple_text_input.subject);
-        println!("{}", ses_send_mail_simple_text_input.text_body);
+        assert_eq!(email_transport.send_inputs.lock().unwrap().len(), sent_mail_count);
+        assert!(retry_sqs_senders.iter().all(|n| n.send_message_inputs.lock().unwrap().len() == 1));
+        let details = executor.email_send_job_repository.get_job_batch_details(&job_id, 0).await.unwrap();
+        assert!(matches!(details[0].status, EmailSendJobBatchDetailStatus::Failed));
+        let attempts = job_controller.get_attempts(&job_id, "retry@example.com").await.unwrap();
+        assert_eq!(attempts.iter().map(|n| n.retry_count).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert!(attempts.iter().all(|n| n.transient && n.failed_reason.contains("Throttling")));
     }
 }
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use tracing::warn;
+
+use opxs_base::OutboxRelay;
+
+use super::{EmailSendJobBatchDetailAttempt, EmailSendJobProgress, EmailSendJobRepository, EmailSendJobType};
+
+pub struct EmailSendJobController {
+    pub email_send_job_repository: Arc<EmailSendJobRepository>,
+    pub outbox_relay: Arc<OutboxRelay>,
+}
+
+impl EmailSendJobController {
+    pub async fn get_progress(&self, job_id: &str) -> anyhow::Result<Option<EmailSendJobProgress>> {
+        let Some(job) = self.email_send_job_repository.find_job(job_id).await? else {
+            return Ok(None);
+        };
+        let batches = self.email_send_job_repository.get_job_batch_progresses(job_id).await?;
+
+        Ok(Some(EmailSendJobProgress {
+            job_id: job.id,
+            batch_count: job.batch_count,
+            email_address_count: job.email_address_count,
+            created_at: job.created_at,
+            batches,
+        }))
+    }
+
+    // Every failed delivery of one recipient, oldest first; the detail itself only keeps the last reason.
+    pub async fn get_attempts(&self, job_id: &str, email_address: &str) -> anyhow::Result<Vec<EmailSendJobBatchDetailAttempt>> {
+        self.email_send_job_repository.get_job_batch_detail_attempts(job_id, email_address).await
+    }
+
+    // The controls below only apply to bulk jobs and return false when nothing changed.
+    pub async fn pause(&self, job_id: &str) -> anyhow::Result<bool> {
+        if !self.is_bulk_job(job_id).await? {
+            return Ok(false);
+        }
+        Ok(self.email_send_job_repository.pause_job(job_id).await? > 0)
+    }
+
+    pub async fn resume(&self, job_id: &str) -> anyhow::Result<bool> {
+        if !self.is_bulk_job(job_id).await? {
+            return Ok(false);
+        }
+
+        let outbox_ids = self.email_send_job_repository.resume_job(job_id).await?;
+        if let Err(e) = self.outbox_relay.relay(&outbox_ids).await {
+            warn!("failed to relay resumed email send job: {:?}", e);
+        }
+
+        Ok(!outbox_ids.is_empty())
+    }
+
+    pub async fn cancel(&self, job_id: &str) -> anyhow::Result<bool> {
+        if !self.is_bulk_job(job_id).await? {
+            return Ok(false);
+        }
+        Ok(self.email_send_job_repository.cancel_job(job_id).await? > 0)
+    }
+
+    async fn is_bulk_job(&self, job_id: &str) -> anyhow::Result<bool> {
+        let job = self.email_send_job_repository.find_job(job_id).await?;
+        Ok(matches!(job.map(|n| n.typ), Some(EmailSendJobType::Announcement)))
+    }
+}
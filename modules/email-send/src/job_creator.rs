@@ -1,14 +1,20 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
-use core_cloud::aws::sqs::SqsSender;
+use tracing::warn;
 
-use super::{EmailConfirmRequestParam, EmailSendJobBatchSqsMessage, EmailSendJobRepository};
+use opxs_base::OutboxRelay;
+
+use super::{
+    render_email, AccountExistsRequestParam, AnnouncementRequestParam, EmailConfirmRequestParam, EmailSendJobRepository, InvitationRequestParam,
+    NewSignInRequestParam,
+};
 
 pub struct EmailSendJobCreator {
     pub email_send_job_repository: Arc<EmailSendJobRepository>,
-    pub send_email_sqs_sender: Arc<dyn SqsSender + Send + Sync>,
+    pub outbox_relay: Arc<OutboxRelay>,
 }
 
+// Params are rendered once before the job is stored, so a template that can't be filled fails here instead of in the batch.
 impl EmailSendJobCreator {
     pub async fn create_email_confirm_job(
         &self,
@@ -17,30 +23,98 @@ impl EmailSendJobCreator {
         to_email_address: &str,
         from_email_address: &str,
         email_confirm_url: &str,
+        locale: &str,
     ) -> anyhow::Result<()> {
         let param = EmailConfirmRequestParam {
             user_name: user_name.to_string(),
             to_email_address: to_email_address.to_string(),
             from_email_address: from_email_address.to_string(),
             email_confirm_url: email_confirm_url.to_string(),
+            locale: locale.to_string(),
         };
-        self.email_send_job_repository.create_email_confirm_job(job_id, &param).await?;
-        let batches = self.email_send_job_repository.get_job_batches(job_id).await?;
+        render_email(&param)?;
+        let outbox_ids = self.email_send_job_repository.create_email_confirm_job(job_id, &param).await?;
+        self.relay(&outbox_ids).await;
+        Ok(())
+    }
 
-        let messages: Vec<EmailSendJobBatchSqsMessage> = batches
-            .iter()
-            .map(|n| EmailSendJobBatchSqsMessage {
-                job_id: n.job_id.clone(),
-                batch_id: n.batch_id,
-            })
-            .collect();
+    pub async fn create_invitation_job(
+        &self,
+        job_id: &str,
+        inviter_name: &str,
+        to_email_address: &str,
+        from_email_address: &str,
+        invitation_url: &str,
+        locale: &str,
+    ) -> anyhow::Result<()> {
+        let param = InvitationRequestParam {
+            inviter_name: inviter_name.to_string(),
+            to_email_address: to_email_address.to_string(),
+            from_email_address: from_email_address.to_string(),
+            invitation_url: invitation_url.to_string(),
+            locale: locale.to_string(),
+        };
+        render_email(&param)?;
+        let outbox_ids = self.email_send_job_repository.create_invitation_job(job_id, &param).await?;
+        self.relay(&outbox_ids).await;
+        Ok(())
+    }
 
-        self.email_send_job_repository.update_status_to_waiting(job_id).await?;
+    pub async fn create_account_exists_job(
+        &self,
+        job_id: &str,
+        to_email_address: &str,
+        from_email_address: &str,
+        login_url: &str,
+        locale: &str,
+    ) -> anyhow::Result<()> {
+        let param = AccountExistsRequestParam {
+            to_email_address: to_email_address.to_string(),
+            from_email_address: from_email_address.to_string(),
+            login_url: login_url.to_string(),
+            locale: locale.to_string(),
+        };
+        render_email(&param)?;
+        let outbox_ids = self.email_send_job_repository.create_account_exists_job(job_id, &param).await?;
+        self.relay(&outbox_ids).await;
+        Ok(())
+    }
+
+    pub async fn create_new_sign_in_job(&self, job_id: &str, param: &NewSignInRequestParam) -> anyhow::Result<()> {
+        render_email(param)?;
+        let outbox_ids = self.email_send_job_repository.create_new_sign_in_job(job_id, param).await?;
+        self.relay(&outbox_ids).await;
+        Ok(())
+    }
+
+    // Addresses are deduplicated case-insensitively, keeping the first spelling.
+    pub async fn create_announcement_job(
+        &self,
+        job_id: &str,
+        param: &AnnouncementRequestParam,
+        to_email_addresses: &[String],
+        batch_size: i32,
+    ) -> anyhow::Result<()> {
+        render_email(param)?;
 
-        for m in messages.iter() {
-            self.send_email_sqs_sender.send_message(&serde_json::to_string(m).unwrap()).await?;
+        let mut seen = HashSet::new();
+        let to_email_addresses: Vec<String> = to_email_addresses.iter().filter(|n| seen.insert(n.to_lowercase())).cloned().collect();
+        if to_email_addresses.is_empty() {
+            anyhow::bail!("no recipients");
         }
 
+        let outbox_ids = self
+            .email_send_job_repository
+            .create_announcement_job(job_id, param, &to_email_addresses, batch_size)
+            .await?;
+        self.relay(&outbox_ids).await;
         Ok(())
     }
+
+    // The job is already committed with its outbox messages, so a failed send here is left to the background relay.
+    async fn relay(&self, outbox_ids: &[String]) {
+        if let Err(e) = self.outbox_relay.relay(outbox_ids).await {
+            warn!("failed to relay email send job: {:?}", e);
+        }
+    }
 }
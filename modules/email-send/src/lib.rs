@@ -1,9 +1,19 @@
 mod executor;
+mod job_controller;
 mod job_creator;
 mod message;
 mod repo;
+mod retry;
+mod suppression;
+mod template;
+mod transport;
 
 pub use executor::*;
+pub use job_controller::*;
 pub use job_creator::*;
 pub use message::*;
 pub use repo::*;
+pub use retry::*;
+pub use suppression::*;
+pub use template::*;
+pub use transport::*;
@@ -1,7 +1,9 @@
+mod feedback;
 mod job;
 mod param;
 mod sqs;
 
+pub use feedback::*;
 pub use job::*;
 pub use param::*;
 pub use sqs::*;
@@ -0,0 +1,57 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum EmailSendLogEventType {
+    Unknown,
+    Bounce,
+    Complaint,
+    Delivery,
+}
+
+impl sqlx::Type<sqlx::Postgres> for EmailSendLogEventType {
+    fn type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("VARCHAR")
+    }
+}
+
+impl sqlx::Encode<'_, sqlx::Postgres> for EmailSendLogEventType {
+    fn encode_by_ref(&self, buf: &mut sqlx::postgres::PgArgumentBuffer) -> sqlx::encode::IsNull {
+        match self {
+            EmailSendLogEventType::Bounce => buf.extend_from_slice(b"Bounce"),
+            EmailSendLogEventType::Complaint => buf.extend_from_slice(b"Complaint"),
+            EmailSendLogEventType::Delivery => buf.extend_from_slice(b"Delivery"),
+            _ => buf.extend_from_slice(b"Unknown"),
+        }
+        sqlx::encode::IsNull::No
+    }
+}
+
+impl sqlx::Decode<'_, sqlx::Postgres> for EmailSendLogEventType {
+    fn decode(value: sqlx::postgres::PgValueRef<'_>) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        match value.as_str() {
+            Ok("Bounce") => Ok(EmailSendLogEventType::Bounce),
+            Ok("Complaint") => Ok(EmailSendLogEventType::Complaint),
+            Ok("Delivery") => Ok(EmailSendLogEventType::Delivery),
+            _ => Ok(EmailSendLogEventType::Unknown),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
+pub struct EmailSendLog {
+    pub message_id: String,
+    pub email_address: String,
+    pub event_type: EmailSendLogEventType,
+    pub event_detail: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow, ToSchema)]
+pub struct EmailSendBlockedAddress {
+    pub email_address: String,
+    pub reason: Option<String>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
@@ -1,10 +1,15 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum EmailSendJobType {
     Unknown,
     EmailConfirm,
+    Invitation,
+    AccountExists,
+    NewSignIn,
+    Announcement,
 }
 
 impl sqlx::Type<sqlx::Postgres> for EmailSendJobType {
@@ -17,6 +22,10 @@ impl sqlx::Encode<'_, sqlx::Postgres> for EmailSendJobType {
     fn encode_by_ref(&self, buf: &mut sqlx::postgres::PgArgumentBuffer) -> sqlx::encode::IsNull {
         match self {
             EmailSendJobType::EmailConfirm => buf.extend_from_slice(b"EmailConfirm"),
+            EmailSendJobType::Invitation => buf.extend_from_slice(b"Invitation"),
+            EmailSendJobType::AccountExists => buf.extend_from_slice(b"AccountExists"),
+            EmailSendJobType::NewSignIn => buf.extend_from_slice(b"NewSignIn"),
+            EmailSendJobType::Announcement => buf.extend_from_slice(b"Announcement"),
             _ => buf.extend_from_slice(b"Unknown"),
         }
         sqlx::encode::IsNull::No
@@ -27,6 +36,10 @@ impl sqlx::Decode<'_, sqlx::Postgres> for EmailSendJobType {
     fn decode(value: sqlx::postgres::PgValueRef<'_>) -> std::result::Result<Self, Box<dyn std::error::Error + Send + Sync + 'static>> {
         match value.as_str() {
             Ok("EmailConfirm") => Ok(EmailSendJobType::EmailConfirm),
+            Ok("Invitation") => Ok(EmailSendJobType::Invitation),
+            Ok("AccountExists") => Ok(EmailSendJobType::AccountExists),
+            Ok("NewSignIn") => Ok(EmailSendJobType::NewSignIn),
+            Ok("Announcement") => Ok(EmailSendJobType::Announcement),
             _ => Ok(EmailSendJobType::Unknown),
         }
     }
@@ -43,14 +56,16 @@ pub struct EmailSendJob {
     pub created_at: NaiveDateTime,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub enum EmailSendJobBatchStatus {
     Unknown,
     Preparing,
     Waiting,
     Processing,
+    Paused,
     Completed,
     Failed,
+    Cancelled,
 }
 
 impl sqlx::Type<sqlx::Postgres> for EmailSendJobBatchStatus {
@@ -65,8 +80,10 @@ impl sqlx::Encode<'_, sqlx::Postgres> for EmailSendJobBatchStatus {
             EmailSendJobBatchStatus::Preparing => buf.extend_from_slice(b"Preparing"),
             EmailSendJobBatchStatus::Waiting => buf.extend_from_slice(b"Waiting"),
             EmailSendJobBatchStatus::Processing => buf.extend_from_slice(b"Processing"),
+            EmailSendJobBatchStatus::Paused => buf.extend_from_slice(b"Paused"),
             EmailSendJobBatchStatus::Completed => buf.extend_from_slice(b"Completed"),
             EmailSendJobBatchStatus::Failed => buf.extend_from_slice(b"Failed"),
+            EmailSendJobBatchStatus::Cancelled => buf.extend_from_slice(b"Cancelled"),
             _ => buf.extend_from_slice(b"Unknown"),
         }
         sqlx::encode::IsNull::No
@@ -79,8 +96,10 @@ impl sqlx::Decode<'_, sqlx::Postgres> for EmailSendJobBatchStatus {
             Ok("Preparing") => Ok(EmailSendJobBatchStatus::Preparing),
             Ok("Waiting") => Ok(EmailSendJobBatchStatus::Waiting),
             Ok("Processing") => Ok(EmailSendJobBatchStatus::Processing),
+            Ok("Paused") => Ok(EmailSendJobBatchStatus::Paused),
             Ok("Completed") => Ok(EmailSendJobBatchStatus::Completed),
             Ok("Failed") => Ok(EmailSendJobBatchStatus::Failed),
+            Ok("Cancelled") => Ok(EmailSendJobBatchStatus::Cancelled),
             _ => Ok(EmailSendJobBatchStatus::Unknown),
         }
     }
@@ -104,6 +123,7 @@ pub enum EmailSendJobBatchDetailStatus {
     Completed,
     Rejected,
     Failed,
+    Cancelled,
 }
 
 impl sqlx::Type<sqlx::Postgres> for EmailSendJobBatchDetailStatus {
@@ -121,6 +141,7 @@ impl sqlx::Encode<'_, sqlx::Postgres> for EmailSendJobBatchDetailStatus {
             EmailSendJobBatchDetailStatus::Completed => buf.extend_from_slice(b"Completed"),
             EmailSendJobBatchDetailStatus::Rejected => buf.extend_from_slice(b"Rejected"),
             EmailSendJobBatchDetailStatus::Failed => buf.extend_from_slice(b"Failed"),
+            EmailSendJobBatchDetailStatus::Cancelled => buf.extend_from_slice(b"Cancelled"),
             _ => buf.extend_from_slice(b"Unknown"),
         }
         sqlx::encode::IsNull::No
@@ -136,6 +157,7 @@ impl sqlx::Decode<'_, sqlx::Postgres> for EmailSendJobBatchDetailStatus {
             Ok("Completed") => Ok(EmailSendJobBatchDetailStatus::Completed),
             Ok("Rejected") => Ok(EmailSendJobBatchDetailStatus::Rejected),
             Ok("Failed") => Ok(EmailSendJobBatchDetailStatus::Failed),
+            Ok("Cancelled") => Ok(EmailSendJobBatchDetailStatus::Cancelled),
             _ => Ok(EmailSendJobBatchDetailStatus::Unknown),
         }
     }
@@ -147,9 +169,41 @@ pub struct EmailSendJobBatchDetail {
     pub batch_id: i32,
     pub email_address: String,
     pub retry_count: i32,
-    pub message_id: String,
+    pub message_id: Option<String>,
     pub status: EmailSendJobBatchDetailStatus,
+    pub failed_reason: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow, ToSchema)]
+pub struct EmailSendJobBatchDetailAttempt {
+    pub job_id: String,
+    pub email_address: String,
+    pub retry_count: i32,
+    pub transient: bool,
     pub failed_reason: String,
     pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow, ToSchema)]
+pub struct EmailSendJobBatchProgress {
+    pub batch_id: i32,
+    pub status: EmailSendJobBatchStatus,
+    pub total_count: i64,
+    pub pending_count: i64,
+    pub completed_count: i64,
+    pub rejected_count: i64,
+    pub failed_count: i64,
+    pub cancelled_count: i64,
     pub updated_at: NaiveDateTime,
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EmailSendJobProgress {
+    pub job_id: String,
+    pub batch_count: i32,
+    pub email_address_count: i32,
+    pub created_at: NaiveDateTime,
+    pub batches: Vec<EmailSendJobBatchProgress>,
+}
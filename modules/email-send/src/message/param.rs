@@ -1,9 +1,133 @@
 use serde::{Deserialize, Serialize};
 
+use crate::EmailTemplateParam;
+
+// Jobs queued before templates were introduced carry no locale and render in the default one.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct EmailConfirmRequestParam {
     pub user_name: String,
     pub to_email_address: String,
     pub from_email_address: String,
     pub email_confirm_url: String,
+    #[serde(default)]
+    pub locale: String,
+}
+
+impl EmailTemplateParam for EmailConfirmRequestParam {
+    fn template_name(&self) -> &'static str {
+        "email_confirm"
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn variables(&self) -> Vec<(&'static str, &str)> {
+        vec![("user_name", &self.user_name), ("email_confirm_url", &self.email_confirm_url)]
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct InvitationRequestParam {
+    pub inviter_name: String,
+    pub to_email_address: String,
+    pub from_email_address: String,
+    pub invitation_url: String,
+    #[serde(default)]
+    pub locale: String,
+}
+
+impl EmailTemplateParam for InvitationRequestParam {
+    fn template_name(&self) -> &'static str {
+        "invitation"
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn variables(&self) -> Vec<(&'static str, &str)> {
+        vec![("inviter_name", &self.inviter_name), ("invitation_url", &self.invitation_url)]
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct AccountExistsRequestParam {
+    pub to_email_address: String,
+    pub from_email_address: String,
+    pub login_url: String,
+    #[serde(default)]
+    pub locale: String,
+}
+
+impl EmailTemplateParam for AccountExistsRequestParam {
+    fn template_name(&self) -> &'static str {
+        "account_exists"
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn variables(&self) -> Vec<(&'static str, &str)> {
+        vec![("login_url", &self.login_url)]
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct NewSignInRequestParam {
+    pub to_email_address: String,
+    pub from_email_address: String,
+    pub user_name: String,
+    pub signed_in_at: String,
+    pub ip_address: String,
+    pub user_agent: String,
+    pub revoke_url: String,
+    #[serde(default)]
+    pub locale: String,
+}
+
+impl EmailTemplateParam for NewSignInRequestParam {
+    fn template_name(&self) -> &'static str {
+        "new_sign_in"
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn variables(&self) -> Vec<(&'static str, &str)> {
+        vec![
+            ("user_name", &self.user_name),
+            ("signed_in_at", &self.signed_in_at),
+            ("ip_address", &self.ip_address),
+            ("user_agent", &self.user_agent),
+            ("revoke_url", &self.revoke_url),
+        ]
+    }
+}
+
+// The same content goes to every recipient of the job; settings_url is where they can opt out.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct AnnouncementRequestParam {
+    pub from_email_address: String,
+    pub subject: String,
+    pub body: String,
+    pub settings_url: String,
+    #[serde(default)]
+    pub locale: String,
+}
+
+impl EmailTemplateParam for AnnouncementRequestParam {
+    fn template_name(&self) -> &'static str {
+        "announcement"
+    }
+
+    fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn variables(&self) -> Vec<(&'static str, &str)> {
+        vec![("subject", &self.subject), ("body", &self.body), ("settings_url", &self.settings_url)]
+    }
 }
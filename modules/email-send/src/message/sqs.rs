@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+pub const EMAIL_SEND_QUEUE_URL: &str = "opxs-batch-email-send-sqs";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EmailSendJobBatchSqsMessage {
     pub job_id: String,
     pub batch_id: i32,
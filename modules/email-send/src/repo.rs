@@ -1,12 +1,23 @@
 use std::sync::Arc;
 
-use chrono::Utc;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use core_base::clock::SystemClock;
 use sqlx::PgPool;
 
-use crate::EmailSendJobBatchDetail;
+use opxs_base::OutboxRepository;
 
-use super::{EmailConfirmRequestParam, EmailSendJob, EmailSendJobBatch, EmailSendJobBatchDetailStatus, EmailSendJobBatchStatus, EmailSendJobType};
+use crate::{
+    AnnouncementRequestParam, EmailSendJobBatchDetail, EmailSendJobBatchDetailAttempt, EmailSendJobBatchProgress, EmailSendJobBatchSqsMessage,
+    EMAIL_SEND_QUEUE_URL,
+};
+
+use super::{
+    AccountExistsRequestParam, EmailConfirmRequestParam, EmailSendBlockedAddress, EmailSendJob, EmailSendJobBatch, EmailSendJobBatchDetailStatus,
+    EmailSendJobBatchStatus, EmailSendJobType, EmailSendLog, EmailSendLogEventType, InvitationRequestParam, NewSignInRequestParam,
+};
+
+// An invocation runs for at most 15 minutes, so a recipient Processing for longer belongs to one that died.
+const STALE_PROCESSING_MINUTES: i64 = 15;
 
 pub struct EmailSendJobRepository {
     pub db: Arc<PgPool>,
@@ -14,7 +25,135 @@ pub struct EmailSendJobRepository {
 }
 
 impl EmailSendJobRepository {
-    pub async fn create_email_confirm_job(&self, job_id: &str, param: &EmailConfirmRequestParam) -> anyhow::Result<()> {
+    pub async fn create_email_confirm_job(&self, job_id: &str, param: &EmailConfirmRequestParam) -> anyhow::Result<Vec<String>> {
+        self.create_single_job(
+            job_id,
+            EmailSendJobType::EmailConfirm,
+            &serde_json::to_string(param)?,
+            &param.to_email_address,
+        )
+        .await
+    }
+
+    pub async fn create_invitation_job(&self, job_id: &str, param: &InvitationRequestParam) -> anyhow::Result<Vec<String>> {
+        self.create_single_job(
+            job_id,
+            EmailSendJobType::Invitation,
+            &serde_json::to_string(param)?,
+            &param.to_email_address,
+        )
+        .await
+    }
+
+    pub async fn create_account_exists_job(&self, job_id: &str, param: &AccountExistsRequestParam) -> anyhow::Result<Vec<String>> {
+        self.create_single_job(
+            job_id,
+            EmailSendJobType::AccountExists,
+            &serde_json::to_string(param)?,
+            &param.to_email_address,
+        )
+        .await
+    }
+
+    pub async fn create_new_sign_in_job(&self, job_id: &str, param: &NewSignInRequestParam) -> anyhow::Result<Vec<String>> {
+        self.create_single_job(
+            job_id,
+            EmailSendJobType::NewSignIn,
+            &serde_json::to_string(param)?,
+            &param.to_email_address,
+        )
+        .await
+    }
+
+    pub async fn create_announcement_job(
+        &self,
+        job_id: &str,
+        param: &AnnouncementRequestParam,
+        to_email_addresses: &[String],
+        batch_size: i32,
+    ) -> anyhow::Result<Vec<String>> {
+        self.create_bulk_job(
+            job_id,
+            EmailSendJobType::Announcement,
+            &serde_json::to_string(param)?,
+            to_email_addresses,
+            batch_size,
+        )
+        .await
+    }
+
+    // Recipients are split in order into batches of batch_size; each batch goes out as one SQS message via the outbox.
+    // Returns the ids of the outbox messages written alongside the job.
+    async fn create_bulk_job(
+        &self,
+        job_id: &str,
+        typ: EmailSendJobType,
+        param: &str,
+        to_email_addresses: &[String],
+        batch_size: i32,
+    ) -> anyhow::Result<Vec<String>> {
+        if batch_size < 1 {
+            anyhow::bail!("invalid batch size");
+        }
+
+        let now = self.system_clock.now();
+        let email_address_count = to_email_addresses.len() as i32;
+        let batch_count = (email_address_count + batch_size - 1) / batch_size;
+
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO email_send_jobs (id, batch_count, email_address_count, type, param, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6);
+        "#,
+        )
+        .bind(job_id)
+        .bind(batch_count)
+        .bind(email_address_count)
+        .bind(typ)
+        .bind(param)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO email_send_job_batches (job_id, batch_id, status, created_at, updated_at)
+    SELECT $1, batch_id, $3, $4, $4
+        FROM generate_series(0, $2 - 1) AS batch_id;
+        "#,
+        )
+        .bind(job_id)
+        .bind(batch_count)
+        .bind(EmailSendJobBatchStatus::Waiting)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+INSERT INTO email_send_job_batch_details (job_id, batch_id, email_address, retry_count, status, created_at, updated_at)
+    SELECT $1, (t.ordinality - 1) / $3, t.email_address, 0, $4, $5, $5
+        FROM UNNEST($2::VARCHAR[]) WITH ORDINALITY AS t(email_address, ordinality);
+        "#,
+        )
+        .bind(job_id)
+        .bind(to_email_addresses)
+        .bind(batch_size)
+        .bind(EmailSendJobBatchDetailStatus::Waiting)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        let outbox_ids = Self::enqueue_batches(&mut tx, job_id, &(0..batch_count).collect::<Vec<_>>(), &now).await?;
+
+        tx.commit().await?;
+
+        Ok(outbox_ids)
+    }
+
+    async fn create_single_job(&self, job_id: &str, typ: EmailSendJobType, param: &str, to_email_address: &str) -> anyhow::Result<Vec<String>> {
         let now = self.system_clock.now();
 
         let mut tx = self.db.begin().await?;
@@ -28,8 +167,8 @@ INSERT INTO email_send_jobs (id, batch_count, email_address_count, type, param,
         .bind(job_id)
         .bind(1)
         .bind(1)
-        .bind(EmailSendJobType::EmailConfirm)
-        .bind(&serde_json::to_string(param).unwrap())
+        .bind(typ)
+        .bind(param)
         .bind(now)
         .execute(&mut tx)
         .await?;
@@ -42,7 +181,7 @@ INSERT INTO email_send_job_batches (job_id, batch_id, status, created_at, update
         )
         .bind(job_id)
         .bind(0)
-        .bind(EmailSendJobBatchStatus::Preparing)
+        .bind(EmailSendJobBatchStatus::Waiting)
         .bind(now)
         .bind(now)
         .execute(&mut tx)
@@ -56,17 +195,38 @@ INSERT INTO email_send_job_batch_details (job_id, batch_id, email_address, retry
         )
         .bind(job_id)
         .bind(0)
-        .bind(param.to_email_address.as_str())
+        .bind(to_email_address)
         .bind(0)
-        .bind(EmailSendJobBatchDetailStatus::Preparing)
+        .bind(EmailSendJobBatchDetailStatus::Waiting)
         .bind(now)
         .bind(now)
         .execute(&mut tx)
         .await?;
 
+        let outbox_ids = Self::enqueue_batches(&mut tx, job_id, &[0], &now).await?;
+
         tx.commit().await?;
 
-        Ok(())
+        Ok(outbox_ids)
+    }
+
+    async fn enqueue_batches(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        job_id: &str,
+        batch_ids: &[i32],
+        now: &DateTime<Utc>,
+    ) -> anyhow::Result<Vec<String>> {
+        let message_bodies = batch_ids
+            .iter()
+            .map(|batch_id| {
+                serde_json::to_string(&EmailSendJobBatchSqsMessage {
+                    job_id: job_id.to_string(),
+                    batch_id: *batch_id,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        OutboxRepository::enqueue(tx, EMAIL_SEND_QUEUE_URL, &message_bodies, now).await
     }
 
     pub async fn get_job(&self, id: &str) -> anyhow::Result<EmailSendJob> {
@@ -84,6 +244,21 @@ SELECT *
         Ok(res)
     }
 
+    pub async fn find_job(&self, id: &str) -> anyhow::Result<Option<EmailSendJob>> {
+        let res: Option<EmailSendJob> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_jobs
+    WHERE id = $1
+"#,
+        )
+        .bind(id)
+        .fetch_optional(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
     pub async fn get_job_batches(&self, job_id: &str) -> anyhow::Result<Vec<EmailSendJobBatch>> {
         let res: Vec<EmailSendJobBatch> = sqlx::query_as(
             r#"
@@ -99,6 +274,46 @@ SELECT *
         Ok(res)
     }
 
+    pub async fn get_job_batch(&self, job_id: &str, batch_id: i32) -> anyhow::Result<EmailSendJobBatch> {
+        let res: EmailSendJobBatch = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_job_batches
+    WHERE job_id = $1 AND batch_id = $2
+"#,
+        )
+        .bind(job_id)
+        .bind(batch_id)
+        .fetch_one(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn get_job_batch_progresses(&self, job_id: &str) -> anyhow::Result<Vec<EmailSendJobBatchProgress>> {
+        let res: Vec<EmailSendJobBatchProgress> = sqlx::query_as(
+            r#"
+SELECT b.batch_id, b.status, b.updated_at,
+        COUNT(d.email_address) AS total_count,
+        COUNT(d.email_address) FILTER (WHERE d.status IN ('Preparing', 'Waiting', 'Processing')) AS pending_count,
+        COUNT(d.email_address) FILTER (WHERE d.status = 'Completed') AS completed_count,
+        COUNT(d.email_address) FILTER (WHERE d.status = 'Rejected') AS rejected_count,
+        COUNT(d.email_address) FILTER (WHERE d.status = 'Failed') AS failed_count,
+        COUNT(d.email_address) FILTER (WHERE d.status = 'Cancelled') AS cancelled_count
+    FROM email_send_job_batches b
+    LEFT JOIN email_send_job_batch_details d ON b.job_id = d.job_id AND b.batch_id = d.batch_id
+    WHERE b.job_id = $1
+    GROUP BY b.batch_id, b.status, b.updated_at
+    ORDER BY b.batch_id
+"#,
+        )
+        .bind(job_id)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
     pub async fn get_job_batch_details(&self, job_id: &str, batch_id: i32) -> anyhow::Result<Vec<EmailSendJobBatchDetail>> {
         let res: Vec<EmailSendJobBatchDetail> = sqlx::query_as(
             r#"
@@ -115,18 +330,19 @@ SELECT *
         Ok(res)
     }
 
-    pub async fn update_status_to_waiting(&self, job_id: &str) -> anyhow::Result<()> {
+    pub async fn update_status_to_processing(&self, job_id: &str, batch_id: i32, email_address: &str) -> anyhow::Result<()> {
         let mut tx = self.db.begin().await?;
         let now = self.system_clock.now();
 
         let res = sqlx::query(
             r#"
-UPDATE email_send_job_batches
-    SET status = 'Waiting', updated_at = $2
-    WHERE job_id = $1 AND status = 'Preparing'
+UPDATE email_send_job_batch_details
+    SET status = 'Processing', updated_at = $3
+    WHERE job_id = $1 AND email_address = $2 AND status = 'Waiting'
 "#,
         )
         .bind(job_id)
+        .bind(email_address)
         .bind(now)
         .execute(&mut tx)
         .await?;
@@ -135,40 +351,247 @@ UPDATE email_send_job_batches
             anyhow::bail!("no rows affected");
         }
 
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batches
+    SET status = (
+        SELECT CASE WHEN COUNT(1) > 0 THEN 'Waiting' ELSE 'Processing' END
+            FROM email_send_job_batch_details
+            WHERE job_id = $1 AND batch_id = $2 AND status = 'Waiting'
+    ), updated_at = $3
+    WHERE job_id = $1 AND batch_id = $2 AND status = 'Waiting'
+"#,
+        )
+        .bind(job_id)
+        .bind(batch_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Starts or continues a bulk batch; returns false when it is paused, cancelled or already finished.
+    pub async fn update_batch_status_to_processing(&self, job_id: &str, batch_id: i32) -> anyhow::Result<bool> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE email_send_job_batches
+    SET status = 'Processing', updated_at = $3
+    WHERE job_id = $1 AND batch_id = $2 AND status IN ('Waiting', 'Processing')
+"#,
+        )
+        .bind(job_id)
+        .bind(batch_id)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    // Claims one recipient of a bulk batch while the batch is still Processing, so a pause or cancel takes effect
+    // without reading the batch per recipient. Fails when another invocation already took the recipient, too.
+    pub async fn try_update_detail_status_to_processing(&self, job_id: &str, batch_id: i32, email_address: &str) -> anyhow::Result<bool> {
+        let now = self.system_clock.now();
+
         let res = sqlx::query(
             r#"
 UPDATE email_send_job_batch_details
+    SET status = 'Processing', updated_at = $4
+    WHERE job_id = $1 AND batch_id = $2 AND email_address = $3 AND status = 'Waiting'
+        AND EXISTS (
+            SELECT 1
+                FROM email_send_job_batches
+                WHERE job_id = $1 AND batch_id = $2 AND status = 'Processing'
+        )
+"#,
+        )
+        .bind(job_id)
+        .bind(batch_id)
+        .bind(email_address)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    // Batches already running stop before their next recipient.
+    pub async fn pause_job(&self, job_id: &str) -> anyhow::Result<u64> {
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE email_send_job_batches
+    SET status = 'Paused', updated_at = $2
+    WHERE job_id = $1 AND status IN ('Waiting', 'Processing')
+"#,
+        )
+        .bind(job_id)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // Resumed batches are enqueued again in the same transaction; returns the ids of those outbox messages.
+    // Recipients left Processing by an invocation that died are sent again, so pausing and resuming recovers
+    // a crashed batch.
+    pub async fn resume_job(&self, job_id: &str) -> anyhow::Result<Vec<String>> {
+        let now = self.system_clock.now();
+        let stale_before = now - Duration::minutes(STALE_PROCESSING_MINUTES);
+
+        let mut tx = self.db.begin().await?;
+
+        let res: Vec<(i32,)> = sqlx::query_as(
+            r#"
+UPDATE email_send_job_batches
     SET status = 'Waiting', updated_at = $2
-    WHERE job_id = $1 AND status = 'Preparing'
+    WHERE job_id = $1 AND status = 'Paused'
+    RETURNING batch_id
 "#,
         )
         .bind(job_id)
         .bind(now)
+        .fetch_all(&mut tx)
+        .await?;
+
+        let batch_ids: Vec<i32> = res.into_iter().map(|(batch_id,)| batch_id).collect();
+
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batch_details
+    SET status = 'Waiting', updated_at = $3
+    WHERE job_id = $1 AND batch_id = ANY($2) AND status = 'Processing' AND updated_at < $4
+"#,
+        )
+        .bind(job_id)
+        .bind(&batch_ids)
+        .bind(now)
+        .bind(stale_before)
         .execute(&mut tx)
         .await?;
+        let outbox_ids = Self::enqueue_batches(&mut tx, job_id, &batch_ids, &now).await?;
 
-        if res.rows_affected() < 1 {
-            anyhow::bail!("no rows affected");
-        }
+        tx.commit().await?;
+
+        Ok(outbox_ids)
+    }
+
+    // Recipients being sent to right now still finish; everyone else in the job is cancelled.
+    pub async fn cancel_job(&self, job_id: &str) -> anyhow::Result<u64> {
+        let mut tx = self.db.begin().await?;
+        let now = self.system_clock.now();
+
+        let res = sqlx::query(
+            r#"
+UPDATE email_send_job_batches
+    SET status = 'Cancelled', updated_at = $2
+    WHERE job_id = $1 AND status IN ('Preparing', 'Waiting', 'Processing', 'Paused')
+"#,
+        )
+        .bind(job_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batch_details
+    SET status = 'Cancelled', updated_at = $2
+    WHERE job_id = $1 AND status IN ('Preparing', 'Waiting')
+"#,
+        )
+        .bind(job_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
 
         tx.commit().await?;
 
-        Ok(())
+        Ok(res.rows_affected())
     }
 
-    pub async fn update_status_to_processing(&self, job_id: &str, batch_id: i32, email_address: &str) -> anyhow::Result<()> {
+    pub async fn update_status_to_completed(&self, job_id: &str, batch_id: i32, email_address: &str, message_id: &str) -> anyhow::Result<()> {
+        self.finish_detail(
+            job_id,
+            batch_id,
+            email_address,
+            EmailSendJobBatchDetailStatus::Completed,
+            Some(message_id),
+            None,
+            None,
+        )
+        .await
+    }
+
+    pub async fn update_status_to_failed(
+        &self,
+        job_id: &str,
+        batch_id: i32,
+        email_address: &str,
+        failed_reason: &str,
+        transient: bool,
+    ) -> anyhow::Result<()> {
+        self.finish_detail(
+            job_id,
+            batch_id,
+            email_address,
+            EmailSendJobBatchDetailStatus::Failed,
+            None,
+            Some(failed_reason),
+            Some(transient),
+        )
+        .await
+    }
+
+    pub async fn update_status_to_rejected(&self, job_id: &str, batch_id: i32, email_address: &str, reason: &str) -> anyhow::Result<()> {
+        self.finish_detail(
+            job_id,
+            batch_id,
+            email_address,
+            EmailSendJobBatchDetailStatus::Rejected,
+            None,
+            Some(reason),
+            None,
+        )
+        .await
+    }
+
+    async fn finish_detail(
+        &self,
+        job_id: &str,
+        batch_id: i32,
+        email_address: &str,
+        status: EmailSendJobBatchDetailStatus,
+        message_id: Option<&str>,
+        failed_reason: Option<&str>,
+        attempt_transient: Option<bool>,
+    ) -> anyhow::Result<()> {
         let mut tx = self.db.begin().await?;
         let now = self.system_clock.now();
 
+        if let (Some(transient), Some(failed_reason)) = (attempt_transient, failed_reason) {
+            Self::create_attempt(&mut tx, job_id, email_address, transient, failed_reason, &now).await?;
+        }
+
         let res = sqlx::query(
             r#"
 UPDATE email_send_job_batch_details
-    SET status = 'Processing', updated_at = $3
-    WHERE job_id = $1 AND email_address = $2 AND status = 'Waiting'
+    SET status = $3, message_id = $4, failed_reason = $5, updated_at = $6
+    WHERE job_id = $1 AND email_address = $2 AND status = 'Processing'
 "#,
         )
         .bind(job_id)
         .bind(email_address)
+        .bind(status)
+        .bind(message_id)
+        .bind(failed_reason)
         .bind(now)
         .execute(&mut tx)
         .await?;
@@ -177,25 +600,346 @@ UPDATE email_send_job_batch_details
             anyhow::bail!("no rows affected");
         }
 
+        Self::finish_batch(&mut tx, job_id, batch_id, &now).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // Puts a detail back to Waiting for another delivery, or returns None once max_retry_count is used up.
+    pub async fn update_status_to_retrying(
+        &self,
+        job_id: &str,
+        batch_id: i32,
+        email_address: &str,
+        failed_reason: &str,
+        max_retry_count: i32,
+    ) -> anyhow::Result<Option<i32>> {
+        let mut tx = self.db.begin().await?;
+        let now = self.system_clock.now();
+
+        let retry_count: Option<i32> = sqlx::query_scalar(
+            r#"
+UPDATE email_send_job_batch_details
+    SET status = 'Waiting', retry_count = retry_count + 1, failed_reason = $3, updated_at = $4
+    WHERE job_id = $1 AND email_address = $2 AND status = 'Processing' AND retry_count < $5
+    RETURNING retry_count
+"#,
+        )
+        .bind(job_id)
+        .bind(email_address)
+        .bind(failed_reason)
+        .bind(now)
+        .bind(max_retry_count)
+        .fetch_optional(&mut tx)
+        .await?;
+
+        let Some(retry_count) = retry_count else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+INSERT INTO email_send_job_batch_detail_attempts (job_id, email_address, retry_count, transient, failed_reason, created_at)
+    VALUES ($1, $2, $3, TRUE, $4, $5);
+"#,
+        )
+        .bind(job_id)
+        .bind(email_address)
+        .bind(retry_count - 1)
+        .bind(failed_reason)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batches
+    SET status = 'Waiting', updated_at = $3
+    WHERE job_id = $1 AND batch_id = $2 AND status = 'Processing'
+"#,
+        )
+        .bind(job_id)
+        .bind(batch_id)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(retry_count))
+    }
+
+    async fn create_attempt(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        job_id: &str,
+        email_address: &str,
+        transient: bool,
+        failed_reason: &str,
+        now: &DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+INSERT INTO email_send_job_batch_detail_attempts (job_id, email_address, retry_count, transient, failed_reason, created_at)
+    SELECT job_id, email_address, retry_count, $3, $4, $5
+        FROM email_send_job_batch_details
+        WHERE job_id = $1 AND email_address = $2 AND status = 'Processing'
+    ON CONFLICT DO NOTHING;
+"#,
+        )
+        .bind(job_id)
+        .bind(email_address)
+        .bind(transient)
+        .bind(failed_reason)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_job_batch_detail_attempts(&self, job_id: &str, email_address: &str) -> anyhow::Result<Vec<EmailSendJobBatchDetailAttempt>> {
+        let res: Vec<EmailSendJobBatchDetailAttempt> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_job_batch_detail_attempts
+    WHERE job_id = $1 AND email_address = $2
+    ORDER BY retry_count
+"#,
+        )
+        .bind(job_id)
+        .bind(email_address)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    // Used when a batch can't be processed at all; details that already finished keep their status.
+    pub async fn update_unfinished_status_to_failed(&self, job_id: &str, batch_id: i32, failed_reason: &str) -> anyhow::Result<()> {
+        let mut tx = self.db.begin().await?;
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batch_details
+    SET status = 'Failed', failed_reason = $3, updated_at = $4
+    WHERE job_id = $1 AND batch_id = $2 AND status IN ('Waiting', 'Processing')
+"#,
+        )
+        .bind(job_id)
+        .bind(batch_id)
+        .bind(failed_reason)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+
+        Self::finish_batch(&mut tx, job_id, batch_id, &now).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // A batch is finished once none of its details is pending, and fails only when every detail failed.
+    async fn finish_batch(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, job_id: &str, batch_id: i32, now: &DateTime<Utc>) -> anyhow::Result<()> {
         sqlx::query(
             r#"
 UPDATE email_send_job_batches
     SET status = (
-        SELECT CASE WHEN COUNT(1) > 0 THEN 'Waiting' ELSE 'Processing' END
+        SELECT CASE WHEN COUNT(1) FILTER (WHERE status <> 'Failed') > 0 THEN 'Completed' ELSE 'Failed' END
             FROM email_send_job_batch_details
-            WHERE job_id = $1 AND batch_id = $2 AND status = 'Waiting'
+            WHERE job_id = $1 AND batch_id = $2
     ), updated_at = $3
-    WHERE job_id = $1 AND batch_id = $2 AND status = 'Waiting'
+    WHERE job_id = $1 AND batch_id = $2 AND status IN ('Waiting', 'Processing')
+        AND NOT EXISTS (
+            SELECT 1
+                FROM email_send_job_batch_details
+                WHERE job_id = $1 AND batch_id = $2 AND status IN ('Preparing', 'Waiting', 'Processing')
+        )
 "#,
         )
         .bind(job_id)
         .bind(batch_id)
         .bind(now)
-        .execute(&mut tx)
+        .execute(&mut *tx)
         .await?;
 
-        tx.commit().await?;
+        Ok(())
+    }
+
+    // Feedback for mail sent outside of a job matches no row, which isn't an error.
+    pub async fn update_status_by_message_id(
+        &self,
+        message_id: &str,
+        status: EmailSendJobBatchDetailStatus,
+        failed_reason: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+UPDATE email_send_job_batch_details
+    SET status = $2, failed_reason = $3, updated_at = $4
+    WHERE message_id = $1
+"#,
+        )
+        .bind(message_id)
+        .bind(status)
+        .bind(failed_reason)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await?;
 
         Ok(())
     }
+
+    // Rows are keyed by the event time SES reports, so a redelivered notification is recorded once.
+    pub async fn create_log(
+        &self,
+        message_id: &str,
+        email_address: &str,
+        event_type: EmailSendLogEventType,
+        event_detail: Option<&str>,
+        created_at: &DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+INSERT INTO email_send_logs (message_id, email_address, event_type, event_detail, created_at)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT DO NOTHING;
+"#,
+        )
+        .bind(message_id)
+        .bind(email_address)
+        .bind(event_type)
+        .bind(event_detail)
+        .bind(created_at)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_logs(&self, message_id: &str) -> anyhow::Result<Vec<EmailSendLog>> {
+        let res: Vec<EmailSendLog> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_logs
+    WHERE message_id = $1
+    ORDER BY created_at
+"#,
+        )
+        .bind(message_id)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn create_blocked_address(
+        &self,
+        email_address: &str,
+        reason: &str,
+        expires_at: Option<&DateTime<Utc>>,
+        created_at: &DateTime<Utc>,
+    ) -> anyhow::Result<Option<EmailSendBlockedAddress>> {
+        let res: Option<EmailSendBlockedAddress> = sqlx::query_as(
+            r#"
+INSERT INTO email_send_blocked_addresses (email_address, reason, expires_at, created_at)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT DO NOTHING
+    RETURNING *;
+"#,
+        )
+        .bind(email_address)
+        .bind(reason)
+        .bind(expires_at)
+        .bind(created_at)
+        .fetch_optional(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn get_blocked_addresses(&self, email_address: &str) -> anyhow::Result<Vec<EmailSendBlockedAddress>> {
+        let res: Vec<EmailSendBlockedAddress> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_blocked_addresses
+    WHERE LOWER(email_address) = LOWER($1)
+    ORDER BY created_at
+"#,
+        )
+        .bind(email_address)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    // An address stays suppressed while any of its entries is unexpired; the longest-lived one is returned.
+    pub async fn get_active_blocked_address(&self, email_address: &str) -> anyhow::Result<Option<EmailSendBlockedAddress>> {
+        let now = self.system_clock.now();
+
+        let res: Option<EmailSendBlockedAddress> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_blocked_addresses
+    WHERE LOWER(email_address) = LOWER($1) AND (expires_at IS NULL OR expires_at > $2)
+    ORDER BY expires_at DESC NULLS FIRST
+    LIMIT 1
+"#,
+        )
+        .bind(email_address)
+        .bind(now)
+        .fetch_optional(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn get_active_blocked_addresses(
+        &self,
+        email_address: Option<&str>,
+        before: Option<&NaiveDateTime>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<EmailSendBlockedAddress>> {
+        let now = self.system_clock.now();
+
+        let res: Vec<EmailSendBlockedAddress> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM email_send_blocked_addresses
+    WHERE (expires_at IS NULL OR expires_at > $1)
+        AND ($2::VARCHAR IS NULL OR LOWER(email_address) = LOWER($2))
+        AND ($3::TIMESTAMP IS NULL OR created_at < $3)
+    ORDER BY created_at DESC
+    LIMIT $4
+"#,
+        )
+        .bind(now)
+        .bind(email_address)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn delete_blocked_addresses(&self, email_address: &str) -> anyhow::Result<u64> {
+        let res = sqlx::query(
+            r#"
+DELETE FROM email_send_blocked_addresses
+    WHERE LOWER(email_address) = LOWER($1)
+"#,
+        )
+        .bind(email_address)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(res.rows_affected())
+    }
 }
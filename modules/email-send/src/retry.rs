@@ -0,0 +1,47 @@
+const MAX_RETRY_DELAY_SECONDS: i32 = 900;
+
+// SQS caps a message delay at 15 minutes, so later retries all wait the maximum.
+pub fn retry_delay_seconds(retry_count: i32, base_delay_seconds: i32) -> i32 {
+    let factor = 2_i32.saturating_pow(retry_count.saturating_sub(1).max(0) as u32);
+    base_delay_seconds.saturating_mul(factor).min(MAX_RETRY_DELAY_SECONDS)
+}
+
+// Transport errors are only reachable through anyhow here, so throttling and service-side
+// failures are recognised by name; anything else is treated as permanent.
+pub fn is_transient_error(e: &anyhow::Error) -> bool {
+    const TRANSIENT_ERRORS: [&str; 9] = [
+        "TooManyRequests",
+        "LimitExceeded",
+        "Throttling",
+        "ServiceUnavailable",
+        "InternalFailure",
+        "DispatchFailure",
+        "TimeoutError",
+        "ResponseError",
+        "Transient",
+    ];
+
+    let text = format!("{:?}", e);
+    TRANSIENT_ERRORS.iter().any(|n| text.contains(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        assert_eq!(retry_delay_seconds(1, 30), 30);
+        assert_eq!(retry_delay_seconds(2, 30), 60);
+        assert_eq!(retry_delay_seconds(3, 30), 120);
+        assert_eq!(retry_delay_seconds(10, 30), 900);
+        assert_eq!(retry_delay_seconds(100, 30), 900);
+
+        assert!(is_transient_error(&anyhow::anyhow!(
+            "TooManyRequestsException: Maximum sending rate exceeded"
+        )));
+        assert!(is_transient_error(&anyhow::anyhow!("DispatchFailure(ConnectorError)")));
+        assert!(is_transient_error(&anyhow::anyhow!("Error {{ kind: Transient(Code {{ 421 }}) }}")));
+        assert!(!is_transient_error(&anyhow::anyhow!("MessageRejected: Email address is not verified")));
+    }
+}
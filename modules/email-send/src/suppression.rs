@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use core_base::clock::SystemClock;
+
+use opxs_base::AppError;
+
+use super::{EmailSendBlockedAddress, EmailSendJobRepository};
+
+const MAX_LIMIT: i64 = 200;
+
+pub struct EmailSuppressionList {
+    pub email_send_job_repository: Arc<EmailSendJobRepository>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl EmailSuppressionList {
+    pub async fn get_entries(
+        &self,
+        email_address: Option<&str>,
+        before: Option<&NaiveDateTime>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<EmailSendBlockedAddress>> {
+        self.email_send_job_repository
+            .get_active_blocked_addresses(email_address, before, limit.clamp(1, MAX_LIMIT))
+            .await
+    }
+
+    // Entries without expires_at are permanent, like the ones recorded from hard bounces and complaints.
+    pub async fn add(&self, email_address: &str, reason: &str, expires_at: Option<&DateTime<Utc>>) -> Result<EmailSendBlockedAddress, AppError> {
+        let now = self.system_clock.now();
+        self.email_send_job_repository
+            .create_blocked_address(email_address, reason, expires_at, &now)
+            .await?
+            .ok_or(AppError::Conflict(anyhow::anyhow!("entry already exists")))
+    }
+
+    // Removes every entry for the address, so sending resumes immediately.
+    pub async fn remove(&self, email_address: &str) -> anyhow::Result<bool> {
+        Ok(self.email_send_job_repository.delete_blocked_addresses(email_address).await? > 0)
+    }
+}
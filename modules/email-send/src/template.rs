@@ -0,0 +1,4 @@
+mod catalog;
+mod engine;
+
+pub use engine::*;
@@ -0,0 +1,291 @@
+use super::engine::{EmailTemplate, EmailTemplateVariant};
+
+pub const TEMPLATES: &[EmailTemplate] = &[
+    EmailTemplate {
+        name: "email_confirm",
+        variants: &[
+            EmailTemplateVariant {
+                locale: "ja",
+                subject: "Opxs: メールアドレスの確認をお願いします",
+                text_body: "\
+こんにちは、{{user_name}}様。
+
+Opxs へのご登録ありがとうございます。
+
+以下のリンクをクリックして、メールアドレスの確認を完了してください。
+
+{{email_confirm_url}}
+
+このメールに心当たりがない場合、またはご自身で opxs に登録を行っていない場合は、このメールを無視してください。
+
+ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。
+
+ありがとうございます。
+
+Opxs サポートチーム",
+                html_body: r#"<p>こんにちは、{{user_name}}様。</p>
+<p>Opxs へのご登録ありがとうございます。</p>
+<p>以下のリンクをクリックして、メールアドレスの確認を完了してください。</p>
+<p><a href="{{email_confirm_url}}">メールアドレスを確認する</a></p>
+<p>このメールに心当たりがない場合、またはご自身で opxs に登録を行っていない場合は、このメールを無視してください。</p>
+<p>ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。</p>
+<p>ありがとうございます。<br>Opxs サポートチーム</p>"#,
+            },
+            EmailTemplateVariant {
+                locale: "en",
+                subject: "Opxs: Please confirm your email address",
+                text_body: "\
+Hello {{user_name}},
+
+Thank you for signing up for Opxs.
+
+Please click the link below to confirm your email address.
+
+{{email_confirm_url}}
+
+If you did not sign up for Opxs, please ignore this email.
+
+If you have any questions, feel free to contact our support.
+
+Thank you,
+
+The Opxs Support Team",
+                html_body: r#"<p>Hello {{user_name}},</p>
+<p>Thank you for signing up for Opxs.</p>
+<p>Please click the link below to confirm your email address.</p>
+<p><a href="{{email_confirm_url}}">Confirm email address</a></p>
+<p>If you did not sign up for Opxs, please ignore this email.</p>
+<p>If you have any questions, feel free to contact our support.</p>
+<p>Thank you,<br>The Opxs Support Team</p>"#,
+            },
+        ],
+    },
+    EmailTemplate {
+        name: "invitation",
+        variants: &[
+            EmailTemplateVariant {
+                locale: "ja",
+                subject: "Opxs: 招待が届いています",
+                text_body: "\
+こんにちは。
+
+{{inviter_name}}様から Opxs への招待が届いています。
+
+以下のリンクから登録を完了してください。
+
+{{invitation_url}}
+
+このメールに心当たりがない場合は、このメールを無視してください。
+
+ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。
+
+ありがとうございます。
+
+Opxs サポートチーム",
+                html_body: r#"<p>こんにちは。</p>
+<p>{{inviter_name}}様から Opxs への招待が届いています。</p>
+<p>以下のリンクから登録を完了してください。</p>
+<p><a href="{{invitation_url}}">招待を受ける</a></p>
+<p>このメールに心当たりがない場合は、このメールを無視してください。</p>
+<p>ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。</p>
+<p>ありがとうございます。<br>Opxs サポートチーム</p>"#,
+            },
+            EmailTemplateVariant {
+                locale: "en",
+                subject: "Opxs: You have been invited",
+                text_body: "\
+Hello,
+
+{{inviter_name}} has invited you to Opxs.
+
+Please complete your registration from the link below.
+
+{{invitation_url}}
+
+If you were not expecting this invitation, please ignore this email.
+
+If you have any questions, feel free to contact our support.
+
+Thank you,
+
+The Opxs Support Team",
+                html_body: r#"<p>Hello,</p>
+<p>{{inviter_name}} has invited you to Opxs.</p>
+<p>Please complete your registration from the link below.</p>
+<p><a href="{{invitation_url}}">Accept the invitation</a></p>
+<p>If you were not expecting this invitation, please ignore this email.</p>
+<p>If you have any questions, feel free to contact our support.</p>
+<p>Thank you,<br>The Opxs Support Team</p>"#,
+            },
+        ],
+    },
+    EmailTemplate {
+        name: "account_exists",
+        variants: &[
+            EmailTemplateVariant {
+                locale: "ja",
+                subject: "Opxs: このメールアドレスは登録済みです",
+                text_body: "\
+こんにちは。
+
+このメールアドレスで Opxs への登録が試みられましたが、すでにアカウントが存在します。
+
+以下のリンクからログインしてください。
+
+{{login_url}}
+
+ご自身で登録を行っていない場合は、このメールを無視してください。アカウントに変更は加えられていません。
+
+ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。
+
+ありがとうございます。
+
+Opxs サポートチーム",
+                html_body: r#"<p>こんにちは。</p>
+<p>このメールアドレスで Opxs への登録が試みられましたが、すでにアカウントが存在します。</p>
+<p>以下のリンクからログインしてください。</p>
+<p><a href="{{login_url}}">ログインする</a></p>
+<p>ご自身で登録を行っていない場合は、このメールを無視してください。アカウントに変更は加えられていません。</p>
+<p>ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。</p>
+<p>ありがとうございます。<br>Opxs サポートチーム</p>"#,
+            },
+            EmailTemplateVariant {
+                locale: "en",
+                subject: "Opxs: This email address is already registered",
+                text_body: "\
+Hello,
+
+Someone tried to sign up for Opxs with this email address, but an account already exists.
+
+Please log in from the link below.
+
+{{login_url}}
+
+If this wasn't you, please ignore this email. No changes have been made to your account.
+
+If you have any questions, feel free to contact our support.
+
+Thank you,
+
+The Opxs Support Team",
+                html_body: r#"<p>Hello,</p>
+<p>Someone tried to sign up for Opxs with this email address, but an account already exists.</p>
+<p>Please log in from the link below.</p>
+<p><a href="{{login_url}}">Log in</a></p>
+<p>If this wasn't you, please ignore this email. No changes have been made to your account.</p>
+<p>If you have any questions, feel free to contact our support.</p>
+<p>Thank you,<br>The Opxs Support Team</p>"#,
+            },
+        ],
+    },
+    EmailTemplate {
+        name: "new_sign_in",
+        variants: &[
+            EmailTemplateVariant {
+                locale: "ja",
+                subject: "Opxs: 新しい端末からのログインがありました",
+                text_body: "\
+{{user_name}} 様
+
+お使いの Opxs アカウントに、これまでと異なる端末からのログインがありました。
+
+日時: {{signed_in_at}}
+IP アドレス: {{ip_address}}
+ブラウザ: {{user_agent}}
+
+ご自身によるログインであれば、対応は不要です。
+
+心当たりがない場合は、以下のリンクからこのログインのセッションを無効化し、パスワードを変更してください。
+
+{{revoke_url}}
+
+ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。
+
+ありがとうございます。
+
+Opxs サポートチーム",
+                html_body: r#"<p>{{user_name}} 様</p>
+<p>お使いの Opxs アカウントに、これまでと異なる端末からのログインがありました。</p>
+<p>日時: {{signed_in_at}}<br>IP アドレス: {{ip_address}}<br>ブラウザ: {{user_agent}}</p>
+<p>ご自身によるログインであれば、対応は不要です。</p>
+<p>心当たりがない場合は、以下のリンクからこのログインのセッションを無効化し、パスワードを変更してください。</p>
+<p><a href="{{revoke_url}}">このログインを無効化する</a></p>
+<p>ご不明点やお困りの点がございましたら、お気軽にサポートまでお問い合わせください。</p>
+<p>ありがとうございます。<br>Opxs サポートチーム</p>"#,
+            },
+            EmailTemplateVariant {
+                locale: "en",
+                subject: "Opxs: New sign-in from a new device",
+                text_body: "\
+Hello {{user_name}},
+
+Your Opxs account was signed in to from a device we haven't seen before.
+
+Time: {{signed_in_at}}
+IP address: {{ip_address}}
+Browser: {{user_agent}}
+
+If this was you, no action is needed.
+
+If it wasn't, revoke this sign-in from the link below and change your password.
+
+{{revoke_url}}
+
+If you have any questions, feel free to contact our support.
+
+Thank you,
+
+The Opxs Support Team",
+                html_body: r#"<p>Hello {{user_name}},</p>
+<p>Your Opxs account was signed in to from a device we haven't seen before.</p>
+<p>Time: {{signed_in_at}}<br>IP address: {{ip_address}}<br>Browser: {{user_agent}}</p>
+<p>If this was you, no action is needed.</p>
+<p>If it wasn't, revoke this sign-in from the link below and change your password.</p>
+<p><a href="{{revoke_url}}">Revoke this sign-in</a></p>
+<p>If you have any questions, feel free to contact our support.</p>
+<p>Thank you,<br>The Opxs Support Team</p>"#,
+            },
+        ],
+    },
+    EmailTemplate {
+        name: "announcement",
+        variants: &[
+            EmailTemplateVariant {
+                locale: "ja",
+                subject: "Opxs: {{subject}}",
+                text_body: "\
+{{body}}
+
+--
+このメールは Opxs からのお知らせの受け取りを希望された方にお送りしています。
+配信の停止は以下のページから行えます。
+
+{{settings_url}}
+
+Opxs サポートチーム",
+                html_body: r#"<p style="white-space: pre-wrap">{{body}}</p>
+<hr>
+<p>このメールは Opxs からのお知らせの受け取りを希望された方にお送りしています。<br>配信の停止は<a href="{{settings_url}}">こちら</a>から行えます。</p>
+<p>Opxs サポートチーム</p>"#,
+            },
+            EmailTemplateVariant {
+                locale: "en",
+                subject: "Opxs: {{subject}}",
+                text_body: "\
+{{body}}
+
+--
+You are receiving this email because you opted in to announcements from Opxs.
+You can unsubscribe from the page below.
+
+{{settings_url}}
+
+The Opxs Support Team",
+                html_body: r#"<p style="white-space: pre-wrap">{{body}}</p>
+<hr>
+<p>You are receiving this email because you opted in to announcements from Opxs.<br>You can unsubscribe <a href="{{settings_url}}">here</a>.</p>
+<p>The Opxs Support Team</p>"#,
+            },
+        ],
+    },
+];
@@ -0,0 +1,169 @@
+use super::catalog::TEMPLATES;
+
+pub const DEFAULT_EMAIL_LOCALE: &str = "ja";
+
+pub struct EmailTemplate {
+    pub name: &'static str,
+    pub variants: &'static [EmailTemplateVariant],
+}
+
+pub struct EmailTemplateVariant {
+    pub locale: &'static str,
+    pub subject: &'static str,
+    pub text_body: &'static str,
+    pub html_body: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+pub trait EmailTemplateParam {
+    fn template_name(&self) -> &'static str;
+    fn locale(&self) -> &str;
+    fn variables(&self) -> Vec<(&'static str, &str)>;
+}
+
+impl EmailTemplate {
+    pub fn find(name: &str) -> Option<&'static EmailTemplate> {
+        TEMPLATES.iter().find(|n| n.name == name)
+    }
+
+    // Matches "en-US" to "en", and falls back to the default locale when no variant fits.
+    pub fn variant(&self, locale: &str) -> &'static EmailTemplateVariant {
+        let locale = locale.trim().to_ascii_lowercase();
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+
+        self.variants
+            .iter()
+            .find(|n| n.locale == locale)
+            .or_else(|| self.variants.iter().find(|n| n.locale == language))
+            .or_else(|| self.variants.iter().find(|n| n.locale == DEFAULT_EMAIL_LOCALE))
+            .unwrap_or(&self.variants[0])
+    }
+}
+
+pub fn render_email<P: EmailTemplateParam>(param: &P) -> anyhow::Result<RenderedEmail> {
+    let template = EmailTemplate::find(param.template_name()).ok_or_else(|| anyhow::anyhow!("template is not found: {}", param.template_name()))?;
+    let variant = template.variant(param.locale());
+    let variables = param.variables();
+
+    Ok(RenderedEmail {
+        subject: render(variant.subject, &variables, false)?,
+        text_body: render(variant.text_body, &variables, false)?,
+        html_body: format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\">\n<body>\n{}\n</body>\n</html>\n",
+            variant.locale,
+            render(variant.html_body, &variables, true)?
+        ),
+    })
+}
+
+// Placeholders are written as {{name}}; values are HTML-escaped when rendering the HTML part.
+fn render(source: &str, variables: &[(&str, &str)], html: bool) -> anyhow::Result<String> {
+    let mut res = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        res.push_str(&rest[..start]);
+        let end = rest[start..].find("}}").ok_or_else(|| anyhow::anyhow!("unclosed placeholder"))? + start;
+        let name = rest[start + 2..end].trim();
+        let value = variables
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| anyhow::anyhow!("missing template variable: {}", name))?;
+        if html {
+            res.push_str(&escape_html(value));
+        } else {
+            res.push_str(value);
+        }
+        rest = &rest[end + 2..];
+    }
+    res.push_str(rest);
+
+    Ok(res)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AccountExistsRequestParam, AnnouncementRequestParam, EmailConfirmRequestParam, InvitationRequestParam, NewSignInRequestParam};
+
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        let param = EmailConfirmRequestParam {
+            user_name: "<b>test</b>".to_string(),
+            to_email_address: "test@example.com".to_string(),
+            from_email_address: "no-reply@example.com".to_string(),
+            email_confirm_url: "https://example.com/?a=1&b=2".to_string(),
+            locale: "en-US".to_string(),
+        };
+        let email = render_email(&param).unwrap();
+        assert_eq!(email.subject, "Opxs: Please confirm your email address");
+        assert!(email.text_body.contains("Hello <b>test</b>,"));
+        assert!(email.text_body.contains("https://example.com/?a=1&b=2"));
+        assert!(email.html_body.contains("&lt;b&gt;test&lt;/b&gt;"));
+        assert!(email.html_body.contains("https://example.com/?a=1&amp;b=2"));
+
+        // unsupported locales fall back to Japanese
+        let email = render_email(&EmailConfirmRequestParam {
+            locale: "fr".to_string(),
+            ..param.clone()
+        })
+        .unwrap();
+        assert_eq!(email.subject, "Opxs: メールアドレスの確認をお願いします");
+
+        // every variant of every template renders with its param
+        for locale in ["ja", "en"] {
+            let locale = locale.to_string();
+            render_email(&EmailConfirmRequestParam {
+                locale: locale.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+            render_email(&InvitationRequestParam {
+                locale: locale.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+            render_email(&AccountExistsRequestParam {
+                locale: locale.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+            render_email(&NewSignInRequestParam {
+                locale: locale.clone(),
+                ..Default::default()
+            })
+            .unwrap();
+            render_email(&AnnouncementRequestParam {
+                locale,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        assert!(render("{{missing}}", &[], false).is_err());
+        assert!(render("{{unclosed", &[("unclosed", "")], false).is_err());
+    }
+}
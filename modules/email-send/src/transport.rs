@@ -0,0 +1,11 @@
+mod email_transport;
+mod email_transport_mock;
+mod local;
+mod ses;
+mod smtp;
+
+pub use email_transport::*;
+pub use email_transport_mock::*;
+pub use local::*;
+pub use ses::*;
+pub use smtp::*;
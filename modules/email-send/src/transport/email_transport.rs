@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+use lettre::message::{Mailbox, MultiPart};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub to_address: String,
+    pub from_address: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+// Returns the id the message was accepted under; feedback and job details are matched on it.
+#[async_trait]
+pub trait EmailTransport {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<String>;
+}
+
+impl EmailMessage {
+    pub fn to_mime(&self, message_id: &str) -> anyhow::Result<lettre::Message> {
+        let from = self.from_address.parse::<Mailbox>()?;
+        let domain = from.email.domain().to_string();
+
+        let res = lettre::Message::builder()
+            .message_id(Some(format!("<{}@{}>", message_id, domain)))
+            .from(from)
+            .to(self.to_address.parse::<Mailbox>()?)
+            .subject(self.subject.as_str())
+            .multipart(MultiPart::alternative_plain_html(self.text_body.clone(), self.html_body.clone()))?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use super::{EmailMessage, EmailTransport};
+
+pub struct EmailTransportMock {
+    pub send_inputs: Arc<Mutex<Vec<EmailMessage>>>,
+    // Each queued error fails one send, in order; sends succeed once the queue is empty.
+    pub send_errors: Arc<Mutex<VecDeque<String>>>,
+}
+
+#[async_trait]
+impl EmailTransport for EmailTransportMock {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<String> {
+        if let Some(error) = self.send_errors.lock().unwrap().pop_front() {
+            anyhow::bail!(error);
+        }
+
+        let mut send_inputs = self.send_inputs.lock().unwrap();
+        send_inputs.push(message.clone());
+
+        Ok(format!("message-id-{}", send_inputs.len()))
+    }
+}
+
+impl EmailTransportMock {
+    #[allow(unused)]
+    pub fn new() -> Self {
+        Self {
+            send_inputs: Arc::new(Mutex::new(vec![])),
+            send_errors: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+}
+
+impl Default for EmailTransportMock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
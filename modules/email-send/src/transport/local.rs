@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use core_base::{clock::SystemClock, tsid::TsidProvider};
+
+use super::{EmailMessage, EmailTransport};
+
+#[derive(Serialize, Deserialize, Debug, sqlx::FromRow, ToSchema)]
+pub struct CapturedEmail {
+    pub id: String,
+    pub to_address: String,
+    pub from_address: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: String,
+    // The message as it would have gone over the wire, viewable as an .eml file.
+    #[serde(skip)]
+    pub raw: String,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct CapturedEmailRepository {
+    pub db: Arc<PgPool>,
+    pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
+}
+
+impl CapturedEmailRepository {
+    pub async fn create(&self, id: &str, message: &EmailMessage, raw: &str) -> anyhow::Result<()> {
+        let now = self.system_clock.now();
+
+        sqlx::query(
+            r#"
+INSERT INTO captured_emails (id, to_address, from_address, subject, text_body, html_body, raw, created_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+"#,
+        )
+        .bind(id)
+        .bind(&message.to_address)
+        .bind(&message.from_address)
+        .bind(&message.subject)
+        .bind(&message.text_body)
+        .bind(&message.html_body)
+        .bind(raw)
+        .bind(now)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_list(&self, to_address: Option<&str>, before: Option<&NaiveDateTime>, limit: i64) -> anyhow::Result<Vec<CapturedEmail>> {
+        let res: Vec<CapturedEmail> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM captured_emails
+    WHERE ($1::VARCHAR IS NULL OR LOWER(to_address) = LOWER($1))
+        AND ($2::TIMESTAMP IS NULL OR created_at < $2)
+    ORDER BY created_at DESC
+    LIMIT $3
+"#,
+        )
+        .bind(to_address)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn get(&self, id: &str) -> anyhow::Result<Option<CapturedEmail>> {
+        let res: Option<CapturedEmail> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM captured_emails
+    WHERE id = $1
+"#,
+        )
+        .bind(id)
+        .fetch_optional(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+}
+
+pub struct LocalEmailTransport {
+    pub captured_email_repository: Arc<CapturedEmailRepository>,
+    pub tsid_provider: Arc<dyn TsidProvider + Send + Sync>,
+}
+
+#[async_trait]
+impl EmailTransport for LocalEmailTransport {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<String> {
+        let id = self.tsid_provider.gen().to_string();
+        let raw = String::from_utf8(message.to_mime(&id)?.formatted())?;
+        self.captured_email_repository.create(&id, message, &raw).await?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core_base::{clock::SystemClockUtc, random_bytes::RandomBytesProviderImpl, tsid::TsidProviderImpl};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simple_test() {
+        let docker = testcontainers::clients::Cli::default();
+        let fixture = opxs_base::setup_postgres(&docker, "15.1").await;
+
+        let captured_email_repository = Arc::new(CapturedEmailRepository {
+            db: fixture.db.clone(),
+            system_clock: Arc::new(SystemClockUtc {}),
+        });
+        let transport = LocalEmailTransport {
+            captured_email_repository: captured_email_repository.clone(),
+            tsid_provider: Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16)),
+        };
+
+        let message = EmailMessage {
+            to_address: "test@example.com".to_string(),
+            from_address: "Opxs <no-reply@example.com>".to_string(),
+            subject: "test subject".to_string(),
+            text_body: "test body".to_string(),
+            html_body: "<p>test body</p>".to_string(),
+        };
+        let id = transport.send(&message).await.unwrap();
+
+        let captured = captured_email_repository.get(&id).await.unwrap().unwrap();
+        assert_eq!(captured.to_address, "test@example.com");
+        assert_eq!(captured.subject, "test subject");
+        assert!(captured.raw.contains("multipart/alternative"));
+        assert!(captured.raw.contains(&format!("<{}@example.com>", id)));
+
+        let list = captured_email_repository.get_list(Some("TEST@example.com"), None, 10).await.unwrap();
+        assert_eq!(list.len(), 1);
+        assert!(captured_email_repository
+            .get_list(Some("other@example.com"), None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
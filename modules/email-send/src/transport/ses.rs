@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use aws_sdk_sesv2::types::{Body, Content, Destination, EmailContent, Message};
+
+use super::{EmailMessage, EmailTransport};
+
+pub struct SesEmailTransport {
+    pub client: aws_sdk_sesv2::Client,
+    pub configuration_set_name: Option<String>,
+}
+
+#[async_trait]
+impl EmailTransport for SesEmailTransport {
+    // The SES message id is what bounce, complaint and delivery notifications refer back to.
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<String> {
+        let body = Body::builder()
+            .text(utf8_content(&message.text_body))
+            .html(utf8_content(&message.html_body))
+            .build();
+        let content = EmailContent::builder()
+            .simple(Message::builder().subject(utf8_content(&message.subject)).body(body).build())
+            .build();
+
+        let res = self
+            .client
+            .send_email()
+            .from_email_address(&message.from_address)
+            .destination(Destination::builder().to_addresses(&message.to_address).build())
+            .content(content)
+            .set_configuration_set_name(self.configuration_set_name.clone())
+            .send()
+            .await?;
+
+        res.message_id()
+            .map(|n| n.to_string())
+            .ok_or_else(|| anyhow::anyhow!("message id is not found"))
+    }
+}
+
+fn utf8_content(data: &str) -> Content {
+    Content::builder().data(data).charset("UTF-8").build()
+}
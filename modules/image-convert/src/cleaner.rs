@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::{ImageConvertJobRepository, S3ObjectRemover};
+
+pub struct ImageConvertJobCleaner {
+    pub image_convert_job_repository: Arc<ImageConvertJobRepository>,
+    pub s3_object_remover: Arc<dyn S3ObjectRemover + Send + Sync>,
+}
+
+impl ImageConvertJobCleaner {
+    pub async fn clean_by_user_id(&self, user_id: &str) -> anyhow::Result<()> {
+        let job_ids = self.image_convert_job_repository.get_job_ids_by_user_id(user_id).await?;
+
+        for job_id in job_ids.iter() {
+            self.s3_object_remover.remove_object(format!("in/{}", job_id).as_str()).await?;
+            self.s3_object_remover.remove_object(format!("out/{}", job_id).as_str()).await?;
+            self.image_convert_job_repository.delete_job(job_id).await?;
+        }
+
+        info!("cleaned image convert jobs: user_id: {}, count: {}", user_id, job_ids.len());
+
+        Ok(())
+    }
+}
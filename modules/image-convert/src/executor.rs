@@ -14,7 +14,10 @@ pub struct Executor {
 impl Executor {
     pub async fn execute(&self, job_ids: &[String]) -> anyhow::Result<()> {
         for job_id in job_ids.iter() {
-            self.image_convert_job_repository.update_status_to_processing(job_id).await?;
+            if !self.image_convert_job_repository.update_status_to_processing(job_id).await? {
+                info!("skipped job that is not waiting: {}", job_id);
+                continue;
+            }
 
             let res = self.execute_one(job_id).await;
 
@@ -57,35 +60,24 @@ impl Executor {
 
 #[cfg(test)]
 mod tests {
-    use chrono::Duration;
     use core_base::{
         clock::SystemClockUtc,
         random_bytes::RandomBytesProviderImpl,
         tsid::{TsidProvider, TsidProviderImpl},
     };
-    use core_migration::postgres::PostgresMigrator;
-    use core_testkit::containers::postgres::PostgresContainer;
-    use sqlx::postgres::PgPoolOptions;
 
     use core_cloud::aws::s3::S3ClientMock;
+    use opxs_base::AppError;
 
-    use crate::{ImageConvertJobCreator, ImageConverterMock, ImageFormat};
+    use crate::{ImageConvertJobCreator, ImageConvertJobKeyMessage, ImageConverterMock, ImageFormat, IMAGE_CONVERT_QUEUE_URL};
 
     use super::*;
 
     #[tokio::test]
     async fn simple_test() {
         let docker = testcontainers::clients::Cli::default();
-        let container = PostgresContainer::new(&docker, "15.1");
-
-        let db = Arc::new(
-            PgPoolOptions::new()
-                .max_connections(100)
-                .idle_timeout(Some(Duration::minutes(15).to_std().unwrap()))
-                .connect(&container.connection_string)
-                .await
-                .unwrap(),
-        );
+        let fixture = opxs_base::setup_postgres(&docker, "15.1").await;
+        let db = fixture.db.clone();
         let system_clock = Arc::new(SystemClockUtc {});
         let tsid_provider = Arc::new(TsidProviderImpl::new(SystemClockUtc, RandomBytesProviderImpl, 16));
         let s3_client = Arc::new(S3ClientMock::new());
@@ -100,15 +92,9 @@ mod tests {
             .unwrap()
             .push_back("https://get.s3.example.com".to_string());
 
-        let migrations_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../conf/migrations");
-        let migrator = PostgresMigrator::new(&container.connection_string, migrations_path, "opxs-api", "")
-            .await
-            .unwrap();
-        migrator.migrate().await.unwrap();
-
         let image_converter = Arc::new(ImageConverterMock::new());
         let image_convert_job_repository = Arc::new(ImageConvertJobRepository {
-            db,
+            db: db.clone(),
             system_clock: system_clock.clone(),
             tsid_provider: tsid_provider.clone(),
         });
@@ -120,14 +106,14 @@ mod tests {
         };
         let job_id = tsid_provider.gen().to_string();
         let upload_url = job_creator
-            .create_image_convert_job(&job_id, "test.png", &ImageFormat::Jpg)
+            .create_image_convert_job(&job_id, None, None, "test.png", &ImageFormat::Jpg, false)
             .await
             .unwrap();
         println!("upload_url: {}", upload_url);
 
         let executor = Executor {
             image_converter: image_converter.clone(),
-            image_convert_job_repository,
+            image_convert_job_repository: image_convert_job_repository.clone(),
             s3_client: s3_client.clone(),
         };
         executor.execute(&[job_id.clone()]).await.unwrap();
@@ -141,5 +127,59 @@ mod tests {
             s3_client.put_object_inputs.lock().unwrap().first().unwrap().key,
             format!("out/{}", job_id).as_str()
         );
+
+        // the outbox fallback for a job its upload event already ran is skipped
+        let (message_body,): (String,) = sqlx::query_as("SELECT message_body FROM outbox_messages WHERE queue_url = $1")
+            .bind(IMAGE_CONVERT_QUEUE_URL)
+            .fetch_one(db.as_ref())
+            .await
+            .unwrap();
+        let message = serde_json::from_str::<ImageConvertJobKeyMessage>(&message_body).unwrap();
+        assert_eq!(message.key, format!("in/{}", job_id));
+        executor.execute(&[job_id.clone()]).await.unwrap();
+        assert_eq!(s3_client.get_object_inputs.lock().unwrap().len(), 1);
+
+        // a guest's quota is only spent on jobs that get created
+        sqlx::query(
+            r#"
+INSERT INTO users (id, name, authentication_type, role, created_at, updated_at)
+    VALUES ('guest_id', 'Guest', 'Guest', 'Guest', NOW(), NOW());
+"#,
+        )
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+INSERT INTO user_guests (user_id, device_id_hash, convert_quota, last_seen_at, created_at)
+    VALUES ('guest_id', 'device_id_hash', 1, NOW(), NOW());
+"#,
+        )
+        .execute(db.as_ref())
+        .await
+        .unwrap();
+        for _ in 0..2 {
+            s3_client
+                .gen_put_presigned_uri_outputs
+                .lock()
+                .unwrap()
+                .push_back("https://put.s3.example.com".to_string());
+        }
+        let guest_job_id = tsid_provider.gen().to_string();
+        job_creator
+            .create_image_convert_job(&guest_job_id, Some("guest_id"), None, "test.png", &ImageFormat::Jpg, true)
+            .await
+            .unwrap();
+        let rejected_job_id = tsid_provider.gen().to_string();
+        assert!(matches!(
+            job_creator
+                .create_image_convert_job(&rejected_job_id, Some("guest_id"), None, "test.png", &ImageFormat::Jpg, true)
+                .await,
+            Err(AppError::Forbidden)
+        ));
+        assert_eq!(
+            image_convert_job_repository.get_job_ids_by_user_id("guest_id").await.unwrap(),
+            vec![guest_job_id]
+        );
     }
 }
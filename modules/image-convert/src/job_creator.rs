@@ -5,10 +5,13 @@ use core_base::clock::SystemClock;
 use core_cloud::aws::s3::S3Client;
 use opxs_base::AppError;
 
-use crate::{ImageConvertFile, ImageConvertJobStatus, ImageConvertRequestParam, ImageFormat};
+use crate::{ImageConvertFile, ImageConvertJob, ImageConvertJobStatus, ImageConvertRequestParam, ImageFormat};
 
 use super::ImageConvertJobRepository;
 
+const UPLOAD_EXPIRES_IN_MINUTES: i64 = 10;
+const UPLOAD_GRACE_MINUTES: i64 = 5;
+
 pub struct ImageConvertJobCreator {
     pub image_convert_job_repository: Arc<ImageConvertJobRepository>,
     pub system_clock: Arc<dyn SystemClock<Utc> + Send + Sync>,
@@ -16,7 +19,15 @@ pub struct ImageConvertJobCreator {
 }
 
 impl ImageConvertJobCreator {
-    pub async fn create_image_convert_job(&self, job_id: &str, filename: &str, convert_format: &ImageFormat) -> Result<String, AppError> {
+    pub async fn create_image_convert_job(
+        &self,
+        job_id: &str,
+        user_id: Option<&str>,
+        organization_id: Option<&str>,
+        filename: &str,
+        convert_format: &ImageFormat,
+        charge_guest_quota: bool,
+    ) -> Result<String, AppError> {
         let filename_without_extension = Path::new(filename)
             .file_stem()
             .ok_or(anyhow::anyhow!("invalid filename"))?
@@ -37,20 +48,42 @@ impl ImageConvertJobCreator {
             input: input.clone(),
             output,
         };
-        self.image_convert_job_repository.create_image_convert_job(job_id, &param).await?;
-
         let now = self.system_clock.now();
-        let expires_in = Duration::minutes(10);
+        let expires_in = Duration::minutes(UPLOAD_EXPIRES_IN_MINUTES);
         let upload_uri = self
             .s3_client
             .gen_put_presigned_uri(format!("in/{}", job_id).as_str(), now, expires_in)
             .await?;
+        // An upload may still be in flight when the URI expires.
+        let fallback_at = now + expires_in + Duration::minutes(UPLOAD_GRACE_MINUTES);
 
-        self.image_convert_job_repository.update_status_to_waiting(job_id).await?;
+        // The upload's S3 event starts the conversion, so the job is written once, already Waiting, after the URI exists.
+        // Its outbox message is only a fallback for a lost event and isn't delivered before the upload window closes.
+        // A guest's quota is charged in the same transaction, so it is only spent on a job that was actually created.
+        let created = self
+            .image_convert_job_repository
+            .create_image_convert_job(job_id, user_id, organization_id, &param, charge_guest_quota, &fallback_at)
+            .await?;
+        if !created {
+            return Err(AppError::Forbidden);
+        }
 
         Ok(upload_uri)
     }
 
+    // Organization jobs are shared with every member; personal jobs stay visible only to their creator.
+    pub async fn get_jobs(&self, user_id: &str, organization_id: Option<&str>, limit: i64) -> Result<Vec<ImageConvertJob>, AppError> {
+        let jobs = match organization_id {
+            Some(organization_id) => {
+                self.image_convert_job_repository
+                    .get_jobs_by_organization_id(organization_id, limit)
+                    .await?
+            }
+            None => self.image_convert_job_repository.get_jobs_by_user_id(user_id, limit).await?,
+        };
+        Ok(jobs)
+    }
+
     pub async fn get_status(&self, job_id: &str) -> Result<(ImageConvertJobStatus, Option<String>), AppError> {
         let job = self.image_convert_job_repository.get_job(job_id).await?;
 
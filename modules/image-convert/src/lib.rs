@@ -1,11 +1,15 @@
+mod cleaner;
 mod converter;
 mod executor;
 mod job_creator;
 mod message;
 mod repo;
+mod storage;
 
+pub use cleaner::*;
 pub use converter::*;
 pub use executor::*;
 pub use job_creator::*;
 pub use message::*;
 pub use repo::*;
+pub use storage::*;
@@ -51,6 +51,8 @@ impl sqlx::Decode<'_, sqlx::Postgres> for ImageConvertJobStatus {
 #[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
 pub struct ImageConvertJob {
     pub id: String,
+    pub user_id: Option<String>,
+    pub organization_id: Option<String>,
     pub param: Option<String>,
     pub status: ImageConvertJobStatus,
     pub failed_reason: Option<String>,
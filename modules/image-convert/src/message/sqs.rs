@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+pub const IMAGE_CONVERT_QUEUE_URL: &str = "opxs-batch-image-convert-sqs";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ImageConvertJobSqsMessage {
     #[serde(rename = "Records")]
@@ -26,3 +28,10 @@ pub struct S3Bucket {
 pub struct S3Object {
     pub key: String,
 }
+
+// Written to the outbox with the job and delivered once the upload window has closed, so a job whose
+// upload event never arrived still ends up Completed or Failed. Same shape as a raw invocation.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImageConvertJobKeyMessage {
+    pub key: String,
+}
@@ -1,10 +1,11 @@
 use std::sync::Arc;
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use core_base::{clock::SystemClock, tsid::TsidProvider};
+use opxs_base::OutboxRepository;
 use sqlx::PgPool;
 
-use crate::{ImageConvertJob, ImageConvertJobStatus, ImageConvertRequestParam};
+use crate::{ImageConvertJob, ImageConvertJobKeyMessage, ImageConvertJobStatus, ImageConvertRequestParam, IMAGE_CONVERT_QUEUE_URL};
 
 pub struct ImageConvertJobRepository {
     pub db: Arc<PgPool>,
@@ -13,24 +14,62 @@ pub struct ImageConvertJobRepository {
 }
 
 impl ImageConvertJobRepository {
-    pub async fn create_image_convert_job(&self, job_id: &str, param: &ImageConvertRequestParam) -> anyhow::Result<()> {
+    // Returns false without writing anything when the guest quota is charged but already used up.
+    pub async fn create_image_convert_job(
+        &self,
+        job_id: &str,
+        user_id: Option<&str>,
+        organization_id: Option<&str>,
+        param: &ImageConvertRequestParam,
+        charge_guest_quota: bool,
+        fallback_at: &DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
         let now = self.system_clock.now();
 
+        let mut tx = self.db.begin().await?;
+
+        if charge_guest_quota {
+            let res = sqlx::query(
+                r#"
+UPDATE user_guests
+    SET convert_quota = convert_quota - 1, last_seen_at = $2
+    WHERE user_id = $1 AND convert_quota > 0;
+"#,
+            )
+            .bind(user_id)
+            .bind(now)
+            .execute(&mut tx)
+            .await?;
+
+            if res.rows_affected() < 1 {
+                return Ok(false);
+            }
+        }
+
         sqlx::query(
             r#"
-INSERT INTO image_convert_jobs (id, param, status, created_at, updated_at)
-    VALUES ($1, $2, $3, $4, $5);
+INSERT INTO image_convert_jobs (id, user_id, organization_id, param, status, created_at, updated_at)
+    VALUES ($1, $2, $3, $4, $5, $6, $7);
         "#,
         )
         .bind(job_id)
+        .bind(user_id)
+        .bind(organization_id)
         .bind(&serde_json::to_string(param).unwrap())
-        .bind(ImageConvertJobStatus::Preparing)
+        .bind(ImageConvertJobStatus::Waiting)
         .bind(now)
         .bind(now)
-        .execute(self.db.as_ref())
+        .execute(&mut tx)
         .await?;
 
-        Ok(())
+        let message = ImageConvertJobKeyMessage {
+            key: format!("in/{}", job_id),
+        };
+        OutboxRepository::enqueue_at(&mut tx, IMAGE_CONVERT_QUEUE_URL, &[serde_json::to_string(&message)?], &now, fallback_at).await?;
+
+        tx.commit().await?;
+
+        Ok(true)
     }
 
     pub async fn get_job(&self, id: &str) -> anyhow::Result<ImageConvertJob> {
@@ -48,22 +87,89 @@ SELECT *
         Ok(res)
     }
 
-    pub async fn update_status_to_waiting(&self, job_id: &str) -> anyhow::Result<()> {
-        self.update_status(job_id, ImageConvertJobStatus::Preparing, ImageConvertJobStatus::Waiting)
-            .await
+    pub async fn get_job_ids_by_user_id(&self, user_id: &str) -> anyhow::Result<Vec<String>> {
+        let res: Vec<(String,)> = sqlx::query_as(
+            r#"
+SELECT id
+    FROM image_convert_jobs
+    WHERE user_id = $1
+"#,
+        )
+        .bind(user_id)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res.into_iter().map(|(id,)| id).collect())
     }
 
-    pub async fn update_status_to_processing(&self, job_id: &str) -> anyhow::Result<()> {
+    pub async fn get_jobs_by_user_id(&self, user_id: &str, limit: i64) -> anyhow::Result<Vec<ImageConvertJob>> {
+        let res: Vec<ImageConvertJob> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM image_convert_jobs
+    WHERE user_id = $1 AND organization_id IS NULL
+    ORDER BY created_at DESC
+    LIMIT $2
+"#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn get_jobs_by_organization_id(&self, organization_id: &str, limit: i64) -> anyhow::Result<Vec<ImageConvertJob>> {
+        let res: Vec<ImageConvertJob> = sqlx::query_as(
+            r#"
+SELECT *
+    FROM image_convert_jobs
+    WHERE organization_id = $1
+    ORDER BY created_at DESC
+    LIMIT $2
+"#,
+        )
+        .bind(organization_id)
+        .bind(limit)
+        .fetch_all(self.db.as_ref())
+        .await?;
+
+        Ok(res)
+    }
+
+    pub async fn delete_job(&self, id: &str) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+DELETE FROM image_convert_jobs
+    WHERE id = $1
+"#,
+        )
+        .bind(id)
+        .execute(self.db.as_ref())
+        .await?;
+
+        Ok(())
+    }
+
+    // Returns false when the job is no longer Waiting, e.g. the upload event and the outbox message both arrived.
+    pub async fn update_status_to_processing(&self, job_id: &str) -> anyhow::Result<bool> {
         self.update_status(job_id, ImageConvertJobStatus::Waiting, ImageConvertJobStatus::Processing)
             .await
     }
 
     pub async fn update_status_to_completed(&self, job_id: &str) -> anyhow::Result<()> {
-        self.update_status(job_id, ImageConvertJobStatus::Processing, ImageConvertJobStatus::Completed)
-            .await
+        if !self
+            .update_status(job_id, ImageConvertJobStatus::Processing, ImageConvertJobStatus::Completed)
+            .await?
+        {
+            anyhow::bail!("no rows affected");
+        }
+
+        Ok(())
     }
 
-    async fn update_status(&self, job_id: &str, old_status: ImageConvertJobStatus, new_status: ImageConvertJobStatus) -> anyhow::Result<()> {
+    async fn update_status(&self, job_id: &str, old_status: ImageConvertJobStatus, new_status: ImageConvertJobStatus) -> anyhow::Result<bool> {
         let now = self.system_clock.now();
 
         let res = sqlx::query(
@@ -80,11 +186,7 @@ UPDATE image_convert_jobs
         .execute(self.db.as_ref())
         .await?;
 
-        if res.rows_affected() < 1 {
-            anyhow::bail!("no rows affected");
-        }
-
-        Ok(())
+        Ok(res.rows_affected() > 0)
     }
 
     pub async fn update_status_to_failed(&self, job_id: &str, failed_reason: &str) -> anyhow::Result<()> {
@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait S3ObjectRemover {
+    async fn remove_object(&self, key: &str) -> anyhow::Result<()>;
+}
+
+pub struct S3ObjectRemoverImpl {
+    pub client: aws_sdk_s3::Client,
+    pub bucket: String,
+}
+
+#[async_trait]
+impl S3ObjectRemover for S3ObjectRemoverImpl {
+    async fn remove_object(&self, key: &str) -> anyhow::Result<()> {
+        self.client.delete_object().bucket(&self.bucket).key(key).send().await?;
+        Ok(())
+    }
+}